@@ -0,0 +1,20 @@
+//! Crawls all the pages of a search and writes the entries on the standard
+//! output, one JSON document per line after a header with the schema version.
+//!
+//! ```bash
+//! cargo run --example crawl -- ubuntu > ubuntu.ndjson
+//! ```
+
+use xdcc_search::dump::{Dump, DumpFormat};
+
+#[tokio::main]
+async fn main() {
+    let query = std::env::args().nth(1).unwrap_or_else(|| "ubuntu".into());
+    let engine = xdcc_search::sunxdcc::Engine::default();
+    let mut dump = Dump::new(std::io::stdout().lock(), DumpFormat::Ndjson);
+    let count = dump
+        .write_stream(engine.search_stream(&query))
+        .await
+        .expect("unable to crawl");
+    eprintln!("{count} entries written for {query:?}");
+}
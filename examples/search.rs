@@ -0,0 +1,23 @@
+//! Searches sunxdcc and prints the first page of results.
+//!
+//! ```bash
+//! cargo run --example search -- ubuntu
+//! ```
+
+#[tokio::main]
+async fn main() {
+    let query = std::env::args().nth(1).unwrap_or_else(|| "ubuntu".into());
+    let engine = xdcc_search::sunxdcc::Engine::default();
+    let entries = engine.search(&query, 0).await.expect("unable to search");
+    for entry in &entries {
+        println!(
+            "{:>10}  {:>10}/s  /msg {} xdcc send #{}  {}",
+            entry.size(),
+            entry.speed(),
+            entry.bot_name,
+            entry.packnum,
+            entry.filename
+        );
+    }
+    eprintln!("{} results for {query:?}", entries.len());
+}
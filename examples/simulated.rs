@@ -0,0 +1,43 @@
+//! Searches a provider simulated in-process and prints a shell script requesting
+//! the results with xdccget, the consecutive packs being batched. Runs without
+//! network access, as a smoke test of the `testing` feature.
+//!
+//! ```bash
+//! cargo run --example simulated --features testing
+//! ```
+
+use std::time::Duration;
+
+use xdcc_search::entry::Entry;
+use xdcc_search::export::{Exporter, Servers};
+use xdcc_search::testing::{Reply, SimulatedProvider};
+
+fn entry(packnum: u64) -> Entry {
+    Entry::builder()
+        .filename(format!("ubuntu-24.04-part{packnum}.iso"))
+        .filesize(1024 * 1024 * 1024)
+        .packnum(packnum)
+        .channel("#ubuntu")
+        .network("libera")
+        .bot_name("Ubuntu|Bot")
+        .build()
+        .unwrap()
+}
+
+#[tokio::main]
+async fn main() {
+    let provider = SimulatedProvider::builder()
+        .page("ubuntu", 0, Reply::Entries((1..=4).map(entry).collect()))
+        .start()
+        .await
+        .expect("unable to start the simulated provider");
+    let engine = xdcc_search::sunxdcc::Engine::with_url(
+        xdcc_search::http::ReqwestBackend::default(),
+        provider.url().to_owned(),
+    );
+    let entries = engine.search_all("ubuntu").await.expect("unable to search");
+    print!(
+        "{}",
+        Exporter::Xdccget(Servers::default()).script_batched(&entries, Duration::from_secs(30))
+    );
+}
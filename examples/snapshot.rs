@@ -0,0 +1,25 @@
+//! Fetches a live page of sunxdcc and saves it, without its volatile values,
+//! as a fixture in `resources/`. The golden file of the fixture is then generated
+//! by the tests.
+//!
+//! ```bash
+//! cargo run --example snapshot -- ubuntu
+//! UPDATE_GOLDEN=1 cargo test should_decode_fixtures_as_golden
+//! ```
+
+#[tokio::main]
+async fn main() {
+    let query = std::env::args().nth(1).unwrap_or_else(|| "ubuntu".into());
+    let engine = xdcc_search::sunxdcc::Engine::default();
+    let body = engine
+        .snapshot(&query, 0)
+        .await
+        .expect("unable to fetch the page");
+    let name = query
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>();
+    let path = format!("{}/resources/{name}.json", env!("CARGO_MANIFEST_DIR"));
+    std::fs::write(&path, body).expect("unable to write the fixture");
+    println!("fixture written to {path}");
+}
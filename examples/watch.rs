@@ -0,0 +1,41 @@
+//! Polls a search and prints the entries that weren't listed before, one JSON
+//! document per line, e.g. to pipe them to a webhook.
+//!
+//! ```bash
+//! cargo run --example watch -- ubuntu 300 | while read -r entry; do
+//!     curl -s -H 'content-type: application/json' -d "$entry" https://example.com/hook
+//! done
+//! ```
+
+use std::time::Duration;
+
+use xdcc_search::seen::{ExactSet, SeenStore};
+
+#[tokio::main]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let query = args.next().unwrap_or_else(|| "ubuntu".into());
+    let interval = args
+        .next()
+        .map(|value| value.parse().expect("invalid interval"))
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(300));
+    let engine = xdcc_search::sunxdcc::Engine::default();
+    let mut seen = ExactSet::default();
+    // the entries listed when starting are not reported
+    let mut initial = true;
+    loop {
+        match engine.search_all(&query).await {
+            Ok(entries) => {
+                for entry in entries {
+                    if seen.insert(&entry) && !initial {
+                        println!("{}", serde_json::to_string(&entry).unwrap());
+                    }
+                }
+                initial = false;
+            }
+            Err(error) => eprintln!("unable to search {query:?}: {error}"),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
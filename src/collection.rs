@@ -0,0 +1,295 @@
+//! Named collections of entries, to gather packs before downloading them.
+//!
+//! A [`Collection`] is a basket of entries, without duplicates, filled over several
+//! searches. [`Collections`] keeps them by name and is persisted as JSON, in a
+//! [`Versioned`] envelope, to any writer, so "gather then download" workflows can
+//! span several sessions.
+//!
+//! The collections and their entries can be annotated with tags and a note (e.g.
+//! `wanted`, `dead bot`, `verified`), to organize the harvested listings.
+//!
+//! ```
+//! use xdcc_search::collection::Collections;
+//!
+//! let mut collections = Collections::default();
+//! let weekend = collections.collection("weekend");
+//! assert!(weekend.is_empty());
+//!
+//! let mut buffer = Vec::new();
+//! collections.write_to(&mut buffer).unwrap();
+//! let read = Collections::read_from(buffer.as_slice()).unwrap();
+//! assert_eq!(read.names().collect::<Vec<_>>(), ["weekend"]);
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+
+use crate::entry::Entry;
+use crate::format::ByteSize;
+use crate::schema::{SchemaError, Versioned};
+
+/// Tags and a note attached by the user to a collection or an entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Annotation {
+    /// The tags, in alphabetical order.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// A free text note.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Annotation {
+    /// Returns `true` if the annotation has no tag and no note.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.note.is_none()
+    }
+
+    /// Returns `true` if the annotation has the tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+}
+
+/// An ordered list of entries, each pack appearing once, see [`Entry::id`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Collection {
+    entries: Vec<Entry>,
+    /// The annotation of the collection itself.
+    #[serde(default, skip_serializing_if = "Annotation::is_empty")]
+    pub annotation: Annotation,
+    /// The annotations of the entries, by identifier.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    annotations: BTreeMap<String, Annotation>,
+}
+
+impl Collection {
+    /// Returns the entries, in the order they were added.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the collection has no entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns `true` if the collection contains the entry with this identifier.
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries.iter().any(|entry| entry.id() == id)
+    }
+
+    /// Adds an entry at the end, returns `false` if it was already in the collection.
+    pub fn add(&mut self, entry: Entry) -> bool {
+        if self.contains(&entry.id()) {
+            return false;
+        }
+        self.entries.push(entry);
+        true
+    }
+
+    /// Removes the entry with this identifier and its annotation, returns it if it
+    /// was in the collection.
+    pub fn remove(&mut self, id: &str) -> Option<Entry> {
+        let index = self.entries.iter().position(|entry| entry.id() == id)?;
+        self.annotations.remove(id);
+        Some(self.entries.remove(index))
+    }
+
+    /// Returns the annotation of the entry with this identifier, if any.
+    pub fn annotation(&self, id: &str) -> Option<&Annotation> {
+        self.annotations.get(id)
+    }
+
+    /// Returns the annotation of the entry with this identifier, to edit it, or
+    /// `None` if the entry isn't in the collection.
+    pub fn annotation_mut(&mut self, id: &str) -> Option<&mut Annotation> {
+        if !self.contains(id) {
+            return None;
+        }
+        Some(self.annotations.entry(id.to_owned()).or_default())
+    }
+
+    /// Adds a tag to the entry with this identifier, returns `false` if the entry
+    /// isn't in the collection.
+    pub fn tag(&mut self, id: &str, tag: impl Into<String>) -> bool {
+        self.annotation_mut(id)
+            .map(|annotation| annotation.tags.insert(tag.into()))
+            .is_some()
+    }
+
+    /// Removes a tag from the entry with this identifier.
+    pub fn untag(&mut self, id: &str, tag: &str) {
+        if let Some(annotation) = self.annotations.get_mut(id) {
+            annotation.tags.remove(tag);
+            if annotation.is_empty() {
+                self.annotations.remove(id);
+            }
+        }
+    }
+
+    /// Returns the entries with the tag, in the order they were added.
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Entry> + 'a {
+        self.entries.iter().filter(move |entry| {
+            self.annotations
+                .get(&entry.id())
+                .is_some_and(|annotation| annotation.has_tag(tag))
+        })
+    }
+
+    /// Returns the total size of the files, e.g. to check the available disk space.
+    pub fn total_size(&self) -> ByteSize {
+        ByteSize(self.entries.iter().map(|entry| entry.filesize).sum())
+    }
+}
+
+impl Extend<Entry> for Collection {
+    fn extend<I: IntoIterator<Item = Entry>>(&mut self, entries: I) {
+        for entry in entries {
+            self.add(entry);
+        }
+    }
+}
+
+/// The collections, by name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(transparent)]
+pub struct Collections(BTreeMap<String, Collection>);
+
+impl Collections {
+    /// Returns the collection with this name, creating it if needed.
+    pub fn collection(&mut self, name: &str) -> &mut Collection {
+        self.0.entry(name.to_owned()).or_default()
+    }
+
+    /// Returns the collection with this name, if it exists.
+    pub fn get(&self, name: &str) -> Option<&Collection> {
+        self.0.get(name)
+    }
+
+    /// Deletes the collection with this name, returns it if it existed.
+    pub fn remove(&mut self, name: &str) -> Option<Collection> {
+        self.0.remove(name)
+    }
+
+    /// Returns the names of the collections, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// Returns the collections with the tag, with their names, in alphabetical order.
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = (&'a str, &'a Collection)> {
+        self.iter()
+            .filter(move |(_, collection)| collection.annotation.has_tag(tag))
+    }
+
+    /// Returns the collections with their names, in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Collection)> {
+        self.0
+            .iter()
+            .map(|(name, collection)| (name.as_str(), collection))
+    }
+
+    /// Writes the collections as JSON, with the current schema version.
+    pub fn write_to<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        serde_json::to_writer(writer, &Versioned::new(self)).map_err(std::io::Error::from)
+    }
+
+    /// Reads back collections written by [`Collections::write_to`], possibly by an
+    /// older version of the crate.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, SchemaError> {
+        Versioned::from_value(serde_json::from_reader(reader)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::tests::entry;
+
+    #[test]
+    fn should_add_entries_once() {
+        let mut collection = Collection::default();
+        assert!(collection.add(entry(1, 100)));
+        assert!(collection.add(entry(2, 50)));
+        assert!(!collection.add(entry(1, 100)));
+        collection.extend([entry(3, 10), entry(2, 50)]);
+        assert_eq!(
+            collection.entries(),
+            [entry(1, 100), entry(2, 50), entry(3, 10)]
+        );
+        assert_eq!(collection.total_size(), ByteSize(160));
+    }
+
+    #[test]
+    fn should_remove_entries() {
+        let mut collection = Collection::default();
+        collection.extend([entry(1, 100), entry(2, 50)]);
+        let id = entry(1, 100).id();
+        assert_eq!(collection.remove(&id), Some(entry(1, 100)));
+        assert_eq!(collection.remove(&id), None);
+        assert_eq!(collection.entries(), [entry(2, 50)]);
+    }
+
+    #[test]
+    fn should_annotate_entries() {
+        let mut collection = Collection::default();
+        collection.extend([entry(1, 100), entry(2, 50)]);
+        let (first, second) = (entry(1, 100).id(), entry(2, 50).id());
+        assert!(collection.tag(&first, "wanted"));
+        assert!(collection.tag(&second, "wanted"));
+        assert!(collection.tag(&second, "dead bot"));
+        assert!(!collection.tag(&entry(3, 10).id(), "wanted"));
+        collection.annotation_mut(&first).unwrap().note = Some("720p only".into());
+
+        let wanted = collection.tagged("wanted").collect::<Vec<_>>();
+        assert_eq!(wanted, [&entry(1, 100), &entry(2, 50)]);
+        collection.untag(&second, "wanted");
+        collection.untag(&second, "dead bot");
+        assert_eq!(collection.tagged("wanted").count(), 1);
+        assert_eq!(collection.annotation(&second), None);
+        collection.remove(&first);
+        assert_eq!(collection.annotation(&first), None);
+    }
+
+    #[test]
+    fn should_persist_collections() {
+        let mut collections = Collections::default();
+        collections.collection("movies").add(entry(1, 100));
+        collections.collection("isos").add(entry(2, 50));
+        collections
+            .collection("isos")
+            .tag(&entry(2, 50).id(), "verified");
+        collections
+            .collection("movies")
+            .annotation
+            .tags
+            .insert("weekend".into());
+        collections.collection("trash");
+        assert!(collections.remove("trash").is_some());
+
+        let mut buffer = Vec::new();
+        collections.write_to(&mut buffer).unwrap();
+        let read = Collections::read_from(buffer.as_slice()).unwrap();
+        assert_eq!(read, collections);
+        assert_eq!(read.names().collect::<Vec<_>>(), ["isos", "movies"]);
+        assert_eq!(read.get("movies").unwrap().entries(), [entry(1, 100)]);
+        let tagged = read
+            .tagged("weekend")
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(tagged, ["movies"]);
+    }
+
+    #[test]
+    fn should_reject_invalid_data() {
+        let error = Collections::read_from(r#"{"version":1,"data":[]}"#.as_bytes()).unwrap_err();
+        assert!(matches!(error, SchemaError::Invalid(_)));
+    }
+}
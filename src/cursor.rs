@@ -0,0 +1,131 @@
+//! Resumable position in a crawl.
+//!
+//! A [`Cursor`] records the provider, the search term and the next page of a
+//! crawl. It can be converted to a string and parsed back, so a deep crawl can be
+//! stopped and resumed later, even from another process, with
+//! [`Engine::resume`](crate::sunxdcc::Engine::resume).
+
+const VERSION: u8 = 1;
+
+/// The position of a crawl, see the [module documentation](self).
+///
+/// The string representation should be considered opaque, it is only guaranteed
+/// to be parsed back by the same version of the crate.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    provider: String,
+    query: String,
+    page: u8,
+}
+
+impl Cursor {
+    /// Creates a cursor at the first page of a search.
+    ///
+    /// The provider name is the one returned by [`SearchProvider::name`](crate::provider::SearchProvider::name).
+    pub fn new(provider: impl Into<String>, query: impl Into<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            query: query.into(),
+            page: 0,
+        }
+    }
+
+    /// Returns the same cursor, moved to the given page.
+    pub fn at_page(mut self, page: u8) -> Self {
+        self.page = page;
+        self
+    }
+
+    /// Returns the name of the provider being crawled.
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    /// Returns the search term.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Returns the next page to fetch.
+    pub fn page(&self) -> u8 {
+        self.page
+    }
+}
+
+fn encode(value: &str) -> String {
+    value.bytes().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode(value: &str) -> Option<String> {
+    if !value.len().is_multiple_of(2) || !value.is_ascii() {
+        return None;
+    }
+    let bytes = (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&value[index..index + 2], 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{VERSION}.{}.{}.{}",
+            self.page,
+            encode(&self.provider),
+            encode(&self.query)
+        )
+    }
+}
+
+/// The error returned when parsing an invalid [`Cursor`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("invalid cursor")]
+pub struct InvalidCursor;
+
+impl std::str::FromStr for Cursor {
+    type Err = InvalidCursor;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.split('.');
+        let mut next = || parts.next().ok_or(InvalidCursor);
+        if next()?.parse::<u8>() != Ok(VERSION) {
+            return Err(InvalidCursor);
+        }
+        let page = next()?.parse::<u8>().map_err(|_| InvalidCursor)?;
+        let provider = decode(next()?).ok_or(InvalidCursor)?;
+        let query = decode(next()?).ok_or(InvalidCursor)?;
+        if parts.next().is_some() {
+            return Err(InvalidCursor);
+        }
+        Ok(Self {
+            provider,
+            query,
+            page,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cursor;
+
+    #[test]
+    fn should_convert_to_string_and_back() {
+        let cursor = Cursor::new("sunxdcc", "Show S01E01.mkv: part 1").at_page(12);
+        let parsed: Cursor = cursor.to_string().parse().unwrap();
+        assert_eq!(parsed, cursor);
+        assert_eq!(parsed.page(), 12);
+        assert_eq!(parsed.query(), "Show S01E01.mkv: part 1");
+    }
+
+    #[test_case::test_case(""; "empty")]
+    #[test_case::test_case("2.0.00.00"; "unknown version")]
+    #[test_case::test_case("1.0.0"; "odd length")]
+    #[test_case::test_case("1.300.00.00"; "page out of range")]
+    #[test_case::test_case("1.0.00.00.00"; "trailing part")]
+    fn shouldnt_parse_invalid_cursor(input: &str) {
+        assert!(input.parse::<Cursor>().is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Diagnostics of the providers, to tell the users what is wrong, and what to do
+//! about it, when the searches fail.
+//!
+//! [`Engine::diagnose`](crate::sunxdcc::Engine::diagnose) searches a common term
+//! and turns what happened into a list of [`Finding`].
+
+use std::time::Duration;
+
+#[cfg(feature = "sunxdcc")]
+use crate::drift::FormatDrift;
+
+/// The term searched to check a provider, expected to always return results.
+pub const PROBE_QUERY: &str = "linux";
+/// The latency above which a provider is reported as slow.
+pub const SLOW_LATENCY: Duration = Duration::from_secs(5);
+
+/// How serious a finding is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Everything is fine.
+    Ok,
+    /// The provider works, but not as well as it should.
+    Warning,
+    /// The provider can't be used.
+    Error,
+}
+
+/// Something found while checking a provider, with the action to take.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// How serious the finding is.
+    pub level: Level,
+    /// What was found and what to do about it.
+    pub message: String,
+}
+
+#[cfg(feature = "sunxdcc")]
+impl Finding {
+    fn new(level: Level, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self.level {
+            Level::Ok => "ok",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        };
+        write!(f, "[{label}] {}", self.message)
+    }
+}
+
+/// The result of the diagnostic of a provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnosis {
+    /// The name of the provider.
+    pub provider: String,
+    /// How long the search took, failed or not.
+    pub latency: Duration,
+    /// What was found, the most serious first.
+    pub findings: Vec<Finding>,
+}
+
+impl Diagnosis {
+    /// Builds the findings from the outcome of the probe search: the number of
+    /// rows received and how many of them couldn't be decoded.
+    #[cfg(feature = "sunxdcc")]
+    pub(crate) fn new(
+        provider: &str,
+        latency: Duration,
+        result: crate::Result<(usize, usize)>,
+    ) -> Self {
+        let mut findings = Vec::new();
+        match result {
+            Ok((rows, failures)) => {
+                findings.push(Finding::new(
+                    Level::Ok,
+                    format!("reachable, answered in {}ms", latency.as_millis()),
+                ));
+                if latency > SLOW_LATENCY {
+                    findings.push(Finding::new(
+                        Level::Warning,
+                        "slow to answer, consider increasing the timeout or using another provider",
+                    ));
+                }
+                if rows == 0 {
+                    findings.push(Finding::new(
+                        Level::Warning,
+                        format!(
+                            "no result for {PROBE_QUERY:?}, the provider may be degraded or have changed its API"
+                        ),
+                    ));
+                } else if failures > 0 {
+                    findings.push(Finding::new(
+                        Level::Warning,
+                        format!(
+                            "{failures} of {rows} rows couldn't be decoded, the provider may have changed its format, see Engine::decode_failures"
+                        ),
+                    ));
+                }
+            }
+            Err(error) => findings.push(Finding::new(Level::Error, describe(&error))),
+        }
+        findings.sort_by_key(|finding| std::cmp::Reverse(finding.level));
+        Self {
+            provider: provider.to_owned(),
+            latency,
+            findings,
+        }
+    }
+
+    #[cfg(feature = "sunxdcc")]
+    pub(crate) fn with_drift(mut self, drift: Option<FormatDrift>) -> Self {
+        if let Some(drift) = drift {
+            self.findings.insert(
+                0,
+                Finding::new(
+                    Level::Error,
+                    format!(
+                        "{:.0}% of the latest rows couldn't be decoded, the provider changed its format",
+                        drift.failure_rate() * 100.0
+                    ),
+                ),
+            );
+        }
+        self
+    }
+
+    /// Returns the most serious level of the findings.
+    pub fn level(&self) -> Level {
+        self.findings
+            .iter()
+            .map(|finding| finding.level)
+            .max()
+            .unwrap_or(Level::Ok)
+    }
+
+    /// Returns `true` if the provider can be used.
+    pub fn is_healthy(&self) -> bool {
+        self.level() != Level::Error
+    }
+}
+
+/// Describes an error with the action to take.
+#[cfg(feature = "sunxdcc")]
+fn describe(error: &crate::Error) -> String {
+    #[cfg(feature = "reqwest")]
+    if error.is_pinning_failure() {
+        return "the certificate of the provider matches none of the pins, update them if the provider renewed its certificate".into();
+    }
+    match error {
+        crate::Error::Request { source, .. } => describe(source),
+        crate::Error::Transport(_) => {
+            format!("unreachable ({error}), check the network connection, the proxy and the URL")
+        }
+        crate::Error::Status(_) if error.is_rate_limited() => {
+            "rate limited, increase the delay between the pages or wait before searching again"
+                .into()
+        }
+        crate::Error::Status(code) if *code >= 500 => {
+            format!("the provider is failing (status {code}), try again later")
+        }
+        crate::Error::Status(code) => {
+            format!("unexpected status {code}, check the URL of the provider")
+        }
+        crate::Error::Body(_) => {
+            "the response isn't in the expected format, the provider changed its API".into()
+        }
+        crate::Error::BodyTooLarge { limit } => {
+            format!("the response is larger than {limit} bytes, increase the maximum body size")
+        }
+        crate::Error::Shutdown => "the engine is shut down".into(),
+        crate::Error::ForeignCursor(_) => error.to_string(),
+        crate::Error::OfflineMiss { .. } => {
+            "the engine is offline, disable the offline mode".into()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sunxdcc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_healthy_provider() {
+        let diagnosis = Diagnosis::new("test", Duration::from_millis(120), Ok((38, 0)));
+        assert!(diagnosis.is_healthy());
+        assert_eq!(diagnosis.level(), Level::Ok);
+        assert_eq!(diagnosis.findings.len(), 1);
+        assert_eq!(
+            diagnosis.findings[0].to_string(),
+            "[ok] reachable, answered in 120ms"
+        );
+    }
+
+    #[test_case::test_case(Ok((38, 0)), Duration::from_secs(10), Level::Warning; "slow")]
+    #[test_case::test_case(Ok((0, 0)), Duration::ZERO, Level::Warning; "empty")]
+    #[test_case::test_case(Ok((38, 3)), Duration::ZERO, Level::Warning; "decode failures")]
+    #[test_case::test_case(Err(crate::Error::Status(429)), Duration::ZERO, Level::Error; "rate limited")]
+    fn should_report_problems(
+        result: crate::Result<(usize, usize)>,
+        latency: Duration,
+        expected: Level,
+    ) {
+        let diagnosis = Diagnosis::new("test", latency, result);
+        assert_eq!(diagnosis.level(), expected);
+        assert_eq!(diagnosis.findings[0].level, expected);
+    }
+
+    #[test]
+    fn should_report_drift() {
+        let drift = FormatDrift {
+            provider: "test".into(),
+            rows: 100,
+            failures: 50,
+        };
+        let diagnosis = Diagnosis::new("test", Duration::ZERO, Ok((38, 0))).with_drift(Some(drift));
+        assert!(!diagnosis.is_healthy());
+        assert!(diagnosis.findings[0].message.starts_with("50%"));
+    }
+}
@@ -0,0 +1,188 @@
+//! Detection of the changes of the format of a provider.
+//!
+//! The providers are scraped services, they can change their output at any time.
+//! When it happens, the rows stop decoding, and the searches silently return fewer
+//! results. The engines watch the rate of rows failing to decode over their latest
+//! pages and report a [`FormatDrift`] when it crosses a threshold, see
+//! [`EngineBuilder::on_format_drift`](crate::sunxdcc::EngineBuilder::on_format_drift).
+
+#[cfg(feature = "sunxdcc")]
+use std::collections::VecDeque;
+#[cfg(feature = "sunxdcc")]
+use std::sync::{Arc, Mutex};
+
+/// The default rate of failing rows above which a drift is reported.
+pub const DEFAULT_THRESHOLD: f64 = 0.2;
+/// The default number of pages considered.
+pub const DEFAULT_WINDOW: usize = 10;
+/// The minimum number of rows in the window to report a drift, to avoid reporting
+/// a single malformed row.
+#[cfg(feature = "sunxdcc")]
+const MIN_ROWS: usize = 20;
+
+/// The rows of a provider failing to decode at an abnormal rate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatDrift {
+    /// The name of the provider.
+    pub provider: String,
+    /// The number of rows received in the window.
+    pub rows: usize,
+    /// The number of rows that couldn't be decoded in the window.
+    pub failures: usize,
+}
+
+impl FormatDrift {
+    /// Returns the rate of rows failing to decode, between 0 and 1.
+    pub fn failure_rate(&self) -> f64 {
+        if self.rows == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.rows as f64
+        }
+    }
+}
+
+#[cfg(feature = "sunxdcc")]
+type Handler = Arc<dyn Fn(&FormatDrift) + Send + Sync>;
+
+#[cfg(feature = "sunxdcc")]
+#[derive(Debug, Default)]
+struct State {
+    /// The number of rows and failures of the latest pages.
+    pages: VecDeque<(usize, usize)>,
+    drifting: bool,
+}
+
+/// Watches the decoding failures over a sliding window of pages.
+#[cfg(feature = "sunxdcc")]
+pub(crate) struct DriftMonitor {
+    pub(crate) threshold: f64,
+    pub(crate) window: usize,
+    pub(crate) handler: Option<Handler>,
+    state: Mutex<State>,
+}
+
+#[cfg(feature = "sunxdcc")]
+impl Default for DriftMonitor {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            window: DEFAULT_WINDOW,
+            handler: None,
+            state: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "sunxdcc")]
+impl std::fmt::Debug for DriftMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DriftMonitor")
+            .field("threshold", &self.threshold)
+            .field("window", &self.window)
+            .field("handler", &self.handler.is_some())
+            .finish()
+    }
+}
+
+#[cfg(feature = "sunxdcc")]
+impl DriftMonitor {
+    fn totals(state: &State) -> (usize, usize) {
+        state.pages.iter().fold((0, 0), |(rows, failures), page| {
+            (rows + page.0, failures + page.1)
+        })
+    }
+
+    /// Records the rows of a page, calling the handler when a drift starts, and
+    /// returning it.
+    pub(crate) fn record(
+        &self,
+        provider: &str,
+        rows: usize,
+        failures: usize,
+    ) -> Option<FormatDrift> {
+        let drift = {
+            let mut state = self.state.lock().unwrap();
+            state.pages.push_back((rows, failures));
+            while state.pages.len() > self.window.max(1) {
+                state.pages.pop_front();
+            }
+            let (rows, failures) = Self::totals(&state);
+            let drifting = rows >= MIN_ROWS && failures as f64 > rows as f64 * self.threshold;
+            let started = drifting && !state.drifting;
+            state.drifting = drifting;
+            started.then(|| FormatDrift {
+                provider: provider.to_owned(),
+                rows,
+                failures,
+            })
+        };
+        if let Some(drift) = &drift {
+            crate::telemetry::log!(
+                warn,
+                "{} rows out of {} from {:?} couldn't be decoded, its format probably changed",
+                drift.failures,
+                drift.rows,
+                drift.provider
+            );
+            if let Some(handler) = &self.handler {
+                handler(drift);
+            }
+        }
+        drift
+    }
+
+    /// Returns the drift in progress, if any.
+    pub(crate) fn current(&self, provider: &str) -> Option<FormatDrift> {
+        let state = self.state.lock().unwrap();
+        state.drifting.then(|| {
+            let (rows, failures) = Self::totals(&state);
+            FormatDrift {
+                provider: provider.to_owned(),
+                rows,
+                failures,
+            }
+        })
+    }
+}
+
+#[cfg(all(test, feature = "sunxdcc"))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn should_report_drift_once() {
+        let reported = Arc::new(AtomicUsize::new(0));
+        let counter = reported.clone();
+        let monitor = DriftMonitor {
+            window: 2,
+            handler: Some(Arc::new(move |drift: &FormatDrift| {
+                assert_eq!(drift.provider, "test");
+                counter.fetch_add(1, Ordering::SeqCst);
+            })),
+            ..Default::default()
+        };
+        monitor.record("test", 38, 1);
+        assert!(monitor.current("test").is_none());
+        monitor.record("test", 38, 20);
+        monitor.record("test", 38, 30);
+        assert_eq!(reported.load(Ordering::SeqCst), 1);
+        let drift = monitor.current("test").unwrap();
+        assert_eq!((drift.rows, drift.failures), (76, 50));
+        // the window forgets the older pages
+        monitor.record("test", 38, 0);
+        monitor.record("test", 38, 0);
+        assert!(monitor.current("test").is_none());
+        monitor.record("test", 38, 38);
+        assert_eq!(reported.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn shouldnt_report_small_samples() {
+        let monitor = DriftMonitor::default();
+        monitor.record("test", 2, 2);
+        assert!(monitor.current("test").is_none());
+    }
+}
@@ -0,0 +1,342 @@
+//! Streaming dumps of the entries, for the crawls too large to be held in memory.
+//!
+//! A [`Dump`] writes the entries as soon as they are received, one line per entry,
+//! either as NDJSON or CSV. Combined with [`Dump::write_stream`] and a stream of
+//! pages, e.g. [`Engine::search_stream`](crate::sunxdcc::Engine::search_stream),
+//! only the current page is kept in memory, whatever the size of the crawl.
+//!
+//! The NDJSON dumps start with a header recording the [`SCHEMA_VERSION`] they
+//! were written with, and are read back with [`read_ndjson`], migrating the
+//! entries written by the older versions.
+//!
+//! ```
+//! use xdcc_search::dump::{Dump, DumpFormat};
+//!
+//! let dump = Dump::new(Vec::new(), DumpFormat::Csv);
+//! let output = dump.finish().unwrap();
+//! assert!(output.starts_with(b"filename,filesize"));
+//! ```
+
+use std::io::{BufRead, Write};
+
+use futures_util::{Stream, StreamExt};
+
+use crate::entry::{Entry, Page};
+use crate::schema::{SCHEMA_VERSION, SchemaError};
+
+const CSV_HEADER: &str = "filename,filesize,downloads,packnum,channel,network,bot_name,bot_speed";
+
+/// The format of the dumped entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// A header with the [`SCHEMA_VERSION`] (`{"version":1}`), then one JSON
+    /// serialized [`Entry`] per line, see [`read_ndjson`].
+    #[default]
+    Ndjson,
+    /// A header, then one line per entry. The provider specific
+    /// [`Entry::extras`] are not written.
+    Csv,
+}
+
+/// The error returned when a stream of pages could not be dumped.
+#[derive(Debug, thiserror::Error)]
+pub enum DumpError {
+    /// A page of the stream could not be fetched.
+    #[error("unable to fetch a page")]
+    Search(#[from] crate::Error),
+    /// The entries could not be written, or read back.
+    #[error("unable to write the entries")]
+    Io(#[from] std::io::Error),
+    /// A dumped entry could not be read back.
+    #[error("unable to read an entry")]
+    Schema(#[from] SchemaError),
+}
+
+/// The first line of a NDJSON dump.
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(deny_unknown_fields)]
+struct Header {
+    version: u32,
+}
+
+/// Writes the entries as they are received, see the [module](self) documentation.
+#[derive(Debug)]
+pub struct Dump<W> {
+    writer: W,
+    format: DumpFormat,
+    header_written: bool,
+    count: usize,
+    anonymize: Option<String>,
+}
+
+/// Quotes a CSV field, when it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+impl<W: Write> Dump<W> {
+    /// Creates a dump writing in `writer`. Wrapping a file in a
+    /// [`BufWriter`](std::io::BufWriter) is recommended.
+    pub fn new(writer: W, format: DumpFormat) -> Self {
+        Self {
+            writer,
+            format,
+            header_written: false,
+            count: 0,
+            anonymize: None,
+        }
+    }
+
+    /// Anonymizes the entries before writing them, with the given salt (possibly
+    /// empty), see [`Entry::anonymized_with_salt`]. Meant for the dumps shared
+    /// publicly, e.g. attached to a bug report.
+    pub fn anonymized(mut self, salt: impl Into<String>) -> Self {
+        self.anonymize = Some(salt.into());
+        self
+    }
+
+    /// Returns the number of entries written so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn write_header(&mut self) -> std::io::Result<()> {
+        if self.header_written {
+            return Ok(());
+        }
+        match self.format {
+            DumpFormat::Ndjson => {
+                let header = Header {
+                    version: SCHEMA_VERSION,
+                };
+                serde_json::to_writer(&mut self.writer, &header).map_err(std::io::Error::from)?;
+                self.writer.write_all(b"\n")?;
+            }
+            DumpFormat::Csv => writeln!(self.writer, "{CSV_HEADER}")?,
+        }
+        self.header_written = true;
+        Ok(())
+    }
+
+    /// Writes a single entry.
+    pub fn write_entry(&mut self, entry: &Entry) -> std::io::Result<()> {
+        self.write_header()?;
+        let anonymized = self
+            .anonymize
+            .as_deref()
+            .map(|salt| entry.anonymized_with_salt(salt));
+        let entry = anonymized.as_ref().unwrap_or(entry);
+        match self.format {
+            DumpFormat::Ndjson => {
+                serde_json::to_writer(&mut self.writer, entry).map_err(std::io::Error::from)?;
+                self.writer.write_all(b"\n")?;
+            }
+            DumpFormat::Csv => {
+                writeln!(
+                    self.writer,
+                    "{},{},{},{},{},{},{},{}",
+                    csv_field(&entry.filename),
+                    entry.filesize,
+                    entry.downloads,
+                    entry.packnum,
+                    csv_field(entry.channel.as_str()),
+                    csv_field(entry.network.as_str()),
+                    csv_field(entry.bot_name.as_str()),
+                    entry.bot_speed,
+                )?;
+            }
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Writes the entries, in the given order.
+    pub fn write_entries<'a, I>(&mut self, entries: I) -> std::io::Result<()>
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        self.write_header()?;
+        entries
+            .into_iter()
+            .try_for_each(|entry| self.write_entry(entry))
+    }
+
+    /// Writes the entries of a page.
+    pub fn write_page(&mut self, page: &Page) -> std::io::Result<()> {
+        self.write_entries(&page.entries)
+    }
+
+    /// Writes the pages of the stream as they arrive, dropping each page once
+    /// written, and returns the number of entries written.
+    ///
+    /// The stream is polled for the next page only once the previous one is
+    /// written, a slow writer slowing the crawl down instead of buffering pages.
+    ///
+    /// # Errors
+    ///
+    /// Stops at the first page that couldn't be fetched or written, the entries
+    /// of the previous pages being written already.
+    pub async fn write_stream<S>(&mut self, stream: S) -> Result<usize, DumpError>
+    where
+        S: Stream<Item = crate::Result<Page>>,
+    {
+        let mut stream = std::pin::pin!(stream);
+        let before = self.count;
+        self.write_header()?;
+        while let Some(page) = stream.next().await {
+            self.write_page(&page?)?;
+        }
+        self.writer.flush()?;
+        Ok(self.count - before)
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.write_header()?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads back the entries of a NDJSON dump, migrating them from the version
+/// recorded in its header, see [`crate::schema::migrate`].
+///
+/// The dumps written before the header was introduced are read as the version `0`.
+pub fn read_ndjson<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Entry, DumpError>> {
+    let mut version = None;
+    reader.lines().filter_map(move |line| {
+        let line = match line {
+            Ok(line) if line.trim().is_empty() => return None,
+            Ok(line) => line,
+            Err(error) => return Some(Err(error.into())),
+        };
+        let version = match version {
+            Some(version) => version,
+            None => match serde_json::from_str::<Header>(&line) {
+                Ok(header) => {
+                    version = Some(header.version);
+                    return None;
+                }
+                Err(_) => *version.insert(0),
+            },
+        };
+        Some(read_entry(&line, version))
+    })
+}
+
+fn read_entry(line: &str, version: u32) -> Result<Entry, DumpError> {
+    let value = serde_json::from_str(line).map_err(SchemaError::from)?;
+    let value = crate::schema::migrate(value, version)?;
+    Ok(serde_json::from_value(value).map_err(SchemaError::from)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::PageInfo;
+    use crate::runtime::now_or_never;
+
+    fn page(packnums: &[u64]) -> crate::Result<Page> {
+        Ok(Page {
+            entries: packnums
+                .iter()
+                .map(|packnum| crate::process::tests::entry(*packnum, 10))
+                .collect(),
+            info: PageInfo {
+                page: 0,
+                per_page: 2,
+                is_last_guess: false,
+            },
+            timing: None,
+        })
+    }
+
+    #[test]
+    fn should_dump_stream_as_ndjson() {
+        let stream = futures_util::stream::iter(vec![page(&[1, 2]), page(&[3])]);
+        let mut dump = Dump::new(Vec::new(), DumpFormat::Ndjson);
+        assert_eq!(now_or_never(dump.write_stream(stream)).unwrap(), 3);
+        let output = dump.finish().unwrap();
+        assert!(output.starts_with(format!("{{\"version\":{SCHEMA_VERSION}}}\n").as_bytes()));
+        let packnums = read_ndjson(output.as_slice())
+            .map(|entry| entry.unwrap().packnum)
+            .collect::<Vec<_>>();
+        assert_eq!(packnums, [1, 2, 3]);
+    }
+
+    #[test]
+    fn should_read_dump_without_header() {
+        let entry = crate::process::tests::entry(1, 10);
+        let output = format!("{}\n", serde_json::to_string(&entry).unwrap());
+        let entries = read_ndjson(output.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(entries, [entry]);
+    }
+
+    #[test]
+    fn shouldnt_read_dump_of_newer_version() {
+        let entry = serde_json::to_string(&crate::process::tests::entry(1, 10)).unwrap();
+        let output = format!("{{\"version\":{}}}\n{entry}\n", SCHEMA_VERSION + 1);
+        let error = read_ndjson(output.as_bytes()).next().unwrap().unwrap_err();
+        assert!(matches!(
+            error,
+            DumpError::Schema(SchemaError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn should_dump_entries_as_csv() {
+        let mut entry = crate::process::tests::entry(1, 10);
+        entry.filename = "a \"quoted\", file.mkv".into();
+        let mut dump = Dump::new(Vec::new(), DumpFormat::Csv);
+        dump.write_entry(&entry).unwrap();
+        dump.write_entry(&crate::process::tests::entry(2, 20))
+            .unwrap();
+        let output = String::from_utf8(dump.finish().unwrap()).unwrap();
+        assert_eq!(
+            output,
+            format!(
+                "{CSV_HEADER}\n\
+                 \"a \"\"quoted\"\", file.mkv\",10,1,1,#chan,net,bot,0\n\
+                 file-2.mkv,20,2,2,#chan,net,bot,0\n"
+            )
+        );
+    }
+
+    #[test]
+    fn should_dump_anonymized_entries() {
+        let entry = crate::process::tests::entry(1, 10);
+        let mut dump = Dump::new(Vec::new(), DumpFormat::Csv).anonymized("");
+        dump.write_entry(&entry).unwrap();
+        let output = String::from_utf8(dump.finish().unwrap()).unwrap();
+        let anonymized = entry.anonymized();
+        assert_eq!(
+            output.lines().nth(1).unwrap(),
+            format!(
+                "file-1.mkv,10,1,1,{},net,{},0",
+                anonymized.channel, anonymized.bot_name
+            )
+        );
+    }
+
+    #[test]
+    fn should_keep_written_entries_on_error() {
+        let stream = futures_util::stream::iter(vec![
+            page(&[1]),
+            Err(crate::Error::Status(500)),
+            page(&[2]),
+        ]);
+        let mut dump = Dump::new(Vec::new(), DumpFormat::Ndjson);
+        let error = now_or_never(dump.write_stream(stream)).unwrap_err();
+        assert!(matches!(
+            error,
+            DumpError::Search(crate::Error::Status(500))
+        ));
+        assert_eq!(dump.count(), 1);
+    }
+}
@@ -0,0 +1,186 @@
+//! Confirmation of the file sizes reported by the listings, asking the bots.
+//!
+//! Some listings report obviously wrong sizes (empty files, videos of a few
+//! bytes). [`enrich_sizes`] asks the bots offering the [suspicious](is_suspicious)
+//! entries for the details of their pack, with `XDCC INFO #<pack>`, and updates
+//! the entries with the confirmed size before any download decision is made.
+//!
+//! This crate doesn't speak IRC: the application sends the [`info_command`] over
+//! its own connection and parses the answer with [`PackInfo::parse`], through the
+//! [`PackInfoSource`] trait.
+
+use crate::entry::Entry;
+use crate::notice::PackInfo;
+
+/// The key of [`Entry::extras`] set to `true` once the size was confirmed by the bot.
+pub const SIZE_CONFIRMED_EXTRA: &str = "size_confirmed";
+/// The key of [`Entry::extras`] keeping the size reported by the listing, when
+/// the bot gave another one.
+pub const REPORTED_SIZE_EXTRA: &str = "reported_size";
+
+/// Below this size, a video, a disk image or an archive is considered suspicious.
+const MIN_MEDIA_SIZE: u64 = 1024 * 1024;
+
+/// The extensions of the files expected to be larger than [`MIN_MEDIA_SIZE`].
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "7z", "avi", "flac", "iso", "m4v", "mkv", "mp4", "rar", "webm", "zip",
+];
+
+/// Returns `true` if the size reported for the entry is probably wrong: an empty
+/// file, or a video, disk image or archive smaller than 1MiB.
+pub fn is_suspicious(entry: &Entry) -> bool {
+    if entry.filesize == 0 {
+        return true;
+    }
+    let is_media = entry
+        .filename
+        .rsplit_once('.')
+        .is_some_and(|(_, extension)| {
+            MEDIA_EXTENSIONS
+                .iter()
+                .any(|media| media.eq_ignore_ascii_case(extension))
+        });
+    is_media && entry.filesize < MIN_MEDIA_SIZE
+}
+
+/// Returns the message to send to the bot to get the details of the pack.
+pub fn info_command(entry: &Entry) -> String {
+    format!("XDCC INFO #{}", entry.packnum)
+}
+
+/// A way to ask a bot for the details of a pack, implemented by the applications
+/// connected to the IRC networks.
+pub trait PackInfoSource: Send + Sync {
+    /// The error returned when the bot could not be asked.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Sends the [`info_command`] to the bot offering the entry and parses its
+    /// answer, returns `None` if the bot doesn't support the command.
+    fn pack_info(
+        &self,
+        entry: &Entry,
+    ) -> impl Future<Output = Result<Option<PackInfo>, Self::Error>> + Send;
+}
+
+/// What [`enrich_sizes`] did, each entry being referred to by its index.
+#[derive(Debug)]
+pub struct Enrichment<E> {
+    /// The entries whose size was confirmed by the bot.
+    pub confirmed: Vec<usize>,
+    /// The entries whose size was replaced by the one given by the bot.
+    pub corrected: Vec<usize>,
+    /// The entries whose bot didn't give the size.
+    pub unsupported: Vec<usize>,
+    /// The entries whose bot could not be asked.
+    pub failed: Vec<(usize, E)>,
+}
+
+impl<E> Default for Enrichment<E> {
+    fn default() -> Self {
+        Self {
+            confirmed: Vec::new(),
+            corrected: Vec::new(),
+            unsupported: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+}
+
+/// Asks the bots for the real size of the suspicious entries, one after the other,
+/// and updates them: the size is replaced by the confirmed one, the reported one
+/// being kept in the [`REPORTED_SIZE_EXTRA`] extra.
+pub async fn enrich_sizes<S>(source: &S, entries: &mut [Entry]) -> Enrichment<S::Error>
+where
+    S: PackInfoSource,
+{
+    let mut result = Enrichment::default();
+    for (index, entry) in entries.iter_mut().enumerate() {
+        if !is_suspicious(entry) {
+            continue;
+        }
+        match source.pack_info(entry).await {
+            Ok(Some(PackInfo {
+                filesize: Some(filesize),
+                ..
+            })) => {
+                if filesize == entry.filesize {
+                    result.confirmed.push(index);
+                } else {
+                    entry
+                        .extras
+                        .insert(REPORTED_SIZE_EXTRA.into(), entry.filesize.to_string());
+                    entry.filesize = filesize;
+                    result.corrected.push(index);
+                }
+                entry
+                    .extras
+                    .insert(SIZE_CONFIRMED_EXTRA.into(), true.to_string());
+            }
+            Ok(_) => result.unsupported.push(index),
+            Err(error) => result.failed.push((index, error)),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::tests::entry;
+
+    struct Bots;
+
+    impl PackInfoSource for Bots {
+        type Error = std::io::Error;
+
+        async fn pack_info(&self, entry: &Entry) -> Result<Option<PackInfo>, Self::Error> {
+            let notices = match entry.packnum {
+                1 => vec![" Filesize       1503238553 [1.4GB]"],
+                2 => vec![" Filesize       0 [0]"],
+                3 => vec!["** Invalid Pack Number, Try Again"],
+                _ => return Err(std::io::ErrorKind::TimedOut.into()),
+            };
+            Ok(PackInfo::parse(notices))
+        }
+    }
+
+    #[test_case::test_case("file.mkv", 0, true; "empty")]
+    #[test_case::test_case("file.MKV", 12, true; "tiny video")]
+    #[test_case::test_case("file.mkv", 1503238553, false; "large video")]
+    #[test_case::test_case("file.nfo", 12, false; "small text")]
+    fn should_detect_suspicious_size(filename: &str, filesize: u64, expected: bool) {
+        let mut entry = entry(1, filesize);
+        entry.filename = filename.into();
+        assert_eq!(is_suspicious(&entry), expected);
+    }
+
+    #[test]
+    fn should_build_info_command() {
+        assert_eq!(info_command(&entry(42, 0)), "XDCC INFO #42");
+    }
+
+    #[test]
+    fn should_enrich_suspicious_sizes() {
+        let mut entries = vec![
+            entry(1, 12),
+            entry(2, 0),
+            entry(3, 0),
+            entry(4, 0),
+            entry(5, 1503238553),
+        ];
+        let result = crate::runtime::now_or_never(enrich_sizes(&Bots, &mut entries));
+        assert_eq!(result.corrected, [0]);
+        assert_eq!(result.confirmed, [1]);
+        assert_eq!(result.unsupported, [2]);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].0, 3);
+
+        assert_eq!(entries[0].filesize, 1503238553);
+        assert_eq!(entries[0].extra(REPORTED_SIZE_EXTRA), Some("12"));
+        assert_eq!(entries[0].extra(SIZE_CONFIRMED_EXTRA), Some("true"));
+        assert_eq!(entries[1].extra(REPORTED_SIZE_EXTRA), None);
+        assert_eq!(entries[1].extra(SIZE_CONFIRMED_EXTRA), Some("true"));
+        assert_eq!(entries[2].extra(SIZE_CONFIRMED_EXTRA), None);
+        assert_eq!(entries[4].extra(SIZE_CONFIRMED_EXTRA), None);
+    }
+}
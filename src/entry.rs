@@ -0,0 +1,507 @@
+//! The types describing the search results, shared by all the providers.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+
+use crate::name::{BotName, Channel, InvalidName, Network};
+
+/// A single XDCC listing entry returned from the search.
+///
+/// Contains all relevant metadata parsed from the server response. New fields
+/// may be added in future releases, the data specific to a provider being
+/// available in [`Entry::extras`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+#[non_exhaustive]
+pub struct Entry {
+    /// The name of the file being shared.
+    pub filename: String,
+    /// The size of the file in bytes.
+    pub filesize: u64,
+    /// Number of times the pack has been downloaded.
+    pub downloads: u64,
+    /// The XDCC pack number (used to request the pack).
+    pub packnum: u64,
+    /// The IRC channel where the bot is located.
+    pub channel: Channel,
+    /// The IRC network hosting the bot.
+    pub network: Network,
+    /// The name of the bot sharing the file.
+    pub bot_name: BotName,
+    /// The reported upload speed of the bot, in bytes per second.
+    pub bot_speed: u64,
+    /// When the pack was first listed, for the providers reporting it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_seen: Option<SystemTime>,
+    /// Additional data exposed by the provider (age, last announce, bot slots...),
+    /// the keys depending on the provider.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extras: BTreeMap<String, String>,
+}
+
+impl Entry {
+    /// Creates a builder, to create an entry with the same guarantees as the
+    /// decoded ones, e.g. from another scraper or in test fixtures.
+    ///
+    /// ```
+    /// use xdcc_search::entry::Entry;
+    ///
+    /// let entry = Entry::builder()
+    ///     .filename("ubuntu-24.04-desktop-amd64.iso")
+    ///     .filesize(6_114_656_256)
+    ///     .packnum(12)
+    ///     .channel("#ubuntu")
+    ///     .network("libera")
+    ///     .bot_name("Ubuntu|Bot")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(entry.id(), "libera/ubuntu|bot/#12/ubuntu-24.04-desktop-amd64.iso");
+    /// ```
+    pub fn builder() -> EntryBuilder {
+        EntryBuilder::default()
+    }
+
+    /// Returns a stable identifier of the entry: the same file, offered in the
+    /// same pack, by the same bot, on the same network.
+    ///
+    /// The network and bot names are case-folded, like when comparing them,
+    /// so a listing changing their casing keeps the same identifiers.
+    pub fn id(&self) -> String {
+        format!(
+            "{}/{}/#{}/{}",
+            crate::name::fold_case(self.network.as_str()),
+            crate::name::fold_case(self.bot_name.as_str()),
+            self.packnum,
+            self.filename
+        )
+    }
+
+    /// Returns the clean human title and year extracted from the filename.
+    ///
+    /// See [`Title::parse`](crate::title::Title::parse) for the details.
+    pub fn normalized_title(&self) -> crate::title::Title {
+        crate::title::Title::parse(&self.filename)
+    }
+
+    /// Returns the metadata detected from the filename (title, languages...).
+    ///
+    /// See [`FileMetadata::parse`](crate::metadata::FileMetadata::parse) for the details.
+    pub fn metadata(&self) -> crate::metadata::FileMetadata {
+        crate::metadata::FileMetadata::parse(&self.filename)
+    }
+
+    /// Returns the size of the file, to display it, see [`crate::format`].
+    pub fn size(&self) -> crate::format::ByteSize {
+        crate::format::ByteSize(self.filesize)
+    }
+
+    /// Returns the upload speed of the bot, to display it, see [`crate::format`].
+    pub fn speed(&self) -> crate::format::Speed {
+        crate::format::Speed(self.bot_speed)
+    }
+
+    /// Returns for how long the pack has been listed, `None` if the provider
+    /// doesn't report it.
+    pub fn age(&self) -> Option<Duration> {
+        self.age_at(SystemTime::now())
+    }
+
+    /// Returns for how long the pack had been listed at the given time.
+    pub fn age_at(&self, now: SystemTime) -> Option<Duration> {
+        self.first_seen
+            .map(|first_seen| now.duration_since(first_seen).unwrap_or_default())
+    }
+
+    /// Returns the provider specific value associated with `key`, if any.
+    pub fn extra(&self, key: &str) -> Option<&str> {
+        self.extras.get(key).map(String::as_str)
+    }
+
+    /// Returns a copy of the entry safe to share publicly, e.g. in a bug report or
+    /// a dataset, without directing traffic at the bots.
+    ///
+    /// The bot and channel names are replaced by pseudonyms derived from their hash
+    /// (`bot-1a2b3c4d`, `#chan-5e6f7a8b`), the same name always giving the same
+    /// pseudonym. The provider specific [`Entry::extras`] are dropped, the other
+    /// fields are kept.
+    ///
+    /// The well known names can be found back by hashing them, use
+    /// [`Entry::anonymized_with_salt`] with a secret salt to prevent it.
+    pub fn anonymized(&self) -> Self {
+        self.anonymized_with_salt("")
+    }
+
+    /// Same as [`Entry::anonymized`], mixing the `salt` in the hash of the names.
+    pub fn anonymized_with_salt(&self, salt: &str) -> Self {
+        let pseudonym = |prefix: &str, name: &str| {
+            // FNV-1a, stable across releases and platforms
+            let hash = salt
+                .bytes()
+                .chain([0])
+                .chain(crate::name::fold_case(name).bytes())
+                .fold(0xcbf29ce484222325_u64, |hash, byte| {
+                    (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+                });
+            format!("{prefix}-{:08x}", hash as u32)
+        };
+        // the pseudonyms are single words, and the channel starts with `#`
+        Self {
+            bot_name: BotName::new(pseudonym("bot", self.bot_name.as_str()))
+                .expect("pseudonym should be a valid bot name"),
+            channel: Channel::new(pseudonym("#chan", self.channel.as_str()))
+                .expect("pseudonym should be a valid channel"),
+            extras: BTreeMap::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns `true` if both entries describe the same pack, offered by the same bot.
+    pub fn is_same_pack(&self, other: &Entry) -> bool {
+        self.packnum == other.packnum
+            && self.bot_name == other.bot_name
+            && self.network == other.network
+    }
+}
+
+/// The error returned when building an invalid [`Entry`], see [`Entry::builder`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum InvalidEntry {
+    /// A required field wasn't set, or is empty.
+    #[error("missing {0}")]
+    Missing(&'static str),
+    /// The pack number is `0`, the packs being numbered from `1`.
+    #[error("invalid pack number 0")]
+    InvalidPacknum,
+    /// The channel, network or bot name is invalid.
+    #[error(transparent)]
+    Name(#[from] InvalidName),
+}
+
+/// Builder for an [`Entry`], created with [`Entry::builder`].
+///
+/// The filename, pack number, channel, network and bot name are required, the
+/// counters default to `0`.
+#[derive(Clone, Debug, Default)]
+pub struct EntryBuilder {
+    filename: Option<String>,
+    filesize: u64,
+    downloads: u64,
+    packnum: Option<u64>,
+    channel: Option<String>,
+    network: Option<String>,
+    bot_name: Option<String>,
+    bot_speed: u64,
+    first_seen: Option<SystemTime>,
+    extras: BTreeMap<String, String>,
+}
+
+impl EntryBuilder {
+    /// Sets the name of the file, required.
+    pub fn filename(mut self, value: impl Into<String>) -> Self {
+        self.filename = Some(value.into());
+        self
+    }
+
+    /// Sets the size of the file in bytes.
+    pub fn filesize(mut self, value: u64) -> Self {
+        self.filesize = value;
+        self
+    }
+
+    /// Sets the number of downloads of the pack.
+    pub fn downloads(mut self, value: u64) -> Self {
+        self.downloads = value;
+        self
+    }
+
+    /// Sets the pack number, required and starting at `1`.
+    pub fn packnum(mut self, value: u64) -> Self {
+        self.packnum = Some(value);
+        self
+    }
+
+    /// Sets the IRC channel of the bot, required.
+    pub fn channel(mut self, value: impl Into<String>) -> Self {
+        self.channel = Some(value.into());
+        self
+    }
+
+    /// Sets the IRC network of the bot, required.
+    pub fn network(mut self, value: impl Into<String>) -> Self {
+        self.network = Some(value.into());
+        self
+    }
+
+    /// Sets the name of the bot, required.
+    pub fn bot_name(mut self, value: impl Into<String>) -> Self {
+        self.bot_name = Some(value.into());
+        self
+    }
+
+    /// Sets the upload speed of the bot, in bytes per second.
+    pub fn bot_speed(mut self, value: u64) -> Self {
+        self.bot_speed = value;
+        self
+    }
+
+    /// Sets when the pack was first listed.
+    pub fn first_seen(mut self, value: SystemTime) -> Self {
+        self.first_seen = Some(value);
+        self
+    }
+
+    /// Adds a provider specific value, see [`Entry::extras`].
+    pub fn extra(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.extras.insert(key.into(), value.into());
+        self
+    }
+
+    /// Validates the fields and builds the entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`InvalidEntry`] if a required field is missing or empty, if the
+    /// pack number is `0`, or if a name is invalid (see [`crate::name`]).
+    pub fn build(self) -> Result<Entry, InvalidEntry> {
+        fn required(value: Option<String>, field: &'static str) -> Result<String, InvalidEntry> {
+            value
+                .filter(|value| !value.is_empty())
+                .ok_or(InvalidEntry::Missing(field))
+        }
+        let filename = required(self.filename, "filename")?;
+        let packnum = match self.packnum {
+            None => return Err(InvalidEntry::Missing("packnum")),
+            Some(0) => return Err(InvalidEntry::InvalidPacknum),
+            Some(packnum) => packnum,
+        };
+        Ok(Entry {
+            filename,
+            filesize: self.filesize,
+            downloads: self.downloads,
+            packnum,
+            channel: Channel::new(required(self.channel, "channel")?)?,
+            network: Network::new(required(self.network, "network")?)?,
+            bot_name: BotName::new(required(self.bot_name, "bot_name")?)?,
+            bot_speed: self.bot_speed,
+            first_seen: self.first_seen,
+            extras: self.extras,
+        })
+    }
+}
+
+/// A page of results, as returned by a provider.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page {
+    /// The entries that could be decoded.
+    pub entries: Vec<Entry>,
+    /// The pagination metadata inferred from the page.
+    pub info: PageInfo,
+    /// The time spent fetching and decoding the page, `None` when the page didn't
+    /// require a request (e.g. served offline) or the provider doesn't measure it.
+    pub timing: Option<crate::http::Timing>,
+}
+
+impl Page {
+    /// Annotates the entries with their provenance, see [`SearchHit`].
+    pub fn into_hits(self, provider: &str, fetched_at: SystemTime) -> Vec<SearchHit> {
+        let page = self.info.page;
+        self.entries
+            .into_iter()
+            .map(|entry| SearchHit {
+                entry,
+                provider: provider.to_owned(),
+                page,
+                fetched_at,
+            })
+            .collect()
+    }
+}
+
+/// An entry with its provenance: which provider returned it, on which page and when.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct SearchHit {
+    /// The entry found.
+    pub entry: Entry,
+    /// The name of the provider that returned the entry.
+    pub provider: String,
+    /// The page the entry was found on.
+    pub page: u8,
+    /// When the page was fetched.
+    pub fetched_at: SystemTime,
+}
+
+/// A representative sample of the results of a broad query, with an estimation
+/// of their total number, see [`Engine::sample`](crate::sunxdcc::Engine::sample).
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Sample {
+    /// Entries spread over the pages that were fetched.
+    pub entries: Vec<Entry>,
+    /// The estimated total number of results, before the pipeline is applied.
+    pub estimate: Estimate,
+}
+
+/// The estimated number of results of a query.
+///
+/// Displayed as `~540 results across ~14 pages`, without the `~` when exact.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Estimate {
+    /// The number of results.
+    pub total: usize,
+    /// The number of pages.
+    pub pages: usize,
+    /// `true` if the number of results is known exactly, e.g. reported by the provider
+    /// or observed down to the last page.
+    pub exact: bool,
+}
+
+impl Estimate {
+    /// Estimates the number of results from the pages observed: the highest page
+    /// known to be full, and the first page looking like the last one with its size.
+    ///
+    /// The pages between both are assumed to be half full. Without last page, the
+    /// estimation is a lower bound.
+    pub fn from_pages(
+        per_page: usize,
+        full: Option<u8>,
+        last: Option<(u8, usize)>,
+    ) -> Option<Self> {
+        let known_pages = full.map_or(0, |page| usize::from(page) + 1);
+        let known = known_pages * per_page;
+        match last {
+            Some((last, size)) => {
+                let gap = usize::from(last).saturating_sub(known_pages);
+                let pages = usize::from(last) + usize::from(size > 0);
+                Some(Self {
+                    total: known + gap * per_page / 2 + size,
+                    pages: pages.max(1),
+                    exact: gap == 0,
+                })
+            }
+            None => full.map(|_| Self {
+                total: known,
+                pages: known_pages,
+                exact: false,
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let approx = if self.exact { "" } else { "~" };
+        write!(
+            f,
+            "{approx}{} results across {approx}{} pages",
+            self.total, self.pages
+        )
+    }
+}
+
+/// Pagination metadata, inferred from the number of results.
+///
+/// The providers don't expose the total number of results, so the page size is
+/// guessed from the largest page received so far.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageInfo {
+    /// The page number, as requested.
+    pub page: u8,
+    /// The estimated number of results per page.
+    pub per_page: usize,
+    /// `true` if the page is empty or smaller than the estimated page size,
+    /// meaning that the next page is probably empty.
+    pub is_last_guess: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Entry, Estimate, InvalidEntry};
+
+    fn builder() -> super::EntryBuilder {
+        Entry::builder()
+            .filename("file.iso")
+            .packnum(1)
+            .channel("#chan")
+            .network("net")
+            .bot_name("bot")
+    }
+
+    #[test]
+    fn should_build_entry() {
+        let entry = builder().filesize(10).extra("age", "1d").build().unwrap();
+        assert_eq!(entry.id(), "net/bot/#1/file.iso");
+        assert_eq!(entry.filesize, 10);
+        assert_eq!(entry.extra("age"), Some("1d"));
+    }
+
+    #[test]
+    fn should_anonymize_entry() {
+        let entry = builder().extra("age", "1d").build().unwrap();
+        let anonymized = entry.anonymized();
+        assert_eq!(anonymized.filename, entry.filename);
+        assert_eq!(anonymized.network, entry.network);
+        assert!(anonymized.bot_name.as_str().starts_with("bot-"));
+        assert!(anonymized.channel.as_str().starts_with("#chan-"));
+        assert!(anonymized.extras.is_empty());
+        // the names are compared ignoring the case
+        let upper = builder().bot_name("BOT").build().unwrap();
+        assert_eq!(upper.anonymized().bot_name, anonymized.bot_name);
+        assert_ne!(entry.anonymized_with_salt("secret"), anonymized);
+    }
+
+    #[test]
+    fn should_reject_invalid_entry() {
+        assert_eq!(
+            builder().filename("").build(),
+            Err(InvalidEntry::Missing("filename"))
+        );
+        assert_eq!(
+            builder().packnum(0).build(),
+            Err(InvalidEntry::InvalidPacknum)
+        );
+        assert_eq!(
+            Entry::builder().filename("file.iso").build(),
+            Err(InvalidEntry::Missing("packnum"))
+        );
+        assert!(matches!(
+            builder().channel("not a channel").build(),
+            Err(InvalidEntry::Name(_))
+        ));
+    }
+
+    #[test_case::test_case(None, Some((0, 12)), 12, 1, true; "single page")]
+    #[test_case::test_case(None, Some((0, 0)), 0, 1, true; "no result")]
+    #[test_case::test_case(Some(2), Some((3, 10)), 130, 4, true; "last page found")]
+    #[test_case::test_case(Some(1), Some((3, 0)), 100, 3, false; "gap before empty page")]
+    #[test_case::test_case(Some(3), None, 160, 4, false; "lower bound")]
+    fn should_estimate(
+        full: Option<u8>,
+        last: Option<(u8, usize)>,
+        total: usize,
+        pages: usize,
+        exact: bool,
+    ) {
+        let estimate = Estimate::from_pages(40, full, last).unwrap();
+        assert_eq!(
+            estimate,
+            Estimate {
+                total,
+                pages,
+                exact
+            }
+        );
+    }
+
+    #[test]
+    fn should_display_estimate() {
+        let estimate = Estimate {
+            total: 540,
+            pages: 14,
+            exact: false,
+        };
+        assert_eq!(estimate.to_string(), "~540 results across ~14 pages");
+        let estimate = Estimate {
+            exact: true,
+            ..estimate
+        };
+        assert_eq!(estimate.to_string(), "540 results across 14 pages");
+    }
+}
@@ -0,0 +1,202 @@
+use crate::http::RequestId;
+
+/// The error returned when a search could not be performed.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The HTTP backend was unable to execute the request.
+    #[error("unable to execute request")]
+    Transport(#[source] Box<dyn std::error::Error + Send + Sync>),
+    /// The server answered with a non successful status code.
+    #[error("unexpected status code {0}")]
+    Status(u16),
+    /// The response body could not be deserialized.
+    #[error("unable to deserialize response body")]
+    Body(#[from] serde_json::Error),
+    /// The response body is larger than the configured limit, it was not read entirely.
+    #[error("response body larger than {limit} bytes")]
+    BodyTooLarge {
+        /// The maximum size of a body, in bytes.
+        limit: usize,
+    },
+    /// The engine was shut down and doesn't accept new searches.
+    #[error("engine is shut down")]
+    Shutdown,
+    /// The cursor was created for another provider.
+    #[error("cursor created for the provider {0:?}")]
+    ForeignCursor(String),
+    /// The engine is offline and the requested page isn't in its cache.
+    #[error("page {page} for {query:?} not available offline")]
+    OfflineMiss {
+        /// The search term.
+        query: String,
+        /// The page requested.
+        page: u8,
+    },
+    /// A request of a search failed, with the identifier of the search to
+    /// correlate the error with the logs, see [`Error::request_id`].
+    #[error("request {request_id} failed")]
+    Request {
+        /// The identifier of the search.
+        request_id: RequestId,
+        /// The reason of the failure.
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Returns the identifier of the search that failed, when the error was
+    /// returned by a provider sending one, see [`RequestId`].
+    pub fn request_id(&self) -> Option<RequestId> {
+        match self {
+            Self::Request { request_id, .. } => Some(*request_id),
+            _ => None,
+        }
+    }
+
+    /// Returns the reason of the failure, without the [`RequestId`] of the search.
+    pub fn inner(&self) -> &Self {
+        match self {
+            Self::Request { source, .. } => source.inner(),
+            other => other,
+        }
+    }
+
+    /// Returns the HTTP status code returned by the server, if any.
+    pub fn status(&self) -> Option<u16> {
+        match self.inner() {
+            Self::Status(code) => Some(*code),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the server asked to slow down (`429 Too Many Requests`).
+    pub fn is_rate_limited(&self) -> bool {
+        self.status() == Some(429)
+    }
+
+    /// Returns `true` if sending the same request again later could succeed.
+    ///
+    /// Transport failures (timeouts, connection errors...) and transient server
+    /// statuses are considered retryable, a malformed response body is not.
+    pub fn is_retryable(&self) -> bool {
+        match self.inner() {
+            #[cfg(feature = "reqwest")]
+            Self::Transport(inner) if inner.is::<reqwest::Error>() => inner
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(|err| err.is_timeout() || err.is_connect() || err.is_request()),
+            Self::Transport(_) => true,
+            Self::Status(code) => matches!(code, 408 | 425 | 429 | 500 | 502 | 503 | 504),
+            Self::Body(_)
+            | Self::BodyTooLarge { .. }
+            | Self::Shutdown
+            | Self::ForeignCursor(_)
+            | Self::OfflineMiss { .. }
+            | Self::Request { .. } => false,
+        }
+    }
+
+    /// Returns `true` if the certificate of the provider matched none of the pins,
+    /// see [`ReqwestBackendBuilder::pin_certificate`](crate::http::ReqwestBackendBuilder::pin_certificate).
+    #[cfg(feature = "reqwest")]
+    pub fn is_pinning_failure(&self) -> bool {
+        let Self::Transport(inner) = self.inner() else {
+            return false;
+        };
+        let mut current: Option<&(dyn std::error::Error + 'static)> = Some(inner.as_ref());
+        while let Some(error) = current {
+            // io errors don't expose the wrapped error as their source
+            let error = match error.downcast_ref::<std::io::Error>() {
+                Some(io) => match io.get_ref() {
+                    Some(wrapped) => wrapped as &(dyn std::error::Error + 'static),
+                    None => error,
+                },
+                None => error,
+            };
+            if let Some(rustls::Error::InvalidCertificate(rustls::CertificateError::Other(other))) =
+                error.downcast_ref::<rustls::Error>()
+                && other.0.is::<crate::http::PinningError>()
+            {
+                return true;
+            }
+            current = error.source();
+        }
+        false
+    }
+
+    #[cfg(any(test, feature = "sunxdcc", feature = "plugin"))]
+    pub(crate) fn transport<E>(error: E) -> Self
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        Self::Transport(Box::new(error))
+    }
+
+    #[cfg(feature = "sunxdcc")]
+    pub(crate) fn with_request_id(self, request_id: RequestId) -> Self {
+        match self {
+            Self::Request { .. } => self,
+            other => Self::Request {
+                request_id,
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// Removes the URLs, and the credentials they may contain, from a transport error.
+    #[cfg(feature = "sunxdcc")]
+    pub(crate) fn redacted(self) -> Self {
+        match self {
+            Self::Transport(inner) => Self::Transport(crate::redact::redact_error(inner)),
+            Self::Request { request_id, source } => Self::Request {
+                request_id,
+                source: Box::new(source.redacted()),
+            },
+            other => other,
+        }
+    }
+}
+
+/// A `Result` alias where the error defaults to [`Error`].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+
+    #[test_case::test_case(408, true, false; "request timeout")]
+    #[test_case::test_case(429, true, true; "too many requests")]
+    #[test_case::test_case(404, false, false; "not found")]
+    #[test_case::test_case(503, true, false; "service unavailable")]
+    fn should_classify_status(code: u16, retryable: bool, rate_limited: bool) {
+        let error = Error::Status(code);
+        assert_eq!(error.status(), Some(code));
+        assert_eq!(error.is_retryable(), retryable);
+        assert_eq!(error.is_rate_limited(), rate_limited);
+    }
+
+    #[test]
+    fn should_not_retry_invalid_body() {
+        let error = Error::from(serde_json::from_str::<u8>("nope").unwrap_err());
+        assert_eq!(error.status(), None);
+        assert!(!error.is_retryable());
+    }
+
+    #[test]
+    fn should_not_retry_too_large_body() {
+        assert!(!Error::BodyTooLarge { limit: 10 }.is_retryable());
+    }
+
+    #[test]
+    fn should_classify_error_with_request_id() {
+        let error = Error::Request {
+            request_id: 255.into(),
+            source: Box::new(Error::Status(503)),
+        };
+        assert_eq!(error.request_id(), Some(255.into()));
+        assert!(matches!(error.inner(), Error::Status(503)));
+        assert_eq!(error.status(), Some(503));
+        assert!(error.is_retryable());
+        assert_eq!(Error::Status(503).request_id(), None);
+    }
+}
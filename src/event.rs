@@ -0,0 +1,250 @@
+//! The events published by the engines, on a broadcast [`EventBus`].
+//!
+//! An application subscribes once to the bus given to its engines, instead of
+//! registering a callback per feature, and receives the [`Event`]s as a stream.
+//! The bus doesn't depend on an async runtime: each [`Subscription`] buffers the
+//! latest events, dropping the oldest ones when it isn't consumed fast enough.
+//!
+//! ```
+//! use xdcc_search::event::{Event, EventBus};
+//!
+//! let bus = EventBus::default();
+//! let mut subscription = bus.subscribe();
+//! bus.publish(Event::SearchCompleted {
+//!     provider: "sunxdcc".into(),
+//!     query: "ubuntu".into(),
+//!     page: 0,
+//!     results: 12,
+//! });
+//! assert!(matches!(subscription.try_next(), Some(Event::SearchCompleted { results: 12, .. })));
+//! ```
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll, Waker};
+
+use futures_util::Stream;
+
+use crate::drift::FormatDrift;
+
+/// The default number of events buffered by a subscription.
+const DEFAULT_CAPACITY: usize = 256;
+
+/// Something that happened in an engine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Event {
+    /// A page of results was received.
+    SearchCompleted {
+        /// The name of the provider.
+        provider: String,
+        /// The search term.
+        query: String,
+        /// The page number.
+        page: u8,
+        /// The number of entries decoded, before the pipeline is applied.
+        results: usize,
+    },
+    /// A request failed in a way suggesting the provider is unavailable (a
+    /// transport failure or a transient status), see [`crate::Error::is_retryable`].
+    ProviderDown {
+        /// The name of the provider.
+        provider: String,
+        /// The description of the error.
+        error: String,
+    },
+    /// The rows of a provider started failing to decode, see [`crate::drift`].
+    FormatDrift(FormatDrift),
+}
+
+#[derive(Debug)]
+struct Queue {
+    events: VecDeque<Event>,
+    lagged: u64,
+    waker: Option<Waker>,
+}
+
+#[derive(Debug)]
+struct Subscriber {
+    capacity: usize,
+    queue: Mutex<Queue>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    capacity: usize,
+    subscribers: Mutex<Vec<Weak<Subscriber>>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // ends the streams of the subscriptions
+        for subscriber in self.subscribers.get_mut().unwrap().drain(..) {
+            if let Some(subscriber) = subscriber.upgrade()
+                && let Some(waker) = subscriber.queue.lock().unwrap().waker.take()
+            {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// A broadcast channel of [`Event`]s, cheap to clone.
+#[derive(Clone, Debug)]
+pub struct EventBus(Arc<Inner>);
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl EventBus {
+    /// Creates a bus whose subscriptions buffer at most `capacity` events.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(Arc::new(Inner {
+            capacity: capacity.max(1),
+            subscribers: Mutex::default(),
+        }))
+    }
+
+    /// Subscribes to the events published from now on.
+    pub fn subscribe(&self) -> Subscription {
+        let subscriber = Arc::new(Subscriber {
+            capacity: self.0.capacity,
+            queue: Mutex::new(Queue {
+                events: VecDeque::new(),
+                lagged: 0,
+                waker: None,
+            }),
+        });
+        self.0
+            .subscribers
+            .lock()
+            .unwrap()
+            .push(Arc::downgrade(&subscriber));
+        Subscription {
+            subscriber,
+            bus: Arc::downgrade(&self.0),
+        }
+    }
+
+    /// Sends the event to all the subscriptions, forgetting the dropped ones.
+    pub fn publish(&self, event: Event) {
+        let mut subscribers = self.0.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| {
+            let Some(subscriber) = subscriber.upgrade() else {
+                return false;
+            };
+            let mut queue = subscriber.queue.lock().unwrap();
+            if queue.events.len() == subscriber.capacity {
+                queue.events.pop_front();
+                queue.lagged += 1;
+            }
+            queue.events.push_back(event.clone());
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+            true
+        });
+    }
+
+    /// Returns the number of active subscriptions.
+    pub fn subscriber_count(&self) -> usize {
+        let mut subscribers = self.0.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.strong_count() > 0);
+        subscribers.len()
+    }
+}
+
+/// The events received from an [`EventBus`], as a stream ending once all the
+/// clones of the bus are dropped.
+#[derive(Debug)]
+pub struct Subscription {
+    subscriber: Arc<Subscriber>,
+    bus: Weak<Inner>,
+}
+
+impl Subscription {
+    /// Returns the next buffered event, if any, without waiting.
+    pub fn try_next(&mut self) -> Option<Event> {
+        self.subscriber.queue.lock().unwrap().events.pop_front()
+    }
+
+    /// Returns the number of events dropped so far because the buffer was full.
+    pub fn lagged(&self) -> u64 {
+        self.subscriber.queue.lock().unwrap().lagged
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Event>> {
+        let mut queue = self.subscriber.queue.lock().unwrap();
+        if let Some(event) = queue.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if self.bus.strong_count() == 0 {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+
+    fn down(error: &str) -> Event {
+        Event::ProviderDown {
+            provider: "sunxdcc".into(),
+            error: error.into(),
+        }
+    }
+
+    #[test]
+    fn should_broadcast_to_all_subscriptions() {
+        let bus = EventBus::default();
+        let mut first = bus.subscribe();
+        bus.publish(down("before"));
+        let mut second = bus.subscribe();
+        bus.publish(down("after"));
+        assert_eq!(first.try_next(), Some(down("before")));
+        assert_eq!(first.try_next(), Some(down("after")));
+        assert_eq!(second.try_next(), Some(down("after")));
+        assert_eq!(second.try_next(), None);
+        drop(first);
+        assert_eq!(bus.subscriber_count(), 1);
+    }
+
+    #[test]
+    fn should_drop_oldest_events_when_lagging() {
+        let bus = EventBus::with_capacity(2);
+        let mut subscription = bus.subscribe();
+        for error in ["a", "b", "c"] {
+            bus.publish(down(error));
+        }
+        assert_eq!(subscription.lagged(), 1);
+        assert_eq!(subscription.try_next(), Some(down("b")));
+        assert_eq!(subscription.try_next(), Some(down("c")));
+    }
+
+    #[tokio::test]
+    async fn should_stream_until_bus_dropped() {
+        let bus = EventBus::default();
+        let subscription = bus.subscribe();
+        let publisher = tokio::spawn(async move {
+            tokio::task::yield_now().await;
+            bus.publish(down("a"));
+            bus.publish(down("b"));
+        });
+        let events = subscription.collect::<Vec<_>>().await;
+        publisher.await.unwrap();
+        assert_eq!(events, [down("a"), down("b")]);
+    }
+}
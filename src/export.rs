@@ -0,0 +1,526 @@
+//! Export of entries for external downloaders.
+//!
+//! For the users searching with this crate but downloading with their existing
+//! tools: [`Exporter::Xdccget`] generates shell lines invoking
+//! [xdccget](https://github.com/Fabian-G/xdccget), [`Exporter::Irssi`] and
+//! [`Exporter::Weechat`] generate commands to paste in the IRC client.
+//!
+//! xdccget connects to a server where the entries only give the name of the
+//! network: the servers of the networks are set with [`Servers`].
+//!
+//! [`Exporter::script`] generates ready-to-load scripts instead, spacing out the
+//! requests so the bots, and the networks, don't see them as flooding.
+//!
+//! The bots supporting it (iroffer-dinoex) accept ranges of packs in a single
+//! `xdcc batch <first>-<last>` request: [`Exporter::export_batched`] and
+//! [`Exporter::script_batched`] group the consecutive packs of a bot in a
+//! [`PackRange`] instead of sending them one by one.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::entry::Entry;
+use crate::name::Network;
+
+/// The servers to connect to for each network, see [`Exporter::Xdccget`].
+///
+/// ```
+/// use xdcc_search::export::Servers;
+///
+/// let servers = Servers::default().server("Rizon".parse().unwrap(), "irc.rizon.net");
+/// assert_eq!(servers.get(&"rizon".parse().unwrap()), "irc.rizon.net");
+/// assert_eq!(servers.get(&"irc.abjects.net".parse().unwrap()), "irc.abjects.net");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Servers(HashMap<Network, String>);
+
+impl Servers {
+    /// Sets the host of the server to connect to for a network.
+    pub fn server(mut self, network: Network, host: impl Into<String>) -> Self {
+        self.0.insert(network, host.into());
+        self
+    }
+
+    /// Returns the host of the server of a network, the name of the network
+    /// when it has none.
+    pub fn get<'a>(&'a self, network: &'a Network) -> &'a str {
+        self.0
+            .get(network)
+            .map(String::as_str)
+            .unwrap_or(network.as_str())
+    }
+}
+
+/// The tool the entries are exported for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Exporter {
+    /// One `xdccget <server> <channel> "<bot> xdcc send #<pack>"` line per entry.
+    ///
+    /// xdccget expects the host of a server: the network names are replaced with
+    /// the servers set in [`Servers`], and passed as is for the other networks.
+    Xdccget(Servers),
+    /// Irssi commands: connecting to the networks, joining the channels and requesting the packs.
+    Irssi,
+    /// WeeChat commands: connecting to the networks, joining the channels and requesting the packs.
+    Weechat,
+}
+
+/// A range of consecutive packs of a bot, requested at once with `xdcc batch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PackRange {
+    /// The first pack number of the range.
+    pub first: u64,
+    /// The last pack number of the range, included.
+    pub last: u64,
+}
+
+impl PackRange {
+    /// A range made of a single pack.
+    pub fn single(packnum: u64) -> Self {
+        Self {
+            first: packnum,
+            last: packnum,
+        }
+    }
+
+    /// Parses a pack number (`5` or `#5`) or a range of packs (`10-20` or `#10-#20`),
+    /// as written in the packlists and the announces.
+    ///
+    /// ```
+    /// use xdcc_search::export::PackRange;
+    ///
+    /// assert_eq!(PackRange::parse("#10-20"), Some(PackRange { first: 10, last: 20 }));
+    /// assert_eq!(PackRange::parse("#5"), Some(PackRange::single(5)));
+    /// assert_eq!(PackRange::parse("20-10"), None);
+    /// ```
+    pub fn parse(value: &str) -> Option<Self> {
+        fn packnum(value: &str) -> Option<u64> {
+            let value = value.trim();
+            value.strip_prefix('#').unwrap_or(value).parse().ok()
+        }
+        let range = match value.split_once('-') {
+            Some((first, last)) => Self {
+                first: packnum(first)?,
+                last: packnum(last)?,
+            },
+            None => Self::single(packnum(value)?),
+        };
+        (range.first <= range.last).then_some(range)
+    }
+
+    /// The number of packs in the range, saturating at `u64::MAX`.
+    pub fn len(&self) -> u64 {
+        (self.last - self.first).saturating_add(1)
+    }
+
+    /// Always `false`, a range holds at least one pack.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// The request to send to the bot: `xdcc send #<pack>` for a single pack,
+    /// `xdcc batch <first>-<last>` otherwise.
+    pub fn command(&self) -> String {
+        if self.first == self.last {
+            format!("xdcc send #{}", self.first)
+        } else {
+            format!("xdcc batch {}-{}", self.first, self.last)
+        }
+    }
+
+    /// Groups the entries following each other with consecutive pack numbers of the
+    /// same bot, keeping the order. Each range comes with its first entry, giving the
+    /// network, channel and bot to request it from.
+    pub fn group<'a, I>(entries: I) -> Vec<(&'a Entry, PackRange)>
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        let mut ranges: Vec<(&Entry, PackRange)> = Vec::new();
+        for entry in entries {
+            match ranges.last_mut() {
+                Some((first, range))
+                    if first.network == entry.network
+                        && first.channel == entry.channel
+                        && first.bot_name == entry.bot_name
+                        && range.last.checked_add(1) == Some(entry.packnum) =>
+                {
+                    range.last = entry.packnum;
+                }
+                _ => ranges.push((entry, Self::single(entry.packnum))),
+            }
+        }
+        ranges
+    }
+}
+
+impl std::fmt::Display for PackRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.first == self.last {
+            write!(f, "#{}", self.first)
+        } else {
+            write!(f, "#{}-{}", self.first, self.last)
+        }
+    }
+}
+
+/// Quotes a value for a Perl single quoted string.
+fn perl_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+/// Quotes a value for a POSIX shell.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+impl Exporter {
+    /// Exports the entries, in the given order.
+    pub fn export<'a, I>(&self, entries: I) -> String
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        self.export_ranges(singles(entries))
+    }
+
+    /// Exports the entries like [`Exporter::export`], requesting the consecutive
+    /// packs of a bot with a single `xdcc batch` request, see [`PackRange::group`].
+    pub fn export_batched<'a, I>(&self, entries: I) -> String
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        self.export_ranges(PackRange::group(entries))
+    }
+
+    fn export_ranges(&self, ranges: Vec<(&Entry, PackRange)>) -> String {
+        let mut output = String::new();
+        match self {
+            Self::Xdccget(servers) => {
+                for (entry, range) in ranges {
+                    let command = format!("{} {}", entry.bot_name, range.command());
+                    let _ = writeln!(
+                        output,
+                        "xdccget {} {} {}",
+                        shell_quote(servers.get(&entry.network)),
+                        shell_quote(entry.channel.as_str()),
+                        shell_quote(&command),
+                    );
+                }
+            }
+            Self::Irssi | Self::Weechat => {
+                let mut connected = Vec::new();
+                let mut joined = Vec::new();
+                for (entry, range) in ranges {
+                    let network = entry.network.as_str();
+                    if !connected.contains(&&entry.network) {
+                        connected.push(&entry.network);
+                        let _ = writeln!(output, "/connect {network}");
+                    }
+                    let target = match self {
+                        Self::Irssi => format!("-{network}"),
+                        _ => format!("-server {network}"),
+                    };
+                    if !joined.contains(&(&entry.network, &entry.channel)) {
+                        joined.push((&entry.network, &entry.channel));
+                        let _ = writeln!(output, "/join {target} {}", entry.channel);
+                    }
+                    let _ = writeln!(
+                        output,
+                        "/msg {target} {} {}",
+                        entry.bot_name,
+                        range.command()
+                    );
+                }
+            }
+        }
+        output
+    }
+
+    /// Generates a script requesting the packs one after the other, waiting `delay`
+    /// after joining a channel and between two requests:
+    ///
+    /// * [`Exporter::Xdccget`]: a shell script calling `sleep` between the downloads,
+    /// * [`Exporter::Irssi`]: a Perl script, loaded with `/script load <file>`,
+    /// * [`Exporter::Weechat`]: commands scheduled with `/wait`, executed with
+    ///   `/exec -sh -oc cat <file>`.
+    pub fn script<'a, I>(&self, entries: I, delay: Duration) -> String
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        self.script_ranges(singles(entries), delay)
+    }
+
+    /// Generates a script like [`Exporter::script`], requesting the consecutive
+    /// packs of a bot with a single `xdcc batch` request, see [`PackRange::group`].
+    pub fn script_batched<'a, I>(&self, entries: I, delay: Duration) -> String
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        self.script_ranges(PackRange::group(entries), delay)
+    }
+
+    fn script_ranges(&self, ranges: Vec<(&Entry, PackRange)>, delay: Duration) -> String {
+        let mut output = String::new();
+        if let Self::Xdccget(_) = self {
+            output.push_str("#!/bin/sh\nset -e\n");
+            for (index, line) in self.export_ranges(ranges).lines().enumerate() {
+                if index > 0 {
+                    let _ = writeln!(output, "sleep {}", delay.as_secs());
+                }
+                let _ = writeln!(output, "{line}");
+            }
+            return output;
+        }
+        // (delay, network, command), the delays being counted from the start
+        let mut scheduled: Vec<(Duration, &str, String)> = Vec::new();
+        let mut networks = Vec::new();
+        let mut joined = Vec::new();
+        let mut at = delay;
+        for (entry, range) in &ranges {
+            if !networks.contains(&entry.network.as_str()) {
+                networks.push(entry.network.as_str());
+            }
+            if !joined.contains(&(&entry.network, &entry.channel)) {
+                joined.push((&entry.network, &entry.channel));
+                scheduled.push((
+                    at,
+                    entry.network.as_str(),
+                    format!("join {}", entry.channel),
+                ));
+                at += delay;
+            }
+            let request = format!("msg {} {}", entry.bot_name, range.command());
+            scheduled.push((at, entry.network.as_str(), request));
+            at += delay;
+        }
+        if *self == Self::Weechat {
+            for network in networks {
+                let _ = writeln!(output, "/connect {network}");
+            }
+            for (at, network, command) in scheduled {
+                let (name, args) = command.split_once(' ').unwrap_or((&command, ""));
+                let _ = writeln!(
+                    output,
+                    "/wait {}s /{name} -server {network} {args}",
+                    at.as_secs()
+                );
+            }
+            return output;
+        }
+        output.push_str(IRSSI_HEADER);
+        for network in networks {
+            let _ = writeln!(
+                output,
+                "Irssi::command({});",
+                perl_quote(&format!("connect {network}"))
+            );
+        }
+        for (at, network, command) in scheduled {
+            let _ = writeln!(
+                output,
+                "later({}, {}, {});",
+                at.as_millis().max(10),
+                perl_quote(network),
+                perl_quote(&command)
+            );
+        }
+        output
+    }
+}
+
+/// Requests each entry on its own.
+fn singles<'a, I>(entries: I) -> Vec<(&'a Entry, PackRange)>
+where
+    I: IntoIterator<Item = &'a Entry>,
+{
+    entries
+        .into_iter()
+        .map(|entry| (entry, PackRange::single(entry.packnum)))
+        .collect()
+}
+
+const IRSSI_HEADER: &str = r#"use strict;
+use warnings;
+use Irssi;
+
+our %IRSSI = (
+    name        => 'xdcc-search',
+    description => 'Requests the packs exported by xdcc-search',
+);
+
+sub later {
+    my ($delay, $network, $command) = @_;
+    Irssi::timeout_add_once($delay, sub {
+        my $server = Irssi::server_find_chatnet($network);
+        if ($server) {
+            $server->command($command);
+        } else {
+            Irssi::print("xdcc-search: not connected to $network, skipping: $command");
+        }
+    }, undef);
+}
+
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        let mut first = crate::process::tests::entry(1, 0);
+        first.bot_name = "Bot|01".parse().unwrap();
+        let second = crate::process::tests::entry(2, 0);
+        let mut third = crate::process::tests::entry(3, 0);
+        third.channel = "#other".parse().unwrap();
+        vec![first, second, third]
+    }
+
+    #[test]
+    fn should_export_for_xdccget() {
+        assert_eq!(
+            Exporter::Xdccget(Servers::default()).export(&entries()),
+            "xdccget 'net' '#chan' 'Bot|01 xdcc send #1'\n\
+             xdccget 'net' '#chan' 'bot xdcc send #2'\n\
+             xdccget 'net' '#other' 'bot xdcc send #3'\n"
+        );
+    }
+
+    #[test]
+    fn should_export_servers_for_xdccget() {
+        let servers = Servers::default().server("NET".parse().unwrap(), "irc.example.net");
+        let mut entries = entries();
+        entries[2].network = "other".parse().unwrap();
+        assert_eq!(
+            Exporter::Xdccget(servers).export(&entries),
+            "xdccget 'irc.example.net' '#chan' 'Bot|01 xdcc send #1'\n\
+             xdccget 'irc.example.net' '#chan' 'bot xdcc send #2'\n\
+             xdccget 'other' '#other' 'bot xdcc send #3'\n"
+        );
+    }
+
+    #[test_case::test_case(Exporter::Irssi, "-net"; "irssi")]
+    #[test_case::test_case(Exporter::Weechat, "-server net"; "weechat")]
+    fn should_export_for_irc_client(exporter: Exporter, target: &str) {
+        assert_eq!(
+            exporter.export(&entries()),
+            format!(
+                "/connect net\n\
+                 /join {target} #chan\n\
+                 /msg {target} Bot|01 xdcc send #1\n\
+                 /msg {target} bot xdcc send #2\n\
+                 /join {target} #other\n\
+                 /msg {target} bot xdcc send #3\n"
+            )
+        );
+    }
+
+    #[test]
+    fn should_generate_shell_script() {
+        let script =
+            Exporter::Xdccget(Servers::default()).script(&entries()[1..], Duration::from_secs(5));
+        assert_eq!(
+            script,
+            "#!/bin/sh\nset -e\n\
+             xdccget 'net' '#chan' 'bot xdcc send #2'\n\
+             sleep 5\n\
+             xdccget 'net' '#other' 'bot xdcc send #3'\n"
+        );
+    }
+
+    #[test]
+    fn should_generate_weechat_script() {
+        let script = Exporter::Weechat.script(&entries(), Duration::from_secs(30));
+        assert_eq!(
+            script,
+            "/connect net\n\
+             /wait 30s /join -server net #chan\n\
+             /wait 60s /msg -server net Bot|01 xdcc send #1\n\
+             /wait 90s /msg -server net bot xdcc send #2\n\
+             /wait 120s /join -server net #other\n\
+             /wait 150s /msg -server net bot xdcc send #3\n"
+        );
+    }
+
+    #[test]
+    fn should_generate_irssi_script() {
+        let script = Exporter::Irssi.script(&entries()[..1], Duration::from_secs(30));
+        assert!(script.starts_with(IRSSI_HEADER));
+        assert_eq!(
+            &script[IRSSI_HEADER.len()..],
+            "Irssi::command('connect net');\n\
+             later(30000, 'net', 'join #chan');\n\
+             later(60000, 'net', 'msg Bot|01 xdcc send #1');\n"
+        );
+    }
+
+    #[test_case::test_case("12", Some(PackRange::single(12)); "single")]
+    #[test_case::test_case(" #12 ", Some(PackRange::single(12)); "single with hash")]
+    #[test_case::test_case("#10-#20", Some(PackRange { first: 10, last: 20 }); "range")]
+    #[test_case::test_case("10 - 20", Some(PackRange { first: 10, last: 20 }); "spaced range")]
+    #[test_case::test_case("20-10", None; "reversed")]
+    #[test_case::test_case("10-", None; "open")]
+    #[test_case::test_case("all", None; "word")]
+    fn should_parse_pack_range(value: &str, expected: Option<PackRange>) {
+        assert_eq!(PackRange::parse(value), expected);
+    }
+
+    #[test]
+    fn should_group_consecutive_packs() {
+        let mut entries = (1..=4)
+            .map(|packnum| crate::process::tests::entry(packnum, 0))
+            .collect::<Vec<_>>();
+        entries[3].packnum = 6;
+        entries.push(crate::process::tests::entry(7, 0));
+        entries[4].bot_name = "other".parse().unwrap();
+        let ranges = PackRange::group(&entries)
+            .into_iter()
+            .map(|(entry, range)| (entry.bot_name.as_str(), range.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            vec![
+                ("bot", "#1-3".into()),
+                ("bot", "#6".into()),
+                ("other", "#7".into())
+            ]
+        );
+        assert_eq!(PackRange::group(&entries)[0].1.len(), 3);
+    }
+
+    #[test]
+    fn should_saturate_length_of_full_range() {
+        let range = PackRange::parse(&format!("0-{}", u64::MAX)).unwrap();
+        assert_eq!(range.len(), u64::MAX);
+    }
+
+    #[test]
+    fn should_export_batched() {
+        let mut entries = entries();
+        entries.insert(2, crate::process::tests::entry(3, 0));
+        assert_eq!(
+            Exporter::Xdccget(Servers::default()).export_batched(&entries),
+            "xdccget 'net' '#chan' 'Bot|01 xdcc send #1'\n\
+             xdccget 'net' '#chan' 'bot xdcc batch 2-3'\n\
+             xdccget 'net' '#other' 'bot xdcc send #3'\n"
+        );
+        let script = Exporter::Weechat.script_batched(&entries[1..], Duration::from_secs(30));
+        assert_eq!(
+            script,
+            "/connect net\n\
+             /wait 30s /join -server net #chan\n\
+             /wait 60s /msg -server net bot xdcc batch 2-3\n\
+             /wait 90s /join -server net #other\n\
+             /wait 120s /msg -server net bot xdcc send #3\n"
+        );
+    }
+
+    #[test]
+    fn should_quote_for_perl() {
+        assert_eq!(perl_quote(r"it's \o/"), r"'it\'s \\o/'");
+    }
+
+    #[test]
+    fn should_quote_for_shell() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,268 @@
+//! A small boolean query language, to express complex filters as a single string.
+//!
+//! ```text
+//! ubuntu AND (22.04 OR 24.04) NOT beta
+//! ```
+//!
+//! The terms are matched with [`Matcher`], ignoring case, accents and separators.
+//! `AND`, `OR` and `NOT` must be written in uppercase, adjacent terms are implicitly
+//! combined with `AND`, parentheses group expressions and double quotes keep
+//! keywords as plain terms (`"AND"`). `NOT` binds tighter than `AND`, which binds
+//! tighter than `OR`.
+//!
+//! The providers only support a plain search term: [`Expression::search_term`]
+//! returns the broadest term to send, the whole expression being applied
+//! client-side on the results.
+
+use crate::matching::Matcher;
+
+/// A parsed boolean expression, see the [module documentation](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expression {
+    /// A term that must be found in the filename.
+    Term {
+        /// The term, as written.
+        text: String,
+        /// The normalized term.
+        matcher: Matcher,
+    },
+    /// All the expressions must match.
+    And(Vec<Expression>),
+    /// At least one of the expressions must match.
+    Or(Vec<Expression>),
+    /// The expression must not match.
+    Not(Box<Expression>),
+}
+
+/// The error returned when parsing an invalid [`Expression`].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseError {
+    /// The expression is empty, or ends where a term was expected.
+    #[error("unexpected end of expression")]
+    UnexpectedEnd,
+    /// A keyword or a parenthesis was found where a term was expected.
+    #[error("unexpected {0:?}")]
+    UnexpectedToken(String),
+    /// A parenthesis or a double quote was not closed.
+    #[error("unclosed {0:?}")]
+    Unclosed(char),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Term(String),
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Self::Open => "(".into(),
+            Self::Close => ")".into(),
+            Self::And => "AND".into(),
+            Self::Or => "OR".into(),
+            Self::Not => "NOT".into(),
+            Self::Term(value) => value.clone(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(current) = chars.next() {
+        match current {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => value.push(c),
+                        None => return Err(ParseError::Unclosed('"')),
+                    }
+                }
+                tokens.push(Token::Term(value));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut value = String::from(c);
+                while let Some(next) = chars.next_if(|c| !c.is_whitespace() && !"()\"".contains(*c))
+                {
+                    value.push(next);
+                }
+                tokens.push(match value.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Term(value),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+}
+
+impl Parser {
+    fn or(&mut self) -> Result<Expression, ParseError> {
+        let mut items = vec![self.and()?];
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            items.push(self.and()?);
+        }
+        Ok(Expression::flatten(items, Expression::Or))
+    }
+
+    fn and(&mut self) -> Result<Expression, ParseError> {
+        let mut items = vec![self.unary()?];
+        loop {
+            match self.tokens.peek() {
+                Some(Token::And) => {
+                    self.tokens.next();
+                    items.push(self.unary()?);
+                }
+                Some(Token::Open | Token::Not | Token::Term(_)) => items.push(self.unary()?),
+                _ => return Ok(Expression::flatten(items, Expression::And)),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, ParseError> {
+        match self.tokens.next() {
+            Some(Token::Not) => Ok(Expression::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let inner = self.or()?;
+                match self.tokens.next() {
+                    Some(Token::Close) => Ok(inner),
+                    Some(other) => Err(ParseError::UnexpectedToken(other.describe())),
+                    None => Err(ParseError::Unclosed('(')),
+                }
+            }
+            Some(Token::Term(text)) => Ok(Expression::term(text)),
+            Some(other) => Err(ParseError::UnexpectedToken(other.describe())),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+}
+
+impl Expression {
+    fn term(text: String) -> Self {
+        let matcher = Matcher::new(&text);
+        Self::Term { text, matcher }
+    }
+
+    fn flatten(mut items: Vec<Self>, build: fn(Vec<Self>) -> Self) -> Self {
+        if items.len() == 1 {
+            items.remove(0)
+        } else {
+            build(items)
+        }
+    }
+
+    /// Parses an expression.
+    ///
+    /// ```
+    /// use xdcc_search::expression::Expression;
+    ///
+    /// let expression = Expression::parse("ubuntu AND (22.04 OR 24.04) NOT beta").unwrap();
+    /// assert_eq!(expression.search_term(), "ubuntu");
+    /// assert!(expression.matches("ubuntu-24.04-desktop-amd64.iso"));
+    /// assert!(!expression.matches("ubuntu-24.04-beta-desktop-amd64.iso"));
+    /// ```
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?.into_iter().peekable(),
+        };
+        let result = parser.or()?;
+        match parser.tokens.next() {
+            Some(token) => Err(ParseError::UnexpectedToken(token.describe())),
+            None => Ok(result),
+        }
+    }
+
+    /// Returns `true` if the text, usually a filename, matches the expression.
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            Self::Term { matcher, .. } => matcher.matches(text),
+            Self::And(items) => items.iter().all(|item| item.matches(text)),
+            Self::Or(items) => items.iter().any(|item| item.matches(text)),
+            Self::Not(inner) => !inner.matches(text),
+        }
+    }
+
+    /// Returns the terms every matching text contains, joined with spaces.
+    ///
+    /// This is the broadest search to send to a provider: its results contain all
+    /// the texts matching the expression. It is empty when no term is required,
+    /// e.g. for `a OR b`.
+    pub fn search_term(&self) -> String {
+        self.required_terms().join(" ")
+    }
+
+    fn required_terms(&self) -> Vec<String> {
+        match self {
+            Self::Term { text, .. } => vec![text.clone()],
+            Self::And(items) => {
+                let mut result: Vec<String> = Vec::new();
+                for term in items.iter().flat_map(Self::required_terms) {
+                    if !result.contains(&term) {
+                        result.push(term);
+                    }
+                }
+                result
+            }
+            Self::Or(items) => {
+                let mut iter = items.iter().map(Self::required_terms);
+                let first = iter.next().unwrap_or_default();
+                iter.fold(first, |acc, terms| {
+                    acc.into_iter()
+                        .filter(|term| terms.contains(term))
+                        .collect()
+                })
+            }
+            Self::Not(_) => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expression, ParseError};
+
+    #[test_case::test_case("ubuntu", "ubuntu"; "single term")]
+    #[test_case::test_case("ubuntu desktop", "ubuntu desktop"; "implicit and")]
+    #[test_case::test_case("ubuntu AND (22.04 OR 24.04) NOT beta", "ubuntu"; "nested")]
+    #[test_case::test_case("(ubuntu 24.04) OR (ubuntu 22.04)", "ubuntu"; "common term")]
+    #[test_case::test_case("debian OR ubuntu", ""; "no common term")]
+    #[test_case::test_case("\"tom and jerry\" NOT cam", "tom and jerry"; "quoted")]
+    fn should_compute_search_term(input: &str, expected: &str) {
+        assert_eq!(Expression::parse(input).unwrap().search_term(), expected);
+    }
+
+    #[test_case::test_case("Ubuntu.24.04.iso", true; "matching")]
+    #[test_case::test_case("Ubuntu.20.04.iso", false; "wrong version")]
+    #[test_case::test_case("Ubuntu.24.04.Beta.iso", false; "excluded")]
+    #[test_case::test_case("Debian.24.04.iso", false; "missing term")]
+    fn should_match(input: &str, expected: bool) {
+        let expression = Expression::parse("ubuntu AND (22.04 OR 24.04) NOT beta").unwrap();
+        assert_eq!(expression.matches(input), expected);
+    }
+
+    #[test_case::test_case("", ParseError::UnexpectedEnd; "empty")]
+    #[test_case::test_case("ubuntu AND", ParseError::UnexpectedEnd; "trailing and")]
+    #[test_case::test_case("(ubuntu", ParseError::Unclosed('('); "unclosed parenthesis")]
+    #[test_case::test_case("\"ubuntu", ParseError::Unclosed('"'); "unclosed quote")]
+    #[test_case::test_case("ubuntu)", ParseError::UnexpectedToken(")".into()); "extra parenthesis")]
+    #[test_case::test_case("OR ubuntu", ParseError::UnexpectedToken("OR".into()); "leading or")]
+    fn shouldnt_parse(input: &str, expected: ParseError) {
+        assert_eq!(Expression::parse(input), Err(expected));
+    }
+}
@@ -0,0 +1,198 @@
+//! Human readable formatting of the file sizes and transfer speeds.
+//!
+//! [`ByteSize`] and [`Speed`] display with binary units and one decimal by default
+//! (`1.2 GiB`, `114.0 KiB/s`). A [`Format`] changes the unit system, the precision
+//! and the decimal separator, to follow the conventions of the users.
+//!
+//! ```
+//! use xdcc_search::format::{ByteSize, Format, UnitSystem};
+//!
+//! assert_eq!(ByteSize(1288490188).to_string(), "1.2 GiB");
+//! let format = Format::default()
+//!     .system(UnitSystem::Si)
+//!     .precision(2)
+//!     .decimal_separator(',');
+//! assert_eq!(ByteSize(1288490188).display(format).to_string(), "1,29 GB");
+//! ```
+
+use std::fmt;
+
+/// The unit system used to display sizes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitSystem {
+    /// Powers of 1024, with the `KiB`, `MiB`... labels.
+    #[default]
+    Iec,
+    /// Powers of 1000, with the `kB`, `MB`... labels.
+    Si,
+}
+
+impl UnitSystem {
+    fn base(self) -> f64 {
+        match self {
+            Self::Iec => 1024.0,
+            Self::Si => 1000.0,
+        }
+    }
+
+    fn labels(self) -> &'static [&'static str] {
+        match self {
+            Self::Iec => &["B", "KiB", "MiB", "GiB", "TiB", "PiB"],
+            Self::Si => &["B", "kB", "MB", "GB", "TB", "PB"],
+        }
+    }
+}
+
+/// Options used to display a [`ByteSize`] or a [`Speed`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Format {
+    system: UnitSystem,
+    precision: usize,
+    decimal_separator: char,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            system: UnitSystem::Iec,
+            precision: 1,
+            decimal_separator: '.',
+        }
+    }
+}
+
+impl Format {
+    /// Sets the unit system, [`UnitSystem::Iec`] by default.
+    pub fn system(mut self, system: UnitSystem) -> Self {
+        self.system = system;
+        self
+    }
+
+    /// Sets the number of decimals, 1 by default. Values in bytes never have decimals.
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// Sets the decimal separator, `.` by default, e.g. `,` for most European locales.
+    pub fn decimal_separator(mut self, separator: char) -> Self {
+        self.decimal_separator = separator;
+        self
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, bytes: u64, suffix: &str) -> fmt::Result {
+        let labels = self.system.labels();
+        let base = self.system.base();
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= base && unit + 1 < labels.len() {
+            value /= base;
+            unit += 1;
+        }
+        if unit == 0 {
+            return write!(f, "{bytes} {}{suffix}", labels[0]);
+        }
+        let number = format!("{value:.precision$}", precision = self.precision);
+        match number.split_once('.') {
+            Some((integer, decimals)) => write!(
+                f,
+                "{integer}{}{decimals} {}{suffix}",
+                self.decimal_separator, labels[unit]
+            ),
+            None => write!(f, "{number} {}{suffix}", labels[unit]),
+        }
+    }
+}
+
+/// A size in bytes, e.g. [`Entry::filesize`](crate::entry::Entry::filesize).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// Displays the size with the given options.
+    pub fn display(self, format: Format) -> impl fmt::Display {
+        Formatted {
+            bytes: self.0,
+            format,
+            suffix: "",
+        }
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Format::default().write(f, self.0, "")
+    }
+}
+
+/// A speed in bytes per second, e.g. [`Entry::bot_speed`](crate::entry::Entry::bot_speed).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Speed(pub u64);
+
+impl Speed {
+    /// Displays the speed with the given options.
+    pub fn display(self, format: Format) -> impl fmt::Display {
+        Formatted {
+            bytes: self.0,
+            format,
+            suffix: "/s",
+        }
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Format::default().write(f, self.0, "/s")
+    }
+}
+
+struct Formatted {
+    bytes: u64,
+    format: Format,
+    suffix: &'static str,
+}
+
+impl fmt::Display for Formatted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.format.write(f, self.bytes, self.suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case::test_case(0, "0 B"; "zero")]
+    #[test_case::test_case(1023, "1023 B"; "bytes")]
+    #[test_case::test_case(1024, "1.0 KiB"; "kibi")]
+    #[test_case::test_case(1258291, "1.2 MiB"; "mebi")]
+    #[test_case::test_case(1319413953331, "1.2 TiB"; "tebi")]
+    #[test_case::test_case(u64::MAX, "16384.0 PiB"; "largest")]
+    fn should_display_size(bytes: u64, expected: &str) {
+        assert_eq!(ByteSize(bytes).to_string(), expected);
+    }
+
+    #[test_case::test_case(UnitSystem::Si, 0, '.', 1500000, "2 MB"; "no decimal")]
+    #[test_case::test_case(UnitSystem::Si, 2, ',', 1234567, "1,23 MB"; "comma")]
+    #[test_case::test_case(UnitSystem::Iec, 3, ',', 999, "999 B"; "bytes")]
+    fn should_display_size_with_format(
+        system: UnitSystem,
+        precision: usize,
+        separator: char,
+        bytes: u64,
+        expected: &str,
+    ) {
+        let format = Format::default()
+            .system(system)
+            .precision(precision)
+            .decimal_separator(separator);
+        assert_eq!(ByteSize(bytes).display(format).to_string(), expected);
+    }
+
+    #[test]
+    fn should_display_speed() {
+        assert_eq!(Speed(116748595).to_string(), "111.3 MiB/s");
+        let format = Format::default().system(UnitSystem::Si);
+        assert_eq!(Speed(12).display(format).to_string(), "12 B/s");
+    }
+}
@@ -0,0 +1,791 @@
+//! Transport abstraction used by the search engines.
+//!
+//! Engines never talk to an HTTP client directly: they describe the request they
+//! need as a [`Request`] and hand it to an [`HttpBackend`]. This keeps the parsing
+//! logic independent from any specific client, so embedders can plug in hyper, ureq
+//! or a platform-specific implementation (wasm, mobile...).
+//!
+//! A [`reqwest`](https://docs.rs/reqwest) based implementation is provided as
+//! [`ReqwestBackend`] when the `reqwest` feature is enabled (default).
+//!
+//! Nothing in this crate spawns tasks or relies on a specific executor, the runtime
+//! requirements are the ones of the backend: [`ReqwestBackend`] needs a tokio runtime,
+//! while a backend built on `surf` or `isahc` can run on async-std or smol.
+
+use std::future::Future;
+#[cfg(feature = "reqwest")]
+use std::net::SocketAddr;
+#[cfg(feature = "reqwest")]
+use std::sync::Arc;
+
+/// The header carrying the [`RequestId`] of a search.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// The `user-agent` sent by the engines, unless configured otherwise: the name
+/// and version of the crate, with the address of its repository.
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "xdcc-search/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/jdrouet/xdcc-search)"
+);
+
+/// The headers added to every request of an engine, with the `user-agent`
+/// optionally rotating from one request to the other.
+#[cfg(feature = "sunxdcc")]
+#[derive(Debug)]
+pub(crate) struct DefaultHeaders {
+    pub(crate) headers: Vec<(&'static str, String)>,
+    pub(crate) user_agents: Vec<String>,
+    next: std::sync::atomic::AtomicUsize,
+}
+
+#[cfg(feature = "sunxdcc")]
+impl Default for DefaultHeaders {
+    fn default() -> Self {
+        Self {
+            headers: Vec::new(),
+            user_agents: vec![DEFAULT_USER_AGENT.to_owned()],
+            next: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "sunxdcc")]
+impl DefaultHeaders {
+    /// Appends the default headers and the next `user-agent`, the headers
+    /// already set by the engine taking precedence.
+    pub(crate) fn apply(&self, headers: &mut Vec<(&'static str, String)>) {
+        let missing = |name: &str, headers: &[(&'static str, String)]| {
+            !headers
+                .iter()
+                .any(|(key, _)| key.eq_ignore_ascii_case(name))
+        };
+        for (name, value) in &self.headers {
+            if missing(name, headers) {
+                headers.push((name, value.clone()));
+            }
+        }
+        if !self.user_agents.is_empty() && missing("user-agent", headers) {
+            let index = self.next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            let value = self.user_agents[index % self.user_agents.len()].clone();
+            headers.push(("user-agent", value));
+        }
+    }
+}
+
+/// An identifier shared by all the requests of a search, to correlate the logs
+/// of the different components involved.
+///
+/// It is sent in the [`REQUEST_ID_HEADER`] header and recorded in the `request_id`
+/// field of the tracing spans. It is displayed as 16 hexadecimal digits and can be
+/// parsed back, to propagate the identifier received by an upstream component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RequestId(u64);
+
+impl RequestId {
+    /// Generates a random identifier.
+    pub fn generate() -> Self {
+        Self(fastrand::u64(..))
+    }
+}
+
+impl From<u64> for RequestId {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl std::str::FromStr for RequestId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(value, 16).map(Self)
+    }
+}
+
+/// A `GET` request issued by a search engine.
+#[derive(Clone, Debug)]
+pub struct Request<'a> {
+    /// The URL to query, without the query string.
+    pub url: &'a str,
+    /// The query string parameters, not yet url-encoded.
+    pub query: Vec<(&'static str, String)>,
+    /// Additional headers to send with the request.
+    pub headers: Vec<(&'static str, String)>,
+    /// The maximum size of the body the engine accepts.
+    ///
+    /// Backends should stop reading the body once this size is exceeded, the
+    /// engine then rejects the truncated response with
+    /// [`Error::BodyTooLarge`](crate::Error::BodyTooLarge).
+    pub max_body_size: Option<usize>,
+    /// The maximum duration of the whole request, reading the body included.
+    pub timeout: Option<std::time::Duration>,
+}
+
+/// The raw response returned by an [`HttpBackend`].
+#[derive(Clone, Debug)]
+pub struct Response {
+    /// The HTTP status code.
+    pub status: u16,
+    /// The response headers. Backends are free to only forward the ones they support.
+    pub headers: Vec<(String, String)>,
+    /// The full response body.
+    pub body: Vec<u8>,
+    /// The time spent in the phases of the request the backend was able to measure.
+    pub timing: Timing,
+}
+
+/// The time spent in the phases of a request, to tell if a slow search is caused
+/// by the network, the provider, or the decoding.
+///
+/// The backends fill the phases they are able to measure, the others being `None`:
+/// the [`ReqwestBackend`] only measures the time to first byte, `reqwest` doesn't
+/// expose the DNS, connection and TLS timings. The engines fill `total` and
+/// `decode`, and return the timing with the page, see
+/// [`Page::timing`](crate::entry::Page::timing).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timing {
+    /// The time spent resolving the host name.
+    pub dns: Option<std::time::Duration>,
+    /// The time spent establishing the TCP connection.
+    pub connect: Option<std::time::Duration>,
+    /// The time spent in the TLS handshake.
+    pub tls: Option<std::time::Duration>,
+    /// The time until the response headers were received, from the start of the request.
+    pub first_byte: Option<std::time::Duration>,
+    /// The time spent in the backend, reading the body included.
+    pub total: std::time::Duration,
+    /// The time spent decoding the body into entries.
+    pub decode: std::time::Duration,
+}
+
+impl Timing {
+    /// Returns the time spent reading the body, once the headers were received.
+    pub fn body(&self) -> Option<std::time::Duration> {
+        self.first_byte
+            .map(|first_byte| self.total.saturating_sub(first_byte))
+    }
+}
+
+impl Response {
+    /// Returns `true` if the status code is in the `2xx` range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Returns `true` if the server answered `304 Not Modified`.
+    pub fn is_not_modified(&self) -> bool {
+        self.status == 304
+    }
+
+    /// Returns `true` if the body, or the announced `Content-Length`, exceeds `limit` bytes.
+    pub fn exceeds(&self, limit: usize) -> bool {
+        self.body.len() > limit
+            || self
+                .header("content-length")
+                .and_then(|value| value.parse::<u64>().ok())
+                .is_some_and(|length| length > limit as u64)
+    }
+
+    /// Returns the value of the first header matching `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A client able to execute the requests issued by the search engines.
+///
+/// Implementations are only expected to send the request and collect the body:
+/// status handling and decoding are done by the engines.
+pub trait HttpBackend: Send + Sync {
+    /// The error returned when the request could not be executed.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Sends a `GET` request and returns the collected response.
+    fn get(
+        &self,
+        request: Request<'_>,
+    ) -> impl Future<Output = Result<Response, Self::Error>> + Send;
+}
+
+/// A backend always answering the same body, for the tests.
+#[cfg(all(test, feature = "sunxdcc"))]
+#[derive(Debug)]
+pub(crate) struct StaticBackend(pub &'static str);
+
+#[cfg(all(test, feature = "sunxdcc"))]
+impl HttpBackend for StaticBackend {
+    type Error = std::convert::Infallible;
+
+    async fn get(&self, _request: Request<'_>) -> Result<Response, Self::Error> {
+        Ok(Response {
+            status: 200,
+            headers: Vec::new(),
+            body: self.0.as_bytes().to_vec(),
+            timing: Timing::default(),
+        })
+    }
+}
+
+/// The default [`HttpBackend`], built on top of a [`reqwest::Client`].
+///
+/// Requests must be executed inside a tokio runtime.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, Default)]
+pub struct ReqwestBackend(reqwest::Client);
+
+#[cfg(feature = "reqwest")]
+impl ReqwestBackend {
+    /// Creates a backend using the provided, preconfigured, client.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self(client)
+    }
+
+    /// Creates a builder to configure the underlying client.
+    pub fn builder() -> ReqwestBackendBuilder {
+        ReqwestBackendBuilder::default()
+    }
+}
+
+/// The IP versions allowed when connecting to a provider.
+///
+/// Some ISPs block or poison the DNS entries of the listing sites on one of the
+/// IP versions only, forcing or preferring the other one works around it.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IpPreference {
+    /// Use the addresses in the order given by the resolver.
+    #[default]
+    Any,
+    /// Try the IPv4 addresses first, then the IPv6 ones.
+    PreferV4,
+    /// Try the IPv6 addresses first, then the IPv4 ones.
+    PreferV6,
+    /// Only connect using IPv4.
+    V4Only,
+    /// Only connect using IPv6.
+    V6Only,
+}
+
+#[cfg(feature = "reqwest")]
+impl IpPreference {
+    fn apply(self, addrs: impl Iterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+        let mut addrs: Vec<SocketAddr> = match self {
+            Self::V4Only => addrs.filter(SocketAddr::is_ipv4).collect(),
+            Self::V6Only => addrs.filter(SocketAddr::is_ipv6).collect(),
+            _ => addrs.collect(),
+        };
+        match self {
+            Self::PreferV4 => addrs.sort_by_key(SocketAddr::is_ipv6),
+            Self::PreferV6 => addrs.sort_by_key(SocketAddr::is_ipv4),
+            _ => {}
+        }
+        addrs
+    }
+}
+
+/// The minimum TLS version accepted when connecting to a provider.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    /// TLS 1.2 and above, the default.
+    #[default]
+    Tls12,
+    /// TLS 1.3 only.
+    Tls13,
+}
+
+/// A compression of the response bodies, see [`ReqwestBackendBuilder::accept_encodings`].
+///
+/// Each one requires the feature of the same name (`gzip`, `deflate` or `brotli`).
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    /// `gzip`, supported by most servers.
+    Gzip,
+    /// `deflate`.
+    Deflate,
+    /// `br`, usually the smallest bodies.
+    Brotli,
+}
+
+#[cfg(feature = "reqwest")]
+impl ContentEncoding {
+    /// Returns `true` if the feature decoding this encoding is enabled.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Gzip => cfg!(feature = "gzip"),
+            Self::Deflate => cfg!(feature = "deflate"),
+            Self::Brotli => cfg!(feature = "brotli"),
+        }
+    }
+}
+
+/// The SHA-256 fingerprint of a certificate, see [`ReqwestBackendBuilder::pin_certificate`].
+///
+/// It can be parsed from its hexadecimal representation, with or without colons,
+/// as printed by `openssl x509 -noout -fingerprint -sha256`.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CertificatePin([u8; 32]);
+
+#[cfg(feature = "reqwest")]
+impl CertificatePin {
+    /// Creates a pin from the SHA-256 digest of a DER encoded certificate.
+    pub fn new(digest: [u8; 32]) -> Self {
+        Self(digest)
+    }
+
+    /// Computes the pin of a DER encoded certificate.
+    pub fn of_certificate(der: &[u8]) -> Self {
+        let digest = ring::digest::digest(&ring::digest::SHA256, der);
+        let mut result = [0; 32];
+        result.copy_from_slice(digest.as_ref());
+        Self(result)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl std::fmt::Display for CertificatePin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The error returned when parsing an invalid [`CertificatePin`].
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("invalid certificate pin, expected 32 hexadecimal bytes")]
+pub struct InvalidCertificatePin;
+
+#[cfg(feature = "reqwest")]
+impl std::str::FromStr for CertificatePin {
+    type Err = InvalidCertificatePin;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let digits = value.replace(':', "");
+        if digits.len() != 64 || !digits.is_ascii() {
+            return Err(InvalidCertificatePin);
+        }
+        let mut result = [0; 32];
+        for (index, byte) in result.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&digits[index * 2..index * 2 + 2], 16)
+                .map_err(|_| InvalidCertificatePin)?;
+        }
+        Ok(Self(result))
+    }
+}
+
+/// The error reported when the certificate of the provider matches none of the pins.
+///
+/// It is the source of the TLS error, use [`Error::is_pinning_failure`](crate::Error::is_pinning_failure)
+/// to detect it.
+#[cfg(feature = "reqwest")]
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("certificate {fingerprint} matches none of the pinned certificates")]
+pub struct PinningError {
+    /// The fingerprint of the certificate presented by the server.
+    pub fingerprint: CertificatePin,
+}
+
+/// Verifies the certificate chain with the web PKI, then checks the pins.
+#[cfg(feature = "reqwest")]
+#[derive(Debug)]
+struct PinningVerifier {
+    inner: Arc<rustls::client::WebPkiServerVerifier>,
+    pins: Vec<CertificatePin>,
+}
+
+#[cfg(feature = "reqwest")]
+impl PinningVerifier {
+    fn check(&self, der: &[u8]) -> Result<(), PinningError> {
+        let fingerprint = CertificatePin::of_certificate(der);
+        if self.pins.contains(&fingerprint) {
+            Ok(())
+        } else {
+            Err(PinningError { fingerprint })
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl rustls::client::danger::ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::pki_types::CertificateDer<'_>,
+        intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        server_name: &rustls::pki_types::ServerName<'_>,
+        ocsp_response: &[u8],
+        now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        )?;
+        self.check(end_entity.as_ref()).map_err(|error| {
+            rustls::Error::InvalidCertificate(rustls::CertificateError::Other(rustls::OtherError(
+                Arc::new(error),
+            )))
+        })?;
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// Builder for a [`ReqwestBackend`], created with [`ReqwestBackend::builder`].
+#[cfg(feature = "reqwest")]
+#[derive(Default)]
+pub struct ReqwestBackendBuilder {
+    resolver: Option<Arc<dyn reqwest::dns::Resolve>>,
+    ip_preference: IpPreference,
+    min_tls_version: TlsVersion,
+    pins: Vec<CertificatePin>,
+    encodings: Option<Vec<ContentEncoding>>,
+}
+
+#[cfg(feature = "reqwest")]
+impl std::fmt::Debug for ReqwestBackendBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReqwestBackendBuilder")
+            .field("resolver", &self.resolver.as_ref().map(|_| ".."))
+            .field("ip_preference", &self.ip_preference)
+            .field("min_tls_version", &self.min_tls_version)
+            .field("pins", &self.pins)
+            .field("encodings", &self.encodings)
+            .finish()
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestBackendBuilder {
+    /// Uses a custom DNS resolver (e.g. DNS over HTTPS with hickory) instead of the system one.
+    pub fn resolver<R: reqwest::dns::Resolve + 'static>(mut self, resolver: R) -> Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Sets which IP versions are allowed, and in which order, when connecting.
+    pub fn ip_preference(mut self, value: IpPreference) -> Self {
+        self.ip_preference = value;
+        self
+    }
+
+    /// Sets the minimum TLS version accepted, defaults to TLS 1.2.
+    pub fn min_tls_version(mut self, value: TlsVersion) -> Self {
+        self.min_tls_version = value;
+        self
+    }
+
+    /// Only accepts a server presenting one of the pinned certificates, on top
+    /// of the usual validation against the web PKI roots.
+    ///
+    /// Can be called several times, e.g. to pin the next certificate before a rotation.
+    /// A mismatch fails the request with a transport error for which
+    /// [`Error::is_pinning_failure`](crate::Error::is_pinning_failure) returns `true`.
+    pub fn pin_certificate(mut self, pin: CertificatePin) -> Self {
+        self.pins.push(pin);
+        self
+    }
+
+    /// Sets the compressions accepted for the response bodies, reducing the transfer
+    /// size of the deep crawls on metered connections.
+    ///
+    /// By default, all the [supported](ContentEncoding::is_supported) encodings are
+    /// accepted. The encodings whose feature is disabled are ignored, and an empty
+    /// list disables the compression.
+    pub fn accept_encodings(mut self, encodings: &[ContentEncoding]) -> Self {
+        self.encodings = Some(encodings.to_vec());
+        self
+    }
+
+    fn pinned_tls_config(&self) -> rustls::ClientConfig {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let roots = Arc::new(rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        });
+        // the bundled roots are never empty
+        let inner =
+            rustls::client::WebPkiServerVerifier::builder_with_provider(roots, provider.clone())
+                .build()
+                .expect("web PKI roots should be valid");
+        let versions: &[&rustls::SupportedProtocolVersion] = match self.min_tls_version {
+            TlsVersion::Tls12 => &[&rustls::version::TLS13, &rustls::version::TLS12],
+            TlsVersion::Tls13 => &[&rustls::version::TLS13],
+        };
+        // the ring provider supports both versions
+        rustls::ClientConfig::builder_with_provider(provider)
+            .with_protocol_versions(versions)
+            .expect("ring should support TLS 1.2 and 1.3")
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(PinningVerifier {
+                inner,
+                pins: self.pins.clone(),
+            }))
+            .with_no_client_auth()
+    }
+
+    /// Builds the backend.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `reqwest::Error` if the TLS backend cannot be initialized.
+    pub fn build(self) -> reqwest::Result<ReqwestBackend> {
+        let mut builder = reqwest::Client::builder();
+        if self.pins.is_empty() {
+            builder = builder.min_tls_version(match self.min_tls_version {
+                TlsVersion::Tls12 => reqwest::tls::Version::TLS_1_2,
+                TlsVersion::Tls13 => reqwest::tls::Version::TLS_1_3,
+            });
+        } else {
+            builder = builder.use_preconfigured_tls(self.pinned_tls_config());
+        }
+        if self.resolver.is_some() || self.ip_preference != IpPreference::Any {
+            builder = builder.dns_resolver(Arc::new(PreferenceResolver {
+                inner: self.resolver,
+                preference: self.ip_preference,
+            }));
+        }
+        #[cfg(any(feature = "gzip", feature = "deflate", feature = "brotli"))]
+        if let Some(encodings) = &self.encodings {
+            #[cfg(feature = "gzip")]
+            {
+                builder = builder.gzip(encodings.contains(&ContentEncoding::Gzip));
+            }
+            #[cfg(feature = "deflate")]
+            {
+                builder = builder.deflate(encodings.contains(&ContentEncoding::Deflate));
+            }
+            #[cfg(feature = "brotli")]
+            {
+                builder = builder.brotli(encodings.contains(&ContentEncoding::Brotli));
+            }
+        }
+        builder.build().map(ReqwestBackend)
+    }
+}
+
+/// Resolver applying an [`IpPreference`] on top of a custom or the system resolver.
+#[cfg(feature = "reqwest")]
+struct PreferenceResolver {
+    inner: Option<Arc<dyn reqwest::dns::Resolve>>,
+    preference: IpPreference,
+}
+
+#[cfg(feature = "reqwest")]
+impl reqwest::dns::Resolve for PreferenceResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let inner = self.inner.clone();
+        let preference = self.preference;
+        let host = name.as_str().to_owned();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = match inner {
+                Some(inner) => inner.resolve(name).await?.collect(),
+                None => tokio::net::lookup_host((host.as_str(), 0)).await?.collect(),
+            };
+            let addrs = preference.apply(addrs.into_iter());
+            if addrs.is_empty() {
+                return Err(format!("no address matching {preference:?} for {host}").into());
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Client> for ReqwestBackend {
+    fn from(value: reqwest::Client) -> Self {
+        Self::new(value)
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl HttpBackend for ReqwestBackend {
+    type Error = reqwest::Error;
+
+    async fn get(&self, request: Request<'_>) -> Result<Response, Self::Error> {
+        let mut builder = self.0.get(request.url).query(&request.query);
+        for (name, value) in request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let started = std::time::Instant::now();
+        let mut res = builder.send().await?;
+        let first_byte = started.elapsed();
+        let status = res.status().as_u16();
+        let headers = res
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?;
+                Some((name.as_str().to_owned(), value.to_owned()))
+            })
+            .collect();
+        let limit = request.max_body_size.unwrap_or(usize::MAX);
+        let mut body = Vec::new();
+        // the content length is checked by the engine, avoid reading such a body at all
+        if res
+            .content_length()
+            .is_none_or(|length| length <= limit as u64)
+        {
+            while let Some(chunk) = res.chunk().await? {
+                body.extend_from_slice(&chunk);
+                if body.len() > limit {
+                    break;
+                }
+            }
+        }
+        Ok(Response {
+            status,
+            headers,
+            body,
+            timing: Timing {
+                first_byte: Some(first_byte),
+                total: started.elapsed(),
+                ..Default::default()
+            },
+        })
+    }
+}
+
+#[cfg(all(test, feature = "reqwest"))]
+mod tests {
+    use std::net::SocketAddr;
+
+    use super::{CertificatePin, ContentEncoding, IpPreference, PinningError};
+
+    const V4: &str = "127.0.0.1:0";
+    const V6: &str = "[::1]:0";
+
+    #[test_case::test_case(IpPreference::Any, &[V6, V4], &[V6, V4]; "any")]
+    #[test_case::test_case(IpPreference::PreferV4, &[V6, V4], &[V4, V6]; "prefer v4")]
+    #[test_case::test_case(IpPreference::PreferV6, &[V4, V6], &[V6, V4]; "prefer v6")]
+    #[test_case::test_case(IpPreference::V4Only, &[V6, V4], &[V4]; "v4 only")]
+    #[test_case::test_case(IpPreference::V6Only, &[V6, V4], &[V6]; "v6 only")]
+    fn should_apply_ip_preference(preference: IpPreference, input: &[&str], expected: &[&str]) {
+        let input = input.iter().map(|addr| addr.parse::<SocketAddr>().unwrap());
+        let expected = expected
+            .iter()
+            .map(|addr| addr.parse::<SocketAddr>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(preference.apply(input), expected);
+    }
+
+    #[test]
+    fn should_parse_certificate_pin() {
+        let pin = CertificatePin::of_certificate(b"certificate");
+        assert_eq!(pin.to_string().parse::<CertificatePin>(), Ok(pin));
+        assert_eq!(
+            pin.to_string().replace(':', "").parse::<CertificatePin>(),
+            Ok(pin)
+        );
+        assert!("AB:CD".parse::<CertificatePin>().is_err());
+    }
+
+    #[test]
+    fn should_detect_pinning_failure() {
+        let error = rustls::Error::InvalidCertificate(rustls::CertificateError::Other(
+            rustls::OtherError(std::sync::Arc::new(PinningError {
+                fingerprint: CertificatePin::of_certificate(b"certificate"),
+            })),
+        ));
+        let error = crate::Error::Transport(Box::new(std::io::Error::other(error)));
+        assert!(error.is_pinning_failure());
+        let error = crate::Error::Transport(Box::new(std::io::Error::other("connection reset")));
+        assert!(!error.is_pinning_failure());
+    }
+
+    #[test]
+    fn should_build_pinned_backend() {
+        let backend = super::ReqwestBackend::builder()
+            .min_tls_version(super::TlsVersion::Tls13)
+            .pin_certificate(CertificatePin::new([0; 32]))
+            .build();
+        assert!(backend.is_ok());
+    }
+
+    #[test]
+    fn should_tell_supported_encodings() {
+        assert_eq!(ContentEncoding::Gzip.is_supported(), cfg!(feature = "gzip"));
+        assert_eq!(
+            ContentEncoding::Brotli.is_supported(),
+            cfg!(feature = "brotli")
+        );
+    }
+
+    #[cfg(all(feature = "gzip", feature = "brotli"))]
+    #[tokio::test]
+    async fn should_accept_configured_encodings() {
+        use super::{HttpBackend, Request};
+
+        let mut server = mockito::Server::new_async().await;
+        let _only_gzip = server
+            .mock("GET", "/gzip")
+            .match_header("accept-encoding", "gzip")
+            .create_async()
+            .await;
+        let _without = server
+            .mock("GET", "/none")
+            .match_header("accept-encoding", mockito::Matcher::Missing)
+            .create_async()
+            .await;
+        for (path, encodings) in [("/gzip", &[ContentEncoding::Gzip][..]), ("/none", &[][..])] {
+            let backend = super::ReqwestBackend::builder()
+                .accept_encodings(encodings)
+                .build()
+                .unwrap();
+            let url = format!("{}{path}", server.url());
+            let response = backend
+                .get(Request {
+                    url: &url,
+                    query: Vec::new(),
+                    headers: Vec::new(),
+                    max_body_size: None,
+                    timeout: None,
+                })
+                .await
+                .unwrap();
+            assert_eq!(response.status, 200, "{path}");
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Search-as-you-type on top of a provider.
+//!
+//! An [`IncrementalSearch`] receives every update of the search field through its
+//! [`QueryInput`] and only searches once the input has been stable for the
+//! debounce delay. A search still in flight when the input changes is dropped,
+//! so the stream returned by [`IncrementalSearch::results`] only yields the
+//! results of the latest input.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+use futures_util::Stream;
+use futures_util::future::Either;
+
+use crate::entry::Page;
+use crate::provider::{SearchProvider, SearchQuery};
+use crate::runtime::Timer;
+
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Default)]
+struct State {
+    text: String,
+    generation: u64,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+#[derive(Debug, Default)]
+struct Shared(Mutex<State>);
+
+impl Shared {
+    /// Completes when the input changed since `seen`, with the new generation
+    /// and text, or with `None` once closed.
+    fn changed(&self, seen: u64) -> impl Future<Output = Option<(u64, String)>> + '_ {
+        std::future::poll_fn(move |cx| {
+            let mut state = self.0.lock().unwrap();
+            if state.closed {
+                Poll::Ready(None)
+            } else if state.generation != seen {
+                Poll::Ready(Some((state.generation, state.text.clone())))
+            } else {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+    }
+}
+
+/// The handle used to send the updates of the search field, see [`IncrementalSearch::input`].
+#[derive(Clone, Debug)]
+pub struct QueryInput(Arc<Shared>);
+
+impl QueryInput {
+    /// Replaces the text to search, superseding the previous one.
+    pub fn update(&self, text: impl Into<String>) {
+        let mut state = self.0.0.lock().unwrap();
+        state.text = text.into();
+        state.generation += 1;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Ends the stream of results.
+    pub fn close(&self) {
+        let mut state = self.0.0.lock().unwrap();
+        state.closed = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// The results of a search, see [`IncrementalSearch::results`].
+#[derive(Debug)]
+pub struct IncrementalUpdate {
+    /// The text that was searched.
+    pub query: String,
+    /// The first page of results.
+    pub result: crate::Result<Page>,
+}
+
+/// Debounced search-as-you-type, see the [module documentation](self).
+pub struct IncrementalSearch<P, T> {
+    provider: P,
+    timer: T,
+    debounce: Duration,
+    min_length: usize,
+    shared: Arc<Shared>,
+}
+
+impl<P: SearchProvider, T> std::fmt::Debug for IncrementalSearch<P, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IncrementalSearch")
+            .field("provider", &self.provider.name())
+            .field("debounce", &self.debounce)
+            .field("min_length", &self.min_length)
+            .finish()
+    }
+}
+
+impl<P: SearchProvider, T: Timer> IncrementalSearch<P, T> {
+    /// Creates an incremental search on the provider, the timer being used for the debounce.
+    pub fn new(provider: P, timer: T) -> Self {
+        Self {
+            provider,
+            timer,
+            debounce: DEFAULT_DEBOUNCE,
+            min_length: 1,
+            shared: Default::default(),
+        }
+    }
+
+    /// Sets how long the input must be stable before searching, defaults to 300ms.
+    pub fn debounce(mut self, value: Duration) -> Self {
+        self.debounce = value;
+        self
+    }
+
+    /// Sets the minimum number of characters to search, shorter inputs are ignored.
+    ///
+    /// Defaults to 1, ignoring empty inputs.
+    pub fn min_length(mut self, value: usize) -> Self {
+        self.min_length = value;
+        self
+    }
+
+    /// Returns the handle used to send the updates of the search field.
+    pub fn input(&self) -> QueryInput {
+        QueryInput(self.shared.clone())
+    }
+
+    /// Streams the results of the latest input, until [`QueryInput::close`] is called.
+    ///
+    /// Only the first page is fetched. Superseded searches are dropped without
+    /// being yielded.
+    pub fn results(&self) -> impl Stream<Item = IncrementalUpdate> + '_ {
+        futures_util::stream::unfold(0u64, move |seen| async move {
+            let (mut generation, mut text) = self.latest(seen).await?;
+            loop {
+                match self.search(generation, &text).await {
+                    Some(result) => {
+                        return Some((
+                            IncrementalUpdate {
+                                query: text,
+                                result,
+                            },
+                            generation,
+                        ));
+                    }
+                    None => {
+                        crate::telemetry::log!(debug, "search for {text:?} superseded");
+                        (generation, text) = self.latest(generation).await?;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Waits for an input that stays stable for the debounce delay.
+    async fn latest(&self, mut seen: u64) -> Option<(u64, String)> {
+        let (mut generation, mut text) = self.shared.changed(seen).await?;
+        loop {
+            if text.chars().count() < self.min_length {
+                seen = generation;
+                (generation, text) = self.shared.changed(seen).await?;
+                continue;
+            }
+            let sleep = std::pin::pin!(self.timer.sleep(self.debounce));
+            let changed = std::pin::pin!(self.shared.changed(generation));
+            match futures_util::future::select(sleep, changed).await {
+                Either::Left(_) => return Some((generation, text)),
+                Either::Right((next, _)) => (generation, text) = next?,
+            }
+        }
+    }
+
+    /// Searches the text, returns `None` if the input changed in the meantime.
+    async fn search(&self, generation: u64, text: &str) -> Option<crate::Result<Page>> {
+        let query = SearchQuery::new(text);
+        let search = std::pin::pin!(crate::provider::search_page(&self.provider, &query, 0));
+        let changed = std::pin::pin!(self.shared.changed(generation));
+        match futures_util::future::select(search, changed).await {
+            Either::Left((result, _)) => Some(result),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::entry::PageInfo;
+    use crate::provider::{BoxFuture, Capabilities};
+    use crate::runtime::TokioTimer;
+
+    #[derive(Default)]
+    struct Counting {
+        queries: Mutex<Vec<String>>,
+        calls: AtomicUsize,
+    }
+
+    impl SearchProvider for Arc<Counting> {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        fn search_page<'a>(
+            &'a self,
+            query: &'a SearchQuery,
+            page: u8,
+        ) -> BoxFuture<'a, crate::Result<Page>> {
+            Box::pin(async move {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                self.queries.lock().unwrap().push(query.text.clone());
+                Ok(Page {
+                    entries: Vec::new(),
+                    info: PageInfo {
+                        page,
+                        per_page: 0,
+                        is_last_guess: true,
+                    },
+                    timing: None,
+                })
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_debounce_and_cancel_superseded_searches() {
+        let provider = Arc::new(Counting::default());
+        let search = IncrementalSearch::new(provider.clone(), TokioTimer)
+            .debounce(Duration::from_millis(20))
+            .min_length(2);
+        let input = search.input();
+        let typing = async {
+            for text in ["u", "ub", "ubu", "ubun"] {
+                input.update(text);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            // wait for the search to start, then supersede it
+            tokio::time::sleep(Duration::from_millis(40)).await;
+            input.update("ubuntu");
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            input.close();
+        };
+        let (updates, _) = tokio::join!(search.results().collect::<Vec<_>>(), typing);
+        let queries = updates
+            .iter()
+            .map(|update| update.query.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(queries, ["ubuntu"]);
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(*provider.queries.lock().unwrap(), ["ubuntu"]);
+    }
+}
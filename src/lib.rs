@@ -1,3 +1,52 @@
-#![doc = include_str!("../readme.md")]
+// the example of the readme searches sunxdcc on the default reqwest backend
+#![cfg_attr(
+    all(feature = "reqwest", feature = "sunxdcc"),
+    doc = include_str!("../readme.md")
+)]
 
+pub mod collection;
+pub mod cursor;
+pub mod doctor;
+pub mod drift;
+pub mod dump;
+pub mod enrich;
+pub mod entry;
+mod error;
+pub mod event;
+pub mod export;
+pub mod expression;
+pub mod format;
+pub mod http;
+pub mod incremental;
+pub mod matching;
+pub mod metadata;
+pub mod multi;
+pub mod name;
+pub mod notice;
+#[cfg(any(test, feature = "plugin"))]
+pub mod plugin;
+pub mod policy;
+pub mod popularity;
+pub mod preset;
+pub mod process;
+pub mod provider;
+pub mod query;
+pub mod redact;
+pub mod release;
+pub mod runtime;
+pub mod schema;
+pub mod score;
+pub mod seen;
+pub mod stats;
+pub mod stream;
+#[cfg(feature = "sunxdcc")]
 pub mod sunxdcc;
+pub mod telemetry;
+#[cfg(any(feature = "testing", all(test, feature = "sunxdcc", feature = "tokio")))]
+pub mod testing;
+pub mod title;
+#[cfg(any(test, feature = "view"))]
+pub mod view;
+pub mod wire;
+
+pub use error::{Error, Result};
@@ -0,0 +1,197 @@
+//! Client-side text matching, insensitive to case, accents and separators.
+//!
+//! Filenames on XDCC bots are written in many different ways: `Pokémon.S01E01`,
+//! `pokemon_s01e01`, `POKEMON S01E01`... The helpers of this module reduce both
+//! sides of a comparison to the same form before matching them, so searching
+//! `pokemon` matches `Pokémon` and vice versa.
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+/// Reduces a text to its comparable form.
+///
+/// The text is decomposed (NFKD), the accents and other combining marks are
+/// removed, the letters are lowercased and every sequence of characters that
+/// aren't letters or digits is replaced by a single space.
+///
+/// ```
+/// use xdcc_search::matching::normalize;
+///
+/// assert_eq!(normalize("Pokémon.S01E01_[VOSTFR]"), "pokemon s01e01 vostfr");
+/// ```
+pub fn normalize(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut separator = false;
+    for c in value.nfkd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_alphanumeric() {
+            if separator && !result.is_empty() {
+                result.push(' ');
+            }
+            separator = false;
+            result.extend(c.to_lowercase());
+        } else {
+            separator = true;
+        }
+    }
+    result
+}
+
+/// A search term normalized once, to be matched against many texts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Matcher {
+    words: Vec<String>,
+}
+
+impl Matcher {
+    /// Creates a matcher from a search term.
+    pub fn new(term: &str) -> Self {
+        Self {
+            words: normalize(term).split(' ').map(String::from).collect(),
+        }
+    }
+
+    /// Returns `true` if every word of the term is found in the text, in any order.
+    ///
+    /// An empty term matches everything.
+    pub fn matches(&self, text: &str) -> bool {
+        let text = normalize(text);
+        self.words.iter().all(|word| text.contains(word.as_str()))
+    }
+}
+
+/// A shell-like pattern on filenames, `*` matching any sequence of characters
+/// and `?` a single character, compared without case.
+///
+/// ```
+/// use xdcc_search::matching::Glob;
+///
+/// assert!(Glob::new("ubuntu-*.iso").matches("Ubuntu-24.04-desktop-amd64.ISO"));
+/// assert!(!Glob::new("*.iso").matches("ubuntu.iso.torrent"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Glob {
+    pattern: String,
+    chars: Vec<char>,
+}
+
+impl Glob {
+    /// Compiles a pattern.
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_owned(),
+            chars: pattern.chars().flat_map(char::to_lowercase).collect(),
+        }
+    }
+
+    /// Returns the pattern, as provided.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns `true` if the whole text matches the pattern.
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text
+            .chars()
+            .flat_map(char::to_lowercase)
+            .collect::<Vec<_>>();
+        let (mut pattern_index, mut text_index) = (0, 0);
+        // position of the last star in the pattern, and of the text when it was met
+        let mut backtrack: Option<(usize, usize)> = None;
+        while text_index < text.len() {
+            match self.chars.get(pattern_index) {
+                Some('*') => {
+                    backtrack = Some((pattern_index, text_index));
+                    pattern_index += 1;
+                }
+                Some(c) if *c == '?' || *c == text[text_index] => {
+                    pattern_index += 1;
+                    text_index += 1;
+                }
+                _ => match backtrack {
+                    Some((star, start)) => {
+                        pattern_index = star + 1;
+                        text_index = start + 1;
+                        backtrack = Some((star, start + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.chars[pattern_index..].iter().all(|c| *c == '*')
+    }
+}
+
+/// A filter on the filenames, see [`SearchQuery::filename_glob`](crate::provider::SearchQuery::filename_glob).
+#[derive(Clone, Debug)]
+pub enum FilenamePattern {
+    /// A shell-like pattern.
+    Glob(Glob),
+    /// A regular expression, found anywhere in the filename unless anchored.
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl FilenamePattern {
+    /// Returns `true` if the filename matches the pattern.
+    pub fn matches(&self, filename: &str) -> bool {
+        match self {
+            Self::Glob(glob) => glob.matches(filename),
+            #[cfg(feature = "regex")]
+            Self::Regex(regex) => regex.is_match(filename),
+        }
+    }
+}
+
+impl PartialEq for FilenamePattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Glob(left), Self::Glob(right)) => left == right,
+            #[cfg(feature = "regex")]
+            (Self::Regex(left), Self::Regex(right)) => left.as_str() == right.as_str(),
+            #[cfg(feature = "regex")]
+            _ => false,
+        }
+    }
+}
+
+impl Eq for FilenamePattern {}
+
+/// Returns `true` if every word of `term` is found in `text`, ignoring case,
+/// accents and separators.
+///
+/// When matching the same term against many texts, prefer building a [`Matcher`] once.
+pub fn matches(term: &str, text: &str) -> bool {
+    Matcher::new(term).matches(text)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test_case::test_case("Pokémon", "pokemon"; "accent")]
+    #[test_case::test_case("Ubuntu.24.04_Desktop", "ubuntu 24 04 desktop"; "separators")]
+    #[test_case::test_case("  [Group] Show - 01 ", "group show 01"; "surrounding separators")]
+    #[test_case::test_case("ＡＢＣ", "abc"; "full width")]
+    #[test_case::test_case("Ærø", "ærø"; "no decomposition")]
+    fn should_normalize(input: &str, expected: &str) {
+        assert_eq!(super::normalize(input), expected);
+    }
+
+    #[test_case::test_case("*.iso", "ubuntu.iso", true; "extension")]
+    #[test_case::test_case("*.iso", "ubuntu.iso.txt", false; "trailing")]
+    #[test_case::test_case("*s01e0?*", "Show.S01E05.1080p.mkv", true; "single character")]
+    #[test_case::test_case("a*b*c", "aXbYbZc", true; "backtracking")]
+    #[test_case::test_case("a*b*c", "aXbYbZ", false; "backtracking failure")]
+    #[test_case::test_case("*", "", true; "empty text")]
+    #[test_case::test_case("", "a", false; "empty pattern")]
+    fn should_match_glob(pattern: &str, text: &str, expected: bool) {
+        assert_eq!(super::Glob::new(pattern).matches(text), expected);
+    }
+
+    #[test_case::test_case("pokemon", "Pokémon.S01E01.mkv", true; "accent in text")]
+    #[test_case::test_case("pokémon", "POKEMON_S01E01.mkv", true; "accent in term")]
+    #[test_case::test_case("s01e01 pokemon", "Pokémon.S01E01.mkv", true; "any order")]
+    #[test_case::test_case("digimon", "Pokémon.S01E01.mkv", false; "different")]
+    #[test_case::test_case("", "anything", true; "empty term")]
+    fn should_match(term: &str, text: &str, expected: bool) {
+        assert_eq!(super::matches(term, text), expected);
+    }
+}
@@ -0,0 +1,313 @@
+//! Metadata detected from the filenames.
+//!
+//! [`compare_quality`] uses the resolution and codec detected to tell which of two
+//! releases of the same content is the best, and [`is_upgrade`] whether a newly
+//! listed release is worth replacing a previously downloaded one.
+
+use std::cmp::Ordering;
+
+use crate::entry::Entry;
+use crate::title::Title;
+
+/// Metadata detected from a filename, see [`FileMetadata::parse`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileMetadata {
+    /// The clean title and year.
+    pub title: Title,
+    /// The audio languages, as ISO 639-1 codes (e.g. `fr`), in order of appearance.
+    pub languages: Vec<String>,
+    /// `true` if the file is tagged with several audio tracks (`MULTI`, `DUAL`).
+    pub multi_audio: bool,
+    /// The subtitle languages, as ISO 639-1 codes, when they could be identified.
+    pub subtitles: Vec<String>,
+    /// `true` if the file is tagged as subtitled, even without a known language.
+    pub subtitled: bool,
+    /// The vertical resolution of the video (e.g. `1080`), `4K` being `2160`.
+    pub resolution: Option<u16>,
+    /// The video codec.
+    pub codec: Option<Codec>,
+    /// The season and episode numbers, from the `S01E02` or `1x02` tags.
+    pub episode: Option<(u16, u16)>,
+}
+
+/// A video codec, ordered from the least to the most efficient.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Codec {
+    /// MPEG-4 Part 2 (`XviD`, `DivX`).
+    Mpeg4,
+    /// H.264 (`x264`, `AVC`).
+    H264,
+    /// H.265 (`x265`, `HEVC`).
+    H265,
+    /// AV1.
+    Av1,
+}
+
+/// Tags giving a video codec, `h 264` being the normalized form of `H.264`.
+const CODEC_TAGS: &[(&str, Codec)] = &[
+    ("xvid", Codec::Mpeg4),
+    ("divx", Codec::Mpeg4),
+    ("x264", Codec::H264),
+    ("h264", Codec::H264),
+    ("avc", Codec::H264),
+    ("x265", Codec::H265),
+    ("h265", Codec::H265),
+    ("hevc", Codec::H265),
+    ("av1", Codec::Av1),
+];
+
+/// The resolutions recognized in the `720p` or `1080i` tags.
+const RESOLUTIONS: &[u16] = &[360, 480, 576, 720, 1080, 1440, 2160, 4320];
+
+fn parse_resolution(word: &str) -> Option<u16> {
+    if matches!(word, "4k" | "uhd") {
+        return Some(2160);
+    }
+    let value = word.strip_suffix('p').or_else(|| word.strip_suffix('i'))?;
+    value
+        .parse()
+        .ok()
+        .filter(|value| RESOLUTIONS.contains(value))
+}
+
+fn parse_episode(word: &str) -> Option<(u16, u16)> {
+    let (season, episode) = match word.strip_prefix('s') {
+        Some(rest) => rest.split_once('e')?,
+        None => word
+            .split_once('x')
+            .filter(|(season, _)| season.len() <= 2)?,
+    };
+    Some((season.parse().ok()?, episode.parse().ok()?))
+}
+
+/// Tags giving an audio language.
+const AUDIO_TAGS: &[(&str, &str)] = &[
+    ("french", "fr"),
+    ("truefrench", "fr"),
+    ("vf", "fr"),
+    ("vff", "fr"),
+    ("vfq", "fr"),
+    ("vfi", "fr"),
+    ("ita", "it"),
+    ("italian", "it"),
+    ("eng", "en"),
+    ("english", "en"),
+    ("ger", "de"),
+    ("german", "de"),
+    ("deutsch", "de"),
+    ("spa", "es"),
+    ("esp", "es"),
+    ("spanish", "es"),
+    ("castellano", "es"),
+    ("jap", "ja"),
+    ("jpn", "ja"),
+    ("japanese", "ja"),
+];
+
+/// Tags giving a subtitle language.
+const SUBTITLE_TAGS: &[(&str, &str)] = &[
+    ("vostfr", "fr"),
+    ("subfrench", "fr"),
+    ("vosta", "en"),
+    ("engsub", "en"),
+    ("engsubs", "en"),
+    ("subita", "it"),
+    ("itasub", "it"),
+    ("subspa", "es"),
+];
+
+/// Tags only telling that the file is subtitled.
+const SUBTITLED_TAGS: &[&str] = &["sub", "subs", "subbed", "hardsub", "softsub", "vost"];
+
+/// Tags telling that the file has several audio tracks.
+const MULTI_TAGS: &[&str] = &["multi", "dual", "dualaudio"];
+
+fn push_unique(values: &mut Vec<String>, value: &str) {
+    if !values.iter().any(|item| item == value) {
+        values.push(value.to_owned());
+    }
+}
+
+impl FileMetadata {
+    /// Detects the metadata from a filename.
+    ///
+    /// ```
+    /// use xdcc_search::metadata::FileMetadata;
+    ///
+    /// let metadata = FileMetadata::parse("Show.S01E01.MULTi.VOSTFR.1080p.mkv");
+    /// assert!(metadata.multi_audio);
+    /// assert_eq!(metadata.subtitles, vec!["fr"]);
+    /// ```
+    pub fn parse(filename: &str) -> Self {
+        let mut result = Self {
+            title: Title::parse(filename),
+            languages: Vec::new(),
+            multi_audio: false,
+            subtitles: Vec::new(),
+            subtitled: false,
+            resolution: None,
+            codec: None,
+            episode: None,
+        };
+        let normalized = crate::matching::normalize(filename);
+        let mut previous = "";
+        for word in normalized.split(' ') {
+            let joined = format!("{previous}{word}");
+            if let Some((_, codec)) = CODEC_TAGS
+                .iter()
+                .find(|(tag, _)| *tag == word || (previous == "h" && *tag == joined))
+            {
+                result.codec.get_or_insert(*codec);
+            } else if let Some(resolution) = parse_resolution(word) {
+                result.resolution.get_or_insert(resolution);
+            } else if let Some(episode) = parse_episode(word) {
+                result.episode.get_or_insert(episode);
+            } else if let Some((_, code)) = AUDIO_TAGS.iter().find(|(tag, _)| *tag == word) {
+                push_unique(&mut result.languages, code);
+            } else if let Some((_, code)) = SUBTITLE_TAGS.iter().find(|(tag, _)| *tag == word) {
+                push_unique(&mut result.subtitles, code);
+                result.subtitled = true;
+            } else if SUBTITLED_TAGS.contains(&word) {
+                result.subtitled = true;
+            } else if MULTI_TAGS.contains(&word) {
+                result.multi_audio = true;
+            }
+            previous = word;
+        }
+        result
+    }
+
+    /// Returns `true` if the audio is tagged with the given language (ISO 639-1 code).
+    pub fn has_language(&self, code: &str) -> bool {
+        self.languages
+            .iter()
+            .any(|item| item.eq_ignore_ascii_case(code))
+    }
+
+    /// Returns `true` if the subtitles are tagged with the given language (ISO 639-1 code).
+    pub fn has_subtitles(&self, code: &str) -> bool {
+        self.subtitles
+            .iter()
+            .any(|item| item.eq_ignore_ascii_case(code))
+    }
+}
+
+/// Compares the quality of two releases: the resolution first, then the efficiency
+/// of the codec, then the size, a larger file having a higher bitrate.
+///
+/// ```
+/// use std::cmp::Ordering;
+/// use xdcc_search::metadata::compare_quality;
+/// # fn entry(filename: &str, filesize: u64) -> xdcc_search::entry::Entry {
+/// #     xdcc_search::entry::Entry::builder()
+/// #         .filename(filename)
+/// #         .filesize(filesize)
+/// #         .packnum(1)
+/// #         .channel("#chan")
+/// #         .network("net")
+/// #         .bot_name("bot")
+/// #         .build()
+/// #         .unwrap()
+/// # }
+///
+/// let sd = entry("Show.S01E01.720p.x264.mkv", 2_000_000_000);
+/// let hd = entry("Show.S01E01.1080p.x265.mkv", 1_000_000_000);
+/// assert_eq!(compare_quality(&hd, &sd), Ordering::Greater);
+/// ```
+pub fn compare_quality(a: &Entry, b: &Entry) -> Ordering {
+    let (first, second) = (a.metadata(), b.metadata());
+    first
+        .resolution
+        .cmp(&second.resolution)
+        .then(first.codec.cmp(&second.codec))
+        .then(a.filesize.cmp(&b.filesize))
+}
+
+/// Returns `true` if the candidate is the same content (title, year and episode)
+/// as the current release, in a better quality, see [`compare_quality`].
+pub fn is_upgrade(current: &Entry, candidate: &Entry) -> bool {
+    let (before, after) = (current.metadata(), candidate.metadata());
+    before.title == after.title
+        && before.episode == after.episode
+        && compare_quality(candidate, current) == Ordering::Greater
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case::test_case("Movie.2020.FRENCH.1080p.mkv", &["fr"], false, &[], false; "french")]
+    #[test_case::test_case("Movie.2020.MULTi.TRUEFRENCH.1080p.mkv", &["fr"], true, &[], false; "multi")]
+    #[test_case::test_case("[Group] Anime - 01 VOSTFR [720p].mkv", &[], false, &["fr"], true; "vostfr")]
+    #[test_case::test_case("Film.ITA.ENG.SubITA.mkv", &["it", "en"], false, &["it"], true; "italian")]
+    #[test_case::test_case("Anime.DUAL.Audio.Subbed.mkv", &[], true, &[], true; "dual subbed")]
+    #[test_case::test_case("ubuntu-24.04-desktop-amd64.iso", &[], false, &[], false; "nothing")]
+    fn should_detect_languages(
+        input: &str,
+        languages: &[&str],
+        multi_audio: bool,
+        subtitles: &[&str],
+        subtitled: bool,
+    ) {
+        let metadata = FileMetadata::parse(input);
+        assert_eq!(metadata.languages, languages);
+        assert_eq!(metadata.multi_audio, multi_audio);
+        assert_eq!(metadata.subtitles, subtitles);
+        assert_eq!(metadata.subtitled, subtitled);
+    }
+
+    #[test_case::test_case("Movie.2020.1080p.BluRay.x264-GRP.mkv", Some(1080), Some(Codec::H264), None; "movie")]
+    #[test_case::test_case("Show.S02E10.2160p.WEB.H.265.mkv", Some(2160), Some(Codec::H265), Some((2, 10)); "dotted codec")]
+    #[test_case::test_case("[Group] Anime 3x05 [720p][HEVC].mkv", Some(720), Some(Codec::H265), Some((3, 5)); "anime")]
+    #[test_case::test_case("Movie.4K.AV1.mkv", Some(2160), Some(Codec::Av1), None; "4k")]
+    #[test_case::test_case("ubuntu-24.04-desktop-amd64.iso", None, None, None; "nothing")]
+    fn should_detect_quality(
+        input: &str,
+        resolution: Option<u16>,
+        codec: Option<Codec>,
+        episode: Option<(u16, u16)>,
+    ) {
+        let metadata = FileMetadata::parse(input);
+        assert_eq!(metadata.resolution, resolution);
+        assert_eq!(metadata.codec, codec);
+        assert_eq!(metadata.episode, episode);
+    }
+
+    fn entry(filename: &str, filesize: u64) -> Entry {
+        let mut entry = crate::process::tests::entry(1, filesize);
+        entry.filename = filename.into();
+        entry
+    }
+
+    #[test_case::test_case("Show.S01E01.1080p.x264.mkv", 1, "Show.S01E01.720p.x265.mkv", 2, Ordering::Greater; "resolution first")]
+    #[test_case::test_case("Show.S01E01.1080p.x265.mkv", 1, "Show.S01E01.1080p.x264.mkv", 2, Ordering::Greater; "codec second")]
+    #[test_case::test_case("Show.S01E01.1080p.x264.mkv", 1, "Show.S01E01.1080p.x264.mkv", 2, Ordering::Less; "size last")]
+    #[test_case::test_case("Show.S01E01.mkv", 1, "Show.S01E01.480p.mkv", 1, Ordering::Less; "unknown resolution")]
+    fn should_compare_quality(a: &str, a_size: u64, b: &str, b_size: u64, expected: Ordering) {
+        assert_eq!(
+            compare_quality(&entry(a, a_size), &entry(b, b_size)),
+            expected
+        );
+    }
+
+    #[test]
+    fn should_detect_upgrade() {
+        let current = entry("Show.S01E01.720p.x264-GRP.mkv", 1000);
+        assert!(is_upgrade(
+            &current,
+            &entry("Show.S01E01.1080p.x264-OTHER.mkv", 900)
+        ));
+        assert!(!is_upgrade(
+            &current,
+            &entry("Show.S01E01.480p.x264-GRP.mkv", 2000)
+        ));
+        assert!(!is_upgrade(
+            &current,
+            &entry("Show.S01E02.1080p.x264-GRP.mkv", 900)
+        ));
+        assert!(!is_upgrade(
+            &current,
+            &entry("Other.S01E01.1080p.x264-GRP.mkv", 900)
+        ));
+    }
+}
@@ -0,0 +1,901 @@
+//! Search across several providers at once.
+//!
+//! A [`MultiEngine`] sends the same query to all its providers concurrently. A
+//! total deadline, and a timeout per provider, can be configured: the providers
+//! that didn't answer in time are cut off and the search returns the partial
+//! results, with the outcome of each provider.
+//!
+//! How the results of the providers are combined is set with a [`MergeStrategy`].
+//!
+//! In [`Mode::Failover`], the providers are tried one after the other instead,
+//! in the order they were added, until one of them answers.
+
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::entry::{Entry, Page, PageInfo, SearchHit};
+use crate::http::RequestId;
+use crate::process::{Deduper, SameFile};
+use crate::provider::{SearchProvider, SearchQuery};
+use crate::runtime::Timer;
+
+/// How the results of the different providers are combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// All the results of the first provider, then the ones of the second...
+    #[default]
+    Grouped,
+    /// The first result of each provider, then the second of each provider...
+    /// keeping the most relevant results of every provider at the top.
+    Interleaved,
+    /// Same as [`MergeStrategy::Interleaved`], but only keeps the first of the
+    /// entries with the same normalized filename and size, across providers.
+    Deduplicated,
+}
+
+impl MergeStrategy {
+    fn merge(self, groups: Vec<Vec<SearchHit>>) -> Vec<SearchHit> {
+        match self {
+            Self::Grouped => groups.into_iter().flatten().collect(),
+            Self::Interleaved => interleave(groups),
+            Self::Deduplicated => {
+                let mut result: Vec<SearchHit> = Vec::new();
+                for hit in interleave(groups) {
+                    if !result
+                        .iter()
+                        .any(|kept| SameFile.is_duplicate(&kept.entry, &hit.entry))
+                    {
+                        result.push(hit);
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+/// How the providers are queried.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// All the providers are queried concurrently and their results are merged.
+    #[default]
+    FanOut,
+    /// The providers are queried one after the other, in the order they were
+    /// added, until one answers. A provider failing or timing out falls back
+    /// to the next one. The [`MergeStrategy`] is irrelevant in this mode.
+    Failover,
+}
+
+fn interleave<T>(groups: Vec<Vec<T>>) -> Vec<T> {
+    let mut result = Vec::with_capacity(groups.iter().map(Vec::len).sum());
+    let mut iterators = groups.into_iter().map(Vec::into_iter).collect::<Vec<_>>();
+    loop {
+        let before = result.len();
+        result.extend(iterators.iter_mut().filter_map(Iterator::next));
+        if result.len() == before {
+            return result;
+        }
+    }
+}
+
+/// The pages received from a provider by [`MultiEngine::search_within`].
+#[derive(Default)]
+struct Crawled {
+    hits: Vec<SearchHit>,
+    /// The pagination metadata of the latest page.
+    last: Option<PageInfo>,
+    error: Option<crate::Error>,
+    /// `true` once the last page was received.
+    done: bool,
+}
+
+struct Slot {
+    provider: Arc<dyn SearchProvider>,
+    timeout: Option<Duration>,
+}
+
+/// Sends the searches to several providers, see the [module documentation](self).
+pub struct MultiEngine<T> {
+    slots: Vec<Slot>,
+    timer: T,
+    deadline: Option<Duration>,
+    merge: MergeStrategy,
+    mode: Mode,
+    page_delay: RangeInclusive<Duration>,
+}
+
+impl<T> std::fmt::Debug for MultiEngine<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiEngine")
+            .field(
+                "providers",
+                &self
+                    .slots
+                    .iter()
+                    .map(|slot| slot.provider.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("deadline", &self.deadline)
+            .field("merge", &self.merge)
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+impl<T: Timer> MultiEngine<T> {
+    /// Creates a builder, the timer being used to enforce the deadlines.
+    pub fn builder(timer: T) -> MultiEngineBuilder<T> {
+        MultiEngineBuilder {
+            slots: Vec::new(),
+            timer,
+            deadline: None,
+            merge: MergeStrategy::default(),
+            mode: Mode::default(),
+            page_delay: crate::runtime::DEFAULT_PAGE_DELAY,
+        }
+    }
+}
+
+/// Builder for a [`MultiEngine`], created with [`MultiEngine::builder`].
+pub struct MultiEngineBuilder<T> {
+    slots: Vec<Slot>,
+    timer: T,
+    deadline: Option<Duration>,
+    merge: MergeStrategy,
+    mode: Mode,
+    page_delay: RangeInclusive<Duration>,
+}
+
+impl<T> MultiEngineBuilder<T> {
+    /// Adds a provider, only limited by the total deadline.
+    pub fn provider<P: SearchProvider + 'static>(mut self, provider: P) -> Self {
+        self.slots.push(Slot {
+            provider: Arc::new(provider),
+            timeout: None,
+        });
+        self
+    }
+
+    /// Adds a provider that will be cut off if it doesn't answer within `timeout`.
+    pub fn provider_with_timeout<P: SearchProvider + 'static>(
+        mut self,
+        provider: P,
+        timeout: Duration,
+    ) -> Self {
+        self.slots.push(Slot {
+            provider: Arc::new(provider),
+            timeout: Some(timeout),
+        });
+        self
+    }
+
+    /// Sets the total time allowed to a search, after which the providers that
+    /// didn't answer yet are cut off.
+    pub fn deadline(mut self, value: Duration) -> Self {
+        self.deadline = Some(value);
+        self
+    }
+
+    /// Sets how the results of the providers are combined, defaults to [`MergeStrategy::Grouped`].
+    pub fn merge_strategy(mut self, value: MergeStrategy) -> Self {
+        self.merge = value;
+        self
+    }
+
+    /// Sets how the providers are queried, defaults to [`Mode::FanOut`].
+    pub fn mode(mut self, value: Mode) -> Self {
+        self.mode = value;
+        self
+    }
+
+    /// Sets the range of the random delay waited between two consecutive pages
+    /// of a provider, see [`MultiEngine::search_within`].
+    ///
+    /// Defaults to 500ms to 1500ms. Use `Duration::ZERO..=Duration::ZERO` to disable it.
+    pub fn page_delay(mut self, range: RangeInclusive<Duration>) -> Self {
+        self.page_delay = range;
+        self
+    }
+
+    /// Builds the engine.
+    pub fn build(self) -> MultiEngine<T> {
+        MultiEngine {
+            slots: self.slots,
+            timer: self.timer,
+            deadline: self.deadline,
+            merge: self.merge,
+            mode: self.mode,
+            page_delay: self.page_delay,
+        }
+    }
+}
+
+/// What happened with a provider during a search.
+#[derive(Debug)]
+pub enum ProviderStatus {
+    /// The provider answered in time.
+    Complete {
+        /// The number of entries returned by the provider.
+        count: usize,
+        /// The pagination metadata of the provider.
+        info: PageInfo,
+    },
+    /// The provider didn't answer before its timeout or the deadline.
+    TimedOut,
+    /// The provider answered with an error.
+    Failed(crate::Error),
+}
+
+impl ProviderStatus {
+    /// Returns the status of a provider, with its entries if it answered.
+    fn from_result(
+        provider: &str,
+        result: Option<crate::Result<Page>>,
+    ) -> (Self, Option<Vec<SearchHit>>) {
+        match result {
+            Some(Ok(found)) => {
+                let status = Self::Complete {
+                    count: found.entries.len(),
+                    info: found.info,
+                };
+                (status, Some(found.into_hits(provider, SystemTime::now())))
+            }
+            Some(Err(error)) => {
+                crate::telemetry::log!(debug, "provider {provider:?} failed: {error:?}");
+                (Self::Failed(error), None)
+            }
+            None => {
+                crate::telemetry::log!(debug, "provider {provider:?} timed out");
+                (Self::TimedOut, None)
+            }
+        }
+    }
+}
+
+/// The outcome of a provider during a search.
+#[derive(Debug)]
+pub struct ProviderOutcome {
+    /// The name of the provider.
+    pub provider: String,
+    /// What happened.
+    pub status: ProviderStatus,
+}
+
+/// The result of a search across several providers.
+#[derive(Debug)]
+pub struct MultiPage {
+    /// The entries returned by the providers that answered in time, combined
+    /// with the configured [`MergeStrategy`], annotated with their provider.
+    pub hits: Vec<SearchHit>,
+    /// The outcome of each provider, in the order they were added.
+    ///
+    /// In [`Mode::Failover`], only the providers that were tried are listed.
+    pub outcomes: Vec<ProviderOutcome>,
+}
+
+impl MultiPage {
+    /// Returns the entries, without their provenance.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.hits.iter().map(|hit| &hit.entry)
+    }
+
+    /// Consumes the page and returns the entries, without their provenance.
+    pub fn into_entries(self) -> Vec<Entry> {
+        self.hits.into_iter().map(|hit| hit.entry).collect()
+    }
+
+    /// Returns `true` if some providers timed out or failed.
+    pub fn is_partial(&self) -> bool {
+        self.outcomes
+            .iter()
+            .any(|outcome| !matches!(outcome.status, ProviderStatus::Complete { .. }))
+    }
+
+    /// Returns the names of the providers that answered, and thus served the entries.
+    pub fn served_by(&self) -> impl Iterator<Item = &str> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome.status, ProviderStatus::Complete { .. }))
+            .map(|outcome| outcome.provider.as_str())
+    }
+
+    /// Returns the names of the providers that timed out.
+    pub fn timed_out(&self) -> impl Iterator<Item = &str> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome.status, ProviderStatus::TimedOut))
+            .map(|outcome| outcome.provider.as_str())
+    }
+}
+
+impl<T: Timer> MultiEngine<T> {
+    /// Fetches the same page from the providers, concurrently or one after the
+    /// other depending on the [`Mode`].
+    ///
+    /// The query is negotiated with each provider, see [`crate::provider::search_page`].
+    /// A provider failing or timing out doesn't fail the search, it's reported
+    /// in [`MultiPage::outcomes`].
+    pub async fn search_page(&self, query: &SearchQuery, page: u8) -> MultiPage {
+        self.search_page_with_id(query, page, RequestId::generate())
+            .await
+    }
+
+    /// Same as [`MultiEngine::search_page`] with the [`RequestId`] provided by the
+    /// caller, passed down to all the providers.
+    pub async fn search_page_with_id(
+        &self,
+        query: &SearchQuery,
+        page: u8,
+        request_id: RequestId,
+    ) -> MultiPage {
+        match self.mode {
+            Mode::FanOut => self.fan_out(query, page, request_id).await,
+            Mode::Failover => self.failover(query, page, request_id).await,
+        }
+    }
+
+    async fn fan_out(&self, query: &SearchQuery, page: u8, request_id: RequestId) -> MultiPage {
+        let searches = self
+            .slots
+            .iter()
+            .map(|slot| self.search_slot(slot, query, page, request_id, self.deadline));
+        let mut groups = Vec::with_capacity(self.slots.len());
+        let mut outcomes = Vec::with_capacity(self.slots.len());
+        for (provider, result) in futures_util::future::join_all(searches).await {
+            let (status, hits) = ProviderStatus::from_result(&provider, result);
+            groups.extend(hits);
+            outcomes.push(ProviderOutcome { provider, status });
+        }
+        MultiPage {
+            hits: self.merge.merge(groups),
+            outcomes,
+        }
+    }
+
+    async fn failover(&self, query: &SearchQuery, page: u8, request_id: RequestId) -> MultiPage {
+        let started = Instant::now();
+        let mut outcomes = Vec::new();
+        for slot in self.slots.iter() {
+            let remaining = self
+                .deadline
+                .map(|deadline| deadline.saturating_sub(started.elapsed()));
+            if remaining == Some(Duration::ZERO) {
+                break;
+            }
+            let (provider, result) = self
+                .search_slot(slot, query, page, request_id, remaining)
+                .await;
+            let (status, hits) = ProviderStatus::from_result(&provider, result);
+            outcomes.push(ProviderOutcome { provider, status });
+            if let Some(hits) = hits {
+                return MultiPage { hits, outcomes };
+            }
+        }
+        MultiPage {
+            hits: Vec::new(),
+            outcomes,
+        }
+    }
+
+    /// Searches all the pages of the providers concurrently, and returns whatever
+    /// was received when `budget` expires, e.g. for the interactive interfaces with
+    /// strict latency targets.
+    ///
+    /// Each provider fetches its pages one after the other, until the last one,
+    /// waiting a random delay between two pages, see [`MultiEngineBuilder::page_delay`]. The
+    /// entries of all the pages received in time are returned, including the ones of
+    /// the providers cut off by the budget, reported as [`ProviderStatus::TimedOut`],
+    /// or failing after a few pages. [`MultiPage::is_partial`] tells if the results
+    /// are partial.
+    ///
+    /// The providers are always queried concurrently, the [`Mode`] and the deadline
+    /// of the engine are ignored. The timeouts of the providers apply to each page.
+    /// All the pages are requested with the same [`RequestId`].
+    pub async fn search_within(&self, query: &SearchQuery, budget: Duration) -> MultiPage {
+        let request_id = RequestId::generate();
+        let crawls = self
+            .slots
+            .iter()
+            .map(|_| Mutex::new(Crawled::default()))
+            .collect::<Vec<_>>();
+        let searches = self
+            .slots
+            .iter()
+            .zip(&crawls)
+            .map(|(slot, crawled)| self.crawl_slot(slot, query, request_id, crawled));
+        let searches = futures_util::future::join_all(searches);
+        if crate::runtime::with_deadline(&self.timer, budget, searches)
+            .await
+            .is_none()
+        {
+            crate::telemetry::log!(debug, "{request_id}: search budget of {budget:?} expired");
+        }
+        let mut groups = Vec::with_capacity(self.slots.len());
+        let mut outcomes = Vec::with_capacity(self.slots.len());
+        for (slot, crawled) in self.slots.iter().zip(crawls) {
+            let crawled = crawled.into_inner().unwrap();
+            let status = match (crawled.error, crawled.last) {
+                (Some(error), _) => ProviderStatus::Failed(error),
+                (None, Some(info)) if crawled.done => ProviderStatus::Complete {
+                    count: crawled.hits.len(),
+                    info,
+                },
+                _ => ProviderStatus::TimedOut,
+            };
+            groups.push(crawled.hits);
+            outcomes.push(ProviderOutcome {
+                provider: slot.provider.name().to_owned(),
+                status,
+            });
+        }
+        MultiPage {
+            hits: self.merge.merge(groups),
+            outcomes,
+        }
+    }
+
+    /// Fetches the pages of a provider until the last one, recording them as they
+    /// arrive, to keep them if the search is cut off.
+    async fn crawl_slot(
+        &self,
+        slot: &Slot,
+        query: &SearchQuery,
+        request_id: RequestId,
+        crawled: &Mutex<Crawled>,
+    ) {
+        let provider = slot.provider.name();
+        let last_page = if slot.provider.capabilities().pagination {
+            u8::MAX
+        } else {
+            0
+        };
+        for page in 0..=last_page {
+            if page > 0 {
+                self.timer
+                    .sleep(crate::runtime::jitter(&self.page_delay))
+                    .await;
+            }
+            let search = crate::provider::search_page_with_id(
+                slot.provider.as_ref(),
+                query,
+                page,
+                request_id,
+            );
+            let result = match slot.timeout {
+                Some(timeout) => crate::runtime::with_deadline(&self.timer, timeout, search).await,
+                None => Some(search.await),
+            };
+            let mut crawled = crawled.lock().unwrap();
+            match result {
+                Some(Ok(found)) => {
+                    let is_last = found.info.is_last_guess || found.entries.is_empty();
+                    crawled.last = Some(found.info);
+                    crawled
+                        .hits
+                        .extend(found.into_hits(provider, SystemTime::now()));
+                    if is_last {
+                        break;
+                    }
+                }
+                Some(Err(error)) => {
+                    crate::telemetry::log!(
+                        debug,
+                        "{request_id}: provider {provider:?} failed: {error:?}"
+                    );
+                    crawled.error = Some(error);
+                    return;
+                }
+                None => {
+                    crate::telemetry::log!(debug, "{request_id}: provider {provider:?} timed out");
+                    return;
+                }
+            }
+        }
+        crawled.lock().unwrap().done = true;
+    }
+
+    /// Searches a provider, returns `None` if it didn't answer before its timeout
+    /// or the deadline.
+    async fn search_slot(
+        &self,
+        slot: &Slot,
+        query: &SearchQuery,
+        page: u8,
+        request_id: RequestId,
+        deadline: Option<Duration>,
+    ) -> (String, Option<crate::Result<Page>>) {
+        let timeout = match (slot.timeout, deadline) {
+            (Some(timeout), Some(deadline)) => Some(timeout.min(deadline)),
+            (timeout, deadline) => timeout.or(deadline),
+        };
+        let search =
+            crate::provider::search_page_with_id(slot.provider.as_ref(), query, page, request_id);
+        let result = match timeout {
+            Some(timeout) => crate::runtime::with_deadline(&self.timer, timeout, search).await,
+            None => Some(search.await),
+        };
+        (slot.provider.name().to_owned(), result)
+    }
+
+    /// Closes all the providers, see [`SearchProvider::close`], then waits for
+    /// their searches in flight to complete, at most for `deadline`.
+    ///
+    /// Returns `true` if all the searches completed in time.
+    pub async fn shutdown(&self, deadline: Duration) -> bool {
+        self.slots.iter().for_each(|slot| slot.provider.close());
+        let drained =
+            futures_util::future::join_all(self.slots.iter().map(|slot| slot.provider.drained()));
+        crate::runtime::with_deadline(&self.timer, deadline, drained)
+            .await
+            .is_some()
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::entry::Page;
+    use crate::provider::{BoxFuture, Capabilities};
+    use crate::runtime::TokioTimer;
+
+    fn entry(name: &str, index: usize) -> Entry {
+        let mut entry = crate::process::tests::entry(index as u64, 1024);
+        entry.filename = format!("{name}-{index}.mkv");
+        entry.bot_name = name.parse().unwrap();
+        entry
+    }
+
+    #[test]
+    fn should_merge_with_strategy() {
+        let groups = || {
+            vec![
+                vec![entry("a", 0), entry("a", 1), entry("a", 2)],
+                vec![entry("b", 0), entry("A", 1)],
+            ]
+            .into_iter()
+            .map(|entries| {
+                let page = Page {
+                    entries,
+                    info: PageInfo {
+                        page: 0,
+                        per_page: 3,
+                        is_last_guess: true,
+                    },
+                    timing: None,
+                };
+                page.into_hits("fake", SystemTime::UNIX_EPOCH)
+            })
+            .collect::<Vec<_>>()
+        };
+        let names = |hits: Vec<SearchHit>| {
+            hits.into_iter()
+                .map(|hit| hit.entry.filename)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(MergeStrategy::Grouped.merge(groups())),
+            ["a-0.mkv", "a-1.mkv", "a-2.mkv", "b-0.mkv", "A-1.mkv"]
+        );
+        assert_eq!(
+            names(MergeStrategy::Interleaved.merge(groups())),
+            ["a-0.mkv", "b-0.mkv", "a-1.mkv", "A-1.mkv", "a-2.mkv"]
+        );
+        assert_eq!(
+            names(MergeStrategy::Deduplicated.merge(groups())),
+            ["a-0.mkv", "b-0.mkv", "a-1.mkv", "a-2.mkv"]
+        );
+    }
+
+    struct Fake {
+        name: &'static str,
+        delay: Duration,
+        count: usize,
+    }
+
+    impl SearchProvider for Fake {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        fn search_page<'a>(
+            &'a self,
+            _query: &'a SearchQuery,
+            page: u8,
+        ) -> BoxFuture<'a, crate::Result<Page>> {
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                let entries = (0..self.count)
+                    .map(|index| entry(self.name, index))
+                    .collect();
+                Ok(Page {
+                    entries,
+                    info: PageInfo {
+                        page,
+                        per_page: self.count,
+                        is_last_guess: true,
+                    },
+                    timing: None,
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_return_partial_results() {
+        let engine = MultiEngine::builder(TokioTimer)
+            .provider(Fake {
+                name: "fast",
+                delay: Duration::ZERO,
+                count: 2,
+            })
+            .provider_with_timeout(
+                Fake {
+                    name: "slow",
+                    delay: Duration::from_millis(500),
+                    count: 3,
+                },
+                Duration::from_millis(20),
+            )
+            .provider(Fake {
+                name: "slower",
+                delay: Duration::from_secs(5),
+                count: 3,
+            })
+            .deadline(Duration::from_millis(100))
+            .build();
+        let result = engine.search_page(&SearchQuery::new("file"), 0).await;
+        assert_eq!(result.hits.len(), 2);
+        assert!(result.hits.iter().all(|hit| hit.provider == "fast"));
+        assert!(result.is_partial());
+        assert_eq!(
+            result.timed_out().collect::<Vec<_>>(),
+            vec!["slow", "slower"]
+        );
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_all_providers() {
+        let engine = MultiEngine::builder(TokioTimer)
+            .provider(Fake {
+                name: "first",
+                delay: Duration::from_millis(10),
+                count: 2,
+            })
+            .provider(Fake {
+                name: "second",
+                delay: Duration::ZERO,
+                count: 3,
+            })
+            .build();
+        let result = engine.search_page(&SearchQuery::new("file"), 0).await;
+        assert_eq!(result.hits.len(), 5);
+        assert!(!result.is_partial());
+    }
+
+    /// A provider answering a page in 10ms, the last one being the page 9.
+    struct Paginated;
+
+    impl SearchProvider for Paginated {
+        fn name(&self) -> &str {
+            "paginated"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                pagination: true,
+                ..Default::default()
+            }
+        }
+
+        fn search_page<'a>(
+            &'a self,
+            _query: &'a SearchQuery,
+            page: u8,
+        ) -> BoxFuture<'a, crate::Result<Page>> {
+            Box::pin(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(Page {
+                    entries: vec![entry("paginated", page.into())],
+                    info: PageInfo {
+                        page,
+                        per_page: 1,
+                        is_last_guess: page == 9,
+                    },
+                    timing: None,
+                })
+            })
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn should_return_results_received_within_budget() {
+        let engine = MultiEngine::builder(TokioTimer)
+            .provider(Fake {
+                name: "fast",
+                delay: Duration::ZERO,
+                count: 2,
+            })
+            .provider(Paginated)
+            .page_delay(Duration::from_millis(5)..=Duration::from_millis(5))
+            .build();
+        let query = SearchQuery::new("file");
+        let result = engine
+            .search_within(&query, Duration::from_millis(35))
+            .await;
+        // the pages of paginated are received after 10ms, 25ms, 40ms...
+        assert_eq!(result.hits.len(), 4);
+        assert!(result.is_partial());
+        assert_eq!(result.served_by().collect::<Vec<_>>(), vec!["fast"]);
+        assert_eq!(result.timed_out().collect::<Vec<_>>(), vec!["paginated"]);
+        let result = engine.search_within(&query, Duration::from_secs(1)).await;
+        assert_eq!(result.hits.len(), 12);
+        assert!(!result.is_partial());
+    }
+
+    struct Failing;
+
+    impl SearchProvider for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        fn search_page<'a>(
+            &'a self,
+            _query: &'a SearchQuery,
+            _page: u8,
+        ) -> BoxFuture<'a, crate::Result<Page>> {
+            Box::pin(async { Err(crate::Error::Status(503)) })
+        }
+    }
+
+    /// A provider recording the identifiers of the searches it receives.
+    #[derive(Clone, Default)]
+    struct Recording(Arc<Mutex<Vec<RequestId>>>);
+
+    impl SearchProvider for Recording {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities::default()
+        }
+
+        fn search_page<'a>(
+            &'a self,
+            _query: &'a SearchQuery,
+            _page: u8,
+        ) -> BoxFuture<'a, crate::Result<Page>> {
+            unreachable!("the engine passes the request id")
+        }
+
+        fn search_page_with_id<'a>(
+            &'a self,
+            _query: &'a SearchQuery,
+            page: u8,
+            request_id: RequestId,
+        ) -> BoxFuture<'a, crate::Result<Page>> {
+            self.0.lock().unwrap().push(request_id);
+            Box::pin(async move {
+                Ok(Page {
+                    entries: Vec::new(),
+                    info: PageInfo {
+                        page,
+                        per_page: 0,
+                        is_last_guess: true,
+                    },
+                    timing: None,
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn should_pass_the_same_request_id_to_all_providers() {
+        let first = Recording::default();
+        let second = Recording::default();
+        let engine = MultiEngine::builder(TokioTimer)
+            .provider(first.clone())
+            .provider(second.clone())
+            .build();
+        let request_id = RequestId::from(255);
+        engine
+            .search_page_with_id(&SearchQuery::new("file"), 0, request_id)
+            .await;
+        assert_eq!(*first.0.lock().unwrap(), [request_id]);
+        assert_eq!(*second.0.lock().unwrap(), [request_id]);
+
+        engine
+            .search_within(&SearchQuery::new("file"), Duration::from_secs(1))
+            .await;
+        let first = first.0.lock().unwrap();
+        assert_eq!(first.len(), 2);
+        assert_ne!(first[1], request_id);
+        assert_eq!(second.0.lock().unwrap()[1], first[1]);
+    }
+
+    #[tokio::test]
+    async fn should_fail_over_to_next_provider() {
+        let engine = MultiEngine::builder(TokioTimer)
+            .mode(Mode::Failover)
+            .provider(Failing)
+            .provider_with_timeout(
+                Fake {
+                    name: "slow",
+                    delay: Duration::from_millis(500),
+                    count: 3,
+                },
+                Duration::from_millis(20),
+            )
+            .provider(Fake {
+                name: "backup",
+                delay: Duration::ZERO,
+                count: 2,
+            })
+            .provider(Fake {
+                name: "unused",
+                delay: Duration::ZERO,
+                count: 1,
+            })
+            .build();
+        let result = engine.search_page(&SearchQuery::new("file"), 0).await;
+        assert_eq!(result.hits.len(), 2);
+        assert_eq!(result.served_by().collect::<Vec<_>>(), vec!["backup"]);
+        assert_eq!(result.timed_out().collect::<Vec<_>>(), vec!["slow"]);
+        assert_eq!(result.outcomes.len(), 3);
+        assert!(matches!(
+            result.outcomes[0].status,
+            ProviderStatus::Failed(crate::Error::Status(503))
+        ));
+    }
+
+    #[tokio::test]
+    async fn should_stop_failover_at_deadline() {
+        let engine = MultiEngine::builder(TokioTimer)
+            .mode(Mode::Failover)
+            .provider(Fake {
+                name: "slow",
+                delay: Duration::from_millis(500),
+                count: 3,
+            })
+            .provider(Fake {
+                name: "backup",
+                delay: Duration::ZERO,
+                count: 2,
+            })
+            .deadline(Duration::from_millis(20))
+            .build();
+        let result = engine.search_page(&SearchQuery::new("file"), 0).await;
+        assert!(result.hits.is_empty());
+        assert_eq!(result.outcomes.len(), 1);
+        assert_eq!(result.served_by().count(), 0);
+    }
+
+    #[cfg(all(feature = "reqwest", feature = "sunxdcc"))]
+    #[tokio::test]
+    async fn should_shutdown_providers() {
+        let engine = MultiEngine::builder(TokioTimer)
+            .provider(crate::sunxdcc::Engine::with_url(
+                crate::http::ReqwestBackend::default(),
+                "http://127.0.0.1:1/deliver.php",
+            ))
+            .build();
+        assert!(engine.shutdown(Duration::from_secs(1)).await);
+        let result = engine.search_page(&SearchQuery::new("file"), 0).await;
+        assert!(matches!(
+            result.outcomes[0].status,
+            ProviderStatus::Failed(crate::Error::Shutdown)
+        ));
+    }
+}
@@ -0,0 +1,244 @@
+//! Validated IRC names: networks, channels and bots.
+//!
+//! The entries used to expose these names as plain strings, making it easy to
+//! pass a channel where a bot was expected. The newtypes of this module are
+//! validated when created, and (de)serialized as plain strings.
+//!
+//! IRC names are case-insensitive: the names keep their original casing but
+//! are compared, hashed and ordered following the RFC1459 casemapping, where
+//! `[]\~` are the uppercase versions of `{}|^`.
+
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+/// The error returned when creating a name from an invalid value.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("invalid {kind} name {value:?}, expected {expected}")]
+pub struct InvalidName {
+    /// The kind of name (`network`, `channel` or `bot`).
+    pub kind: &'static str,
+    /// The rejected value.
+    pub value: String,
+    /// What was expected.
+    pub expected: &'static str,
+}
+
+/// Folds a character following the RFC1459 casemapping.
+fn fold(c: char) -> char {
+    match c {
+        '[' => '{',
+        ']' => '}',
+        '\\' => '|',
+        '~' => '^',
+        _ => c.to_ascii_lowercase(),
+    }
+}
+
+/// Folds a name following the RFC1459 casemapping, equal names giving the same value.
+pub(crate) fn fold_case(value: &str) -> String {
+    value.chars().map(fold).collect()
+}
+
+/// Compares two names following the RFC1459 casemapping.
+pub fn eq_ignore_case(left: &str, right: &str) -> bool {
+    left.len() == right.len() && left.chars().map(fold).eq(right.chars().map(fold))
+}
+
+fn is_valid_word(value: &str) -> bool {
+    !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || c == ',')
+}
+
+/// Implements the conversions shared by the names.
+macro_rules! name_conversions {
+    ($name:ident) => {
+        impl $name {
+            /// Returns the name as a string.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = InvalidName;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                Self::new(value)
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = InvalidName;
+
+            fn try_from(value: String) -> Result<Self, Self::Error> {
+                Self::new(value)
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                eq_ignore_case(&self.0, &other.0)
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.chars().map(fold).for_each(|c| c.hash(state));
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.chars().map(fold).cmp(other.0.chars().map(fold))
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                eq_ignore_case(&self.0, other)
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                eq_ignore_case(&self.0, other)
+            }
+        }
+    };
+}
+
+/// The name of an IRC network (e.g. `Abjects`).
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Network(String);
+
+impl Network {
+    /// Creates a network name, which must be a single non empty word.
+    pub fn new(value: impl Into<String>) -> Result<Self, InvalidName> {
+        let value = value.into();
+        if is_valid_word(&value) {
+            Ok(Self(value))
+        } else {
+            Err(InvalidName {
+                kind: "network",
+                value,
+                expected: "a single word",
+            })
+        }
+    }
+}
+
+name_conversions!(Network);
+
+/// The name of an IRC channel, starting with `#` (e.g. `#moviegods`).
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Channel(String);
+
+impl Channel {
+    /// Creates a channel name, which must start with `#` and contain no space or comma.
+    pub fn new(value: impl Into<String>) -> Result<Self, InvalidName> {
+        let value = value.into();
+        if value.len() > 1 && value.starts_with('#') && is_valid_word(&value) {
+            Ok(Self(value))
+        } else {
+            Err(InvalidName {
+                kind: "channel",
+                value,
+                expected: "a single word starting with '#'",
+            })
+        }
+    }
+}
+
+name_conversions!(Channel);
+
+/// The nickname of a bot.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BotName(String);
+
+impl BotName {
+    /// Creates a bot name, which must be a single non empty word.
+    pub fn new(value: impl Into<String>) -> Result<Self, InvalidName> {
+        let value = value.into();
+        if is_valid_word(&value) {
+            Ok(Self(value))
+        } else {
+            Err(InvalidName {
+                kind: "bot",
+                value,
+                expected: "a single word",
+            })
+        }
+    }
+}
+
+name_conversions!(BotName);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case::test_case("#moviegods", true; "regular")]
+    #[test_case::test_case("moviegods", false; "missing prefix")]
+    #[test_case::test_case("#", false; "prefix only")]
+    #[test_case::test_case("#movie gods", false; "space")]
+    fn should_validate_channel(input: &str, valid: bool) {
+        assert_eq!(Channel::new(input).is_ok(), valid);
+    }
+
+    #[test]
+    fn should_preserve_network_casing() {
+        let network = Network::new("Abjects").unwrap();
+        assert_eq!(network.as_str(), "Abjects");
+        assert_eq!(network, "abjects");
+        assert!(Network::new("").is_err());
+    }
+
+    #[test_case::test_case("#Moviegods", "#moviegods", true; "ascii")]
+    #[test_case::test_case("#chan[1]", "#CHAN{1}", true; "brackets")]
+    #[test_case::test_case("#a\\b~", "#a|b^", true; "pipe and caret")]
+    #[test_case::test_case("#chan", "#chan2", false; "different")]
+    fn should_compare_with_casemapping(left: &str, right: &str, expected: bool) {
+        let left = Channel::new(left).unwrap();
+        let right = Channel::new(right).unwrap();
+        assert_eq!(left == right, expected);
+        let set = std::collections::HashSet::from([left.clone()]);
+        assert_eq!(set.contains(&right), expected);
+        assert_eq!(left.cmp(&right).is_eq(), expected);
+    }
+
+    #[test]
+    fn should_serialize_as_string() {
+        let bot: BotName = serde_json::from_str("\"Bot|01\"").unwrap();
+        assert_eq!(bot.as_str(), "Bot|01");
+        assert_eq!(serde_json::to_string(&bot).unwrap(), "\"Bot|01\"");
+        assert!(serde_json::from_str::<Channel>("\"nope\"").is_err());
+    }
+}
@@ -0,0 +1,292 @@
+//! Parsing of the notices sent by the XDCC bots.
+//!
+//! When a pack is requested, the bots answer with a notice telling whether the
+//! transfer starts, the request was queued or refused. The wording depends on the
+//! bot software, [`BotQueueStatus::parse`] understands the common ones (iroffer and
+//! its forks) so the download can report why a transfer doesn't start.
+//!
+//! [`PackInfo::parse`] reads the answer to `XDCC INFO`, giving the exact size of a pack.
+
+/// What a bot answered to a pack request, see [`BotQueueStatus::parse`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BotQueueStatus {
+    /// The bot is about to send the pack.
+    Sending {
+        /// The pack number, when mentioned.
+        packnum: Option<u64>,
+        /// The name of the file, when mentioned.
+        filename: Option<String>,
+    },
+    /// The slots are full, the request was queued.
+    Queued {
+        /// The pack number, when mentioned.
+        packnum: Option<u64>,
+        /// The position in the queue, starting at 1.
+        position: u32,
+        /// The size of the queue, when mentioned.
+        total: Option<u32>,
+    },
+    /// The same pack was already requested and is waiting in the queue.
+    AlreadyQueued,
+    /// The queue is full, the request must be sent again later.
+    QueueFull,
+    /// The bot doesn't offer this pack number.
+    InvalidPack,
+    /// The request was removed from the queue.
+    Removed,
+    /// The request was refused for another reason (not in the channel, too many
+    /// transfers...).
+    Denied {
+        /// The explanation given by the bot.
+        reason: String,
+    },
+}
+
+/// Removes the IRC formatting codes (bold, colors...) from a message.
+fn strip_formatting(message: &str) -> String {
+    let mut result = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    while let Some(current) = chars.next() {
+        match current {
+            '\x03' => {
+                // color code: up to two digits, optionally followed by a comma and a background
+                for _ in 0..2 {
+                    chars.next_if(char::is_ascii_digit);
+                }
+                if chars.peek() == Some(&',') {
+                    chars.next();
+                    for _ in 0..2 {
+                        chars.next_if(char::is_ascii_digit);
+                    }
+                }
+            }
+            '\x02' | '\x0f' | '\x11' | '\x16' | '\x1d' | '\x1e' | '\x1f' => {}
+            other => result.push(other),
+        }
+    }
+    result
+}
+
+/// Parses the number following `marker` in `message`, skipping a leading `#`.
+fn number_after<T: std::str::FromStr>(message: &str, marker: &str) -> Option<T> {
+    let start = message.find(marker)? + marker.len();
+    let digits = message[start..].trim_start_matches('#');
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    digits[..end].parse().ok()
+}
+
+/// Returns the byte offset of the first occurrence of the ASCII `needle` in
+/// `message`, ignoring the ASCII case.
+fn find_ignore_ascii_case(message: &str, needle: &str) -> Option<usize> {
+    message
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Extracts the quoted filename, e.g. `pack #5 ("file.mkv")`.
+fn quoted(message: &str) -> Option<String> {
+    let start = message.find('"')? + 1;
+    let end = message[start..].find('"')? + start;
+    Some(message[start..end].to_owned()).filter(|value| !value.is_empty())
+}
+
+impl BotQueueStatus {
+    /// Parses a notice sent by a bot, returns `None` if it isn't about a pack request.
+    ///
+    /// ```
+    /// use xdcc_search::notice::BotQueueStatus;
+    ///
+    /// let notice = "** All Slots Full, Added you to the main queue for pack 5 (\"file.mkv\") in position 3.";
+    /// assert_eq!(
+    ///     BotQueueStatus::parse(notice),
+    ///     Some(BotQueueStatus::Queued { packnum: Some(5), position: 3, total: None }),
+    /// );
+    /// ```
+    pub fn parse(notice: &str) -> Option<Self> {
+        let message = strip_formatting(notice);
+        let message = message.trim_start_matches(['*', ' ']).trim();
+        let lower = message.to_lowercase();
+        if lower.contains("sending you pack") {
+            return Some(Self::Sending {
+                packnum: number_after(&lower, "pack "),
+                filename: quoted(message),
+            });
+        }
+        let position = number_after(&lower, "in position ")
+            .or_else(|| number_after(&lower, "at position "))
+            .or_else(|| number_after(&lower, "position in queue: "));
+        if let Some(position) = position {
+            return Some(Self::Queued {
+                packnum: number_after(&lower, "pack "),
+                position,
+                total: number_after(&lower, " of "),
+            });
+        }
+        if lower.contains("already") && (lower.contains("queued") || lower.contains("requested")) {
+            return Some(Self::AlreadyQueued);
+        }
+        if lower.contains("invalid pack") {
+            return Some(Self::InvalidPack);
+        }
+        if lower.contains("queue") && lower.contains("full") {
+            return Some(Self::QueueFull);
+        }
+        if lower.contains("removed you from") {
+            return Some(Self::Removed);
+        }
+        // the offset is searched in the message itself, lowercasing can change the length
+        if let Some(index) = find_ignore_ascii_case(message, "denied") {
+            let reason = message[index + "denied".len()..]
+                .trim_start_matches([',', ':', ' '])
+                .trim_end_matches('.')
+                .to_owned();
+            return Some(Self::Denied { reason });
+        }
+        None
+    }
+}
+
+/// The details of a pack, as answered by a bot to `XDCC INFO #<pack>`, see [`PackInfo::parse`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PackInfo {
+    /// The name of the file.
+    pub filename: Option<String>,
+    /// The exact size of the file, in bytes.
+    pub filesize: Option<u64>,
+    /// The number of times the pack was downloaded.
+    pub downloads: Option<u64>,
+}
+
+impl PackInfo {
+    /// Parses the notices answered by a bot to `XDCC INFO`, one line per notice,
+    /// returns `None` if none of them describes the pack.
+    ///
+    /// The size is only read when given in bytes, as done by iroffer and its forks.
+    ///
+    /// ```
+    /// use xdcc_search::notice::PackInfo;
+    ///
+    /// let notices = [
+    ///     "Pack Info for Pack #5:",
+    ///     " Filename       file.mkv",
+    ///     " Filesize       1503238553 [1.4GB]",
+    ///     " Gets           12",
+    /// ];
+    /// let info = PackInfo::parse(notices).unwrap();
+    /// assert_eq!(info.filesize, Some(1503238553));
+    /// ```
+    pub fn parse<'a, I>(notices: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut info = Self::default();
+        for notice in notices {
+            let message = strip_formatting(notice);
+            let Some((key, value)) = message.trim().split_once(char::is_whitespace) else {
+                continue;
+            };
+            let value = value.trim();
+            match key.to_lowercase().as_str() {
+                "filename" if !value.is_empty() => info.filename = Some(value.to_owned()),
+                "filesize" => info.filesize = number_after(value, ""),
+                "gets" => info.downloads = number_after(value, ""),
+                _ => {}
+            }
+        }
+        (info != Self::default()).then_some(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BotQueueStatus, PackInfo};
+
+    #[test_case::test_case(
+        "** Sending you pack #5 (\"Show.S01E01.mkv\"), which is 1.2GB. (resume supported)",
+        BotQueueStatus::Sending { packnum: Some(5), filename: Some("Show.S01E01.mkv".into()) };
+        "sending"
+    )]
+    #[test_case::test_case(
+        "\x02**\x02 All Slots Full, Added you to the main queue for pack 12 (\"file.iso\") in position \x0304\x0f3. To Remove yourself at a later time type \"/MSG bot XDCC REMOVE 12\".",
+        BotQueueStatus::Queued { packnum: Some(12), position: 3, total: None };
+        "queued with formatting"
+    )]
+    #[test_case::test_case(
+        "Queue for pack #7: you are at position 4 of 10",
+        BotQueueStatus::Queued { packnum: Some(7), position: 4, total: Some(10) };
+        "queued with total"
+    )]
+    #[test_case::test_case(
+        "** All Slots Full, Denied, You already have that item queued.",
+        BotQueueStatus::AlreadyQueued;
+        "already queued"
+    )]
+    #[test_case::test_case("** Invalid Pack Number, Try Again", BotQueueStatus::InvalidPack; "invalid pack")]
+    #[test_case::test_case(
+        "** Main queue of size 10 is Full, Try Again Later",
+        BotQueueStatus::QueueFull;
+        "queue full"
+    )]
+    #[test_case::test_case(
+        "** Removed you from the queue for \"file.iso\"",
+        BotQueueStatus::Removed;
+        "removed"
+    )]
+    #[test_case::test_case(
+        "** XDCC SEND denied, you must be on a known channel to request a pack",
+        BotQueueStatus::Denied { reason: "you must be on a known channel to request a pack".into() };
+        "denied"
+    )]
+    fn should_parse_notice(input: &str, expected: BotQueueStatus) {
+        assert_eq!(BotQueueStatus::parse(input), Some(expected));
+    }
+
+    #[test]
+    fn should_parse_non_ascii_denied_notice() {
+        assert_eq!(
+            BotQueueStatus::parse("İİİİİİİİ DENIED: Ünknown user"),
+            Some(BotQueueStatus::Denied {
+                reason: "Ünknown user".into()
+            })
+        );
+        assert_eq!(
+            BotQueueStatus::parse("İİİİİİİİ denied"),
+            Some(BotQueueStatus::Denied {
+                reason: String::new()
+            })
+        );
+    }
+
+    #[test]
+    fn should_ignore_other_notices() {
+        assert_eq!(BotQueueStatus::parse("Welcome to #channel, enjoy!"), None);
+    }
+
+    #[test]
+    fn should_parse_pack_info() {
+        let notices = [
+            "\x02Pack Info for Pack #5:\x02",
+            " Filename       Show.S01E01.mkv",
+            " Filesize       1503238553 [1.4GB]",
+            " Last Modified  2024-01-01 12:00 UTC",
+            " Gets           12",
+        ];
+        assert_eq!(
+            PackInfo::parse(notices),
+            Some(PackInfo {
+                filename: Some("Show.S01E01.mkv".into()),
+                filesize: Some(1503238553),
+                downloads: Some(12),
+            })
+        );
+    }
+
+    #[test_case::test_case(&[" Filesize       [1.4GB]"]; "size without bytes")]
+    #[test_case::test_case(&["** Invalid Pack Number, Try Again"]; "invalid pack")]
+    fn shouldnt_parse_pack_info(notices: &[&str]) {
+        assert_eq!(PackInfo::parse(notices.iter().copied()), None);
+    }
+}
@@ -0,0 +1,441 @@
+//! Search providers implemented by external executables (experimental, behind
+//! the `plugin` feature).
+//!
+//! A [`ProcessProvider`] spawns an executable and exchanges JSON documents with
+//! it, one per line, over its standard input and output. It lets the users add
+//! niche or private listing sites without recompiling the crate. The standard
+//! error of the executable is inherited, for its logs.
+//!
+//! Once started, the executable introduces itself with the name of the provider
+//! and the constraints it supports, see [`Capabilities`]:
+//!
+//! ```json
+//! {"name": "mysite", "capabilities": ["pagination", "size_filter"]}
+//! ```
+//!
+//! Then, for each search, it receives the query, with only the supported
+//! constraints, and a request `id` to copy in the reply:
+//!
+//! ```json
+//! {"id": 1, "text": "ubuntu", "page": 0, "min_size": 1024}
+//! ```
+//!
+//! It replies with the entries, serialized like [`Entry`], and optionally if the
+//! page is the last one, or with an error message:
+//!
+//! ```json
+//! {"id": 1, "entries": [{"filename": "ubuntu.iso", "filesize": 1024, "downloads": 0, "packnum": 1, "channel": "#chan", "network": "net", "bot_name": "bot", "bot_speed": 0}], "is_last": true}
+//! {"id": 2, "error": "site unavailable"}
+//! ```
+//!
+//! The requests are sent one at a time. When a search is cancelled, the reply to
+//! its request is skipped. A line longer than the maximum body size fails the
+//! search with [`Error::BodyTooLarge`](crate::Error::BodyTooLarge), see
+//! [`ProcessProvider::max_body_size`].
+
+use std::io;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+
+use crate::entry::{Entry, Page, PageInfo};
+use crate::provider::{BoxFuture, Capabilities, SearchProvider, SearchQuery, SortOrder};
+
+const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// The error reported by the executable in its reply, returned as a
+/// [`Error::Transport`](crate::Error::Transport).
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("plugin error: {0}")]
+pub struct PluginError(pub String);
+
+/// The first line written by the executable.
+#[derive(serde::Deserialize)]
+struct Hello {
+    name: String,
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+impl Hello {
+    fn capabilities(&self) -> Capabilities {
+        let supports = |name: &str| self.capabilities.iter().any(|value| value == name);
+        Capabilities {
+            pagination: supports("pagination"),
+            server_sort: supports("server_sort"),
+            size_filter: supports("size_filter"),
+            date_filter: supports("date_filter"),
+            bot_filter: supports("bot_filter"),
+            channel_filter: supports("channel_filter"),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Request<'a> {
+    id: u64,
+    text: &'a str,
+    page: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bot: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel: Option<&'a str>,
+    /// In seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_age: Option<u64>,
+}
+
+impl<'a> Request<'a> {
+    fn new(id: u64, query: &'a SearchQuery, page: u8) -> Self {
+        Self {
+            id,
+            text: query.text.as_str(),
+            page,
+            min_size: query.min_size,
+            max_size: query.max_size,
+            sort: query.sort.map(|sort| match sort {
+                SortOrder::Downloads => "downloads",
+                SortOrder::Size => "size",
+                SortOrder::Filename => "filename",
+            }),
+            bot: query.bot.as_deref(),
+            channel: query.channel.as_deref(),
+            max_age: query.max_age.map(|age| age.as_secs()),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Reply {
+    Error {
+        id: u64,
+        error: String,
+    },
+    Page {
+        id: u64,
+        entries: Vec<Entry>,
+        #[serde(default)]
+        is_last: Option<bool>,
+    },
+}
+
+impl Reply {
+    fn id(&self) -> u64 {
+        match self {
+            Self::Error { id, .. } | Self::Page { id, .. } => *id,
+        }
+    }
+}
+
+/// The pipes of the executable, keeping the partially written and read lines
+/// between two calls, for the searches cancelled while sending or receiving.
+#[derive(Debug)]
+struct Pipes {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// The bytes of the requests not written yet.
+    pending: Vec<u8>,
+    /// The bytes of the line being read.
+    partial: Vec<u8>,
+    /// Set when the line being read is too large, until its end is skipped.
+    skipping: bool,
+    max_body_size: usize,
+}
+
+impl Pipes {
+    fn new(stdin: ChildStdin, stdout: ChildStdout) -> Self {
+        Self {
+            stdin,
+            stdout: BufReader::new(stdout),
+            pending: Vec::new(),
+            partial: Vec::new(),
+            skipping: false,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Writes a line, the part not written yet when cancelled is written before
+    /// the next one.
+    async fn write_line(&mut self, line: &[u8]) -> crate::Result<()> {
+        self.pending.extend_from_slice(line);
+        self.pending.push(b'\n');
+        while !self.pending.is_empty() {
+            let written = self
+                .stdin
+                .write(&self.pending)
+                .await
+                .map_err(crate::Error::transport)?;
+            if written == 0 {
+                return Err(crate::Error::transport(io::Error::from(
+                    io::ErrorKind::WriteZero,
+                )));
+            }
+            self.pending.drain(..written);
+        }
+        Ok(())
+    }
+
+    /// Reads a line, the part already read when cancelled is kept for the next call.
+    async fn read_line(&mut self) -> crate::Result<String> {
+        loop {
+            let available = self
+                .stdout
+                .fill_buf()
+                .await
+                .map_err(crate::Error::transport)?;
+            if available.is_empty() {
+                return Err(crate::Error::transport(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "plugin closed its output",
+                )));
+            }
+            let (chunk, complete) = match available.iter().position(|byte| *byte == b'\n') {
+                Some(index) => (&available[..index], true),
+                None => (available, false),
+            };
+            let consumed = chunk.len() + usize::from(complete);
+            if self.skipping {
+                self.skipping = !complete;
+            } else if self.partial.len() + chunk.len() > self.max_body_size {
+                self.partial.clear();
+                self.skipping = !complete;
+                self.stdout.consume(consumed);
+                return Err(crate::Error::BodyTooLarge {
+                    limit: self.max_body_size,
+                });
+            } else {
+                self.partial.extend_from_slice(chunk);
+                if complete {
+                    self.stdout.consume(consumed);
+                    let line = std::mem::take(&mut self.partial);
+                    return String::from_utf8(line).map_err(crate::Error::transport);
+                }
+            }
+            self.stdout.consume(consumed);
+        }
+    }
+}
+
+/// A [`SearchProvider`] delegating the searches to an external executable, see
+/// the [module documentation](self) for the protocol.
+///
+/// The executable is killed when the provider is dropped.
+#[derive(Debug)]
+pub struct ProcessProvider {
+    name: String,
+    capabilities: Capabilities,
+    pipes: tokio::sync::Mutex<Pipes>,
+    next_id: AtomicU64,
+    largest_page: AtomicUsize,
+    _child: Child,
+}
+
+impl ProcessProvider {
+    /// Spawns the executable and waits for it to introduce itself.
+    ///
+    /// Its standard input and output are replaced by pipes. Requires a tokio runtime.
+    pub async fn spawn(command: std::process::Command) -> io::Result<Self> {
+        let mut command = tokio::process::Command::from(command);
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let mut pipes = Pipes::new(
+            child.stdin.take().expect("piped stdin"),
+            child.stdout.take().expect("piped stdout"),
+        );
+        let hello: Hello =
+            serde_json::from_str(&pipes.read_line().await.map_err(io::Error::other)?)?;
+        crate::telemetry::log!(debug, "plugin {:?} started", hello.name);
+        Ok(Self {
+            capabilities: hello.capabilities(),
+            name: hello.name,
+            pipes: tokio::sync::Mutex::new(pipes),
+            next_id: AtomicU64::new(1),
+            largest_page: AtomicUsize::new(0),
+            _child: child,
+        })
+    }
+
+    /// Sets the maximum size of a reply, in bytes. A larger reply fails the search
+    /// with [`Error::BodyTooLarge`](crate::Error::BodyTooLarge) instead of being
+    /// buffered in memory.
+    ///
+    /// Defaults to 16MB.
+    pub fn max_body_size(mut self, limit: usize) -> Self {
+        self.pipes.get_mut().max_body_size = limit;
+        self
+    }
+
+    async fn request(&self, query: &SearchQuery, page: u8) -> crate::Result<Page> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let line = serde_json::to_vec(&Request::new(id, query, page))?;
+        let mut pipes = self.pipes.lock().await;
+        pipes.write_line(&line).await?;
+        let reply = loop {
+            let line = pipes.read_line().await?;
+            let reply: Reply = serde_json::from_str(&line)?;
+            // the replies to the cancelled requests are still in the pipe
+            if reply.id() == id {
+                break reply;
+            }
+        };
+        drop(pipes);
+        let (entries, is_last) = match reply {
+            Reply::Error { error, .. } => {
+                return Err(crate::Error::Transport(Box::new(PluginError(error))));
+            }
+            Reply::Page {
+                entries, is_last, ..
+            } => (entries, is_last),
+        };
+        let size = entries.len();
+        let per_page = self
+            .largest_page
+            .fetch_max(size, Ordering::Relaxed)
+            .max(size);
+        Ok(Page {
+            entries,
+            info: PageInfo {
+                page,
+                per_page,
+                is_last_guess: is_last.unwrap_or(size == 0 || size < per_page),
+            },
+            timing: None,
+        })
+    }
+}
+
+impl SearchProvider for ProcessProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    fn search_page<'a>(
+        &'a self,
+        query: &'a SearchQuery,
+        page: u8,
+    ) -> BoxFuture<'a, crate::Result<Page>> {
+        Box::pin(self.request(query, page))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{PluginError, ProcessProvider};
+    use crate::provider::{SearchProvider, SearchQuery};
+
+    const SCRIPT: &str = r#"
+echo '{"name": "fake", "capabilities": ["pagination", "size_filter"]}'
+while read -r line; do
+    id=$(echo "$line" | sed 's/.*"id":\([0-9]*\).*/\1/')
+    case "$line" in
+        *broken*) echo "{\"id\": $id, \"error\": \"site unavailable\"}" ;;
+        *slow*) printf '{"id": %s, ' "$id"; sleep 0.2; echo '"entries": []}' ;;
+        *min_size*) echo "{\"id\": $id, \"entries\": [$ENTRY], \"is_last\": true}" ;;
+        *) echo "{\"id\": $id, \"entries\": []}" ;;
+    esac
+done
+"#;
+
+    async fn provider() -> ProcessProvider {
+        let entry = serde_json::to_string(&crate::process::tests::entry(1, 10)).unwrap();
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(SCRIPT).env("ENTRY", entry);
+        ProcessProvider::spawn(command).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_search_with_plugin() {
+        let provider = provider().await;
+        assert_eq!(provider.name(), "fake");
+        assert!(provider.capabilities().pagination);
+        assert!(provider.capabilities().size_filter);
+        assert!(!provider.capabilities().server_sort);
+
+        let query = SearchQuery::new("file").min_size(5);
+        let page = crate::provider::search_page(&provider, &query, 0)
+            .await
+            .unwrap();
+        assert_eq!(page.entries, vec![crate::process::tests::entry(1, 10)]);
+        assert!(page.info.is_last_guess);
+
+        let page = provider
+            .search_page(&SearchQuery::new("file"), 1)
+            .await
+            .unwrap();
+        assert!(page.entries.is_empty());
+        assert!(page.info.is_last_guess);
+    }
+
+    #[tokio::test]
+    async fn should_forward_plugin_errors() {
+        let provider = provider().await;
+        let error = provider
+            .search_page(&SearchQuery::new("broken"), 0)
+            .await
+            .unwrap_err();
+        let crate::Error::Transport(inner) = error else {
+            panic!("unexpected error {error:?}");
+        };
+        assert_eq!(
+            inner.downcast_ref::<PluginError>(),
+            Some(&PluginError("site unavailable".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_too_large_replies() {
+        let provider = provider().await.max_body_size(64);
+        let query = SearchQuery::new("file").min_size(5);
+        let error = crate::provider::search_page(&provider, &query, 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, crate::Error::BodyTooLarge { limit: 64 }));
+        let page = provider
+            .search_page(&SearchQuery::new("file"), 0)
+            .await
+            .unwrap();
+        assert!(page.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_skip_reply_read_partially_by_cancelled_search() {
+        let provider = provider().await;
+        let query = SearchQuery::new("slow");
+        let search = provider.search_page(&query, 0);
+        let cancelled = tokio::time::timeout(std::time::Duration::from_millis(50), search).await;
+        assert!(cancelled.is_err());
+        let page = provider
+            .search_page(&SearchQuery::new("file"), 0)
+            .await
+            .unwrap();
+        assert!(page.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_plugin_exits() {
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(r#"echo '{"name": "fake"}'"#);
+        let provider = ProcessProvider::spawn(command).await.unwrap();
+        let error = provider
+            .search_page(&SearchQuery::new("file"), 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, crate::Error::Transport(_)));
+    }
+}
@@ -0,0 +1,220 @@
+//! Courtesy policies, to avoid getting banned from the IRC networks.
+//!
+//! The networks, and the bots themselves, don't like users requesting packs too
+//! often, downloading too many of them at once, or joining a channel only to
+//! request a pack. A [`NetworkPolicy`] describes the limits to respect on a
+//! network, and [`Courtesy`] tracks the activity of the application to tell
+//! whether a new pack can be requested.
+//!
+//! This crate doesn't download anything, the application reports its activity
+//! with [`Courtesy::record_request`], [`Courtesy::start_download`] and
+//! [`Courtesy::finish_download`].
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::name::Network;
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// The limits to respect on a network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetworkPolicy {
+    /// The maximum number of simultaneous downloads.
+    pub max_downloads: usize,
+    /// The maximum number of packs requested to the bots per minute.
+    pub max_requests_per_minute: usize,
+    /// How long to stay in a channel before requesting a pack.
+    pub idle_before_request: Duration,
+}
+
+impl Default for NetworkPolicy {
+    /// Conservative limits, respected by most networks.
+    fn default() -> Self {
+        Self {
+            max_downloads: 2,
+            max_requests_per_minute: 4,
+            idle_before_request: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The policies of each network, with a fallback for the other networks.
+///
+/// The default policies include stricter limits for well-known networks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Policies {
+    fallback: NetworkPolicy,
+    networks: HashMap<Network, NetworkPolicy>,
+}
+
+impl Default for Policies {
+    fn default() -> Self {
+        let strict = NetworkPolicy {
+            max_downloads: 1,
+            max_requests_per_minute: 2,
+            idle_before_request: Duration::from_secs(60),
+        };
+        ["Rizon", "irc.rizon.net", "Abjects", "irc.abjects.net"]
+            .into_iter()
+            .filter_map(|name| Network::new(name).ok())
+            .fold(Self::new(NetworkPolicy::default()), |policies, network| {
+                policies.network(network, strict)
+            })
+    }
+}
+
+impl Policies {
+    /// Creates policies applying the same limits to all the networks.
+    pub fn new(fallback: NetworkPolicy) -> Self {
+        Self {
+            fallback,
+            networks: HashMap::new(),
+        }
+    }
+
+    /// Sets the policy of a network.
+    pub fn network(mut self, network: Network, policy: NetworkPolicy) -> Self {
+        self.networks.insert(network, policy);
+        self
+    }
+
+    /// Returns the policy of a network.
+    pub fn get(&self, network: &Network) -> &NetworkPolicy {
+        self.networks.get(network).unwrap_or(&self.fallback)
+    }
+}
+
+/// Why a pack can't be requested yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum Wait {
+    /// The channel was joined too recently.
+    #[error("joined too recently, wait {0:?}")]
+    Idle(Duration),
+    /// Too many packs were requested during the last minute.
+    #[error("too many requests, wait {0:?}")]
+    RateLimited(Duration),
+    /// Too many downloads are in progress, wait for one to finish.
+    #[error("too many downloads in progress")]
+    Busy,
+}
+
+#[derive(Debug, Default)]
+struct Activity {
+    downloads: usize,
+    requests: VecDeque<Instant>,
+}
+
+/// Tracks the activity on each network, to respect their [`NetworkPolicy`].
+#[derive(Debug, Default)]
+pub struct Courtesy {
+    policies: Policies,
+    activity: HashMap<Network, Activity>,
+}
+
+impl Courtesy {
+    /// Creates a tracker respecting the given policies.
+    pub fn new(policies: Policies) -> Self {
+        Self {
+            policies,
+            activity: HashMap::new(),
+        }
+    }
+
+    /// Checks whether a pack can be requested on the network at `now`, the channel
+    /// of the bot having been joined at `joined_at`.
+    pub fn check(
+        &mut self,
+        network: &Network,
+        joined_at: Instant,
+        now: Instant,
+    ) -> Result<(), Wait> {
+        let policy = *self.policies.get(network);
+        let idle = now.saturating_duration_since(joined_at);
+        if idle < policy.idle_before_request {
+            return Err(Wait::Idle(policy.idle_before_request - idle));
+        }
+        let activity = self.activity.entry(network.clone()).or_default();
+        if activity.downloads >= policy.max_downloads {
+            return Err(Wait::Busy);
+        }
+        while activity
+            .requests
+            .front()
+            .is_some_and(|sent| now.saturating_duration_since(*sent) >= MINUTE)
+        {
+            activity.requests.pop_front();
+        }
+        if activity.requests.len() >= policy.max_requests_per_minute {
+            let oldest = activity.requests.front().copied().unwrap_or(now);
+            return Err(Wait::RateLimited(
+                MINUTE.saturating_sub(now.saturating_duration_since(oldest)),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Records that a pack was requested on the network at `now`.
+    pub fn record_request(&mut self, network: &Network, now: Instant) {
+        let activity = self.activity.entry(network.clone()).or_default();
+        activity.requests.push_back(now);
+    }
+
+    /// Records that a download started on the network.
+    pub fn start_download(&mut self, network: &Network) {
+        self.activity.entry(network.clone()).or_default().downloads += 1;
+    }
+
+    /// Records that a download finished, successfully or not, on the network.
+    pub fn finish_download(&mut self, network: &Network) {
+        if let Some(activity) = self.activity.get_mut(network) {
+            activity.downloads = activity.downloads.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_use_strict_policy_for_well_known_networks() {
+        let policies = Policies::default();
+        let rizon = Network::new("rizon").unwrap();
+        let other = Network::new("other").unwrap();
+        assert_eq!(policies.get(&rizon).max_downloads, 1);
+        assert_eq!(policies.get(&other), &NetworkPolicy::default());
+    }
+
+    #[test]
+    fn should_respect_policy() {
+        let network = Network::new("net").unwrap();
+        let policy = NetworkPolicy {
+            max_downloads: 1,
+            max_requests_per_minute: 2,
+            idle_before_request: Duration::from_secs(10),
+        };
+        let mut courtesy = Courtesy::new(Policies::new(policy));
+        let joined = Instant::now();
+        assert_eq!(
+            courtesy.check(&network, joined, joined + Duration::from_secs(4)),
+            Err(Wait::Idle(Duration::from_secs(6)))
+        );
+        let now = joined + Duration::from_secs(10);
+        assert_eq!(courtesy.check(&network, joined, now), Ok(()));
+        courtesy.record_request(&network, now);
+        courtesy.record_request(&network, now + Duration::from_secs(20));
+        assert_eq!(
+            courtesy.check(&network, joined, now + Duration::from_secs(30)),
+            Err(Wait::RateLimited(Duration::from_secs(30)))
+        );
+        assert_eq!(courtesy.check(&network, joined, now + MINUTE), Ok(()));
+        courtesy.start_download(&network);
+        assert_eq!(
+            courtesy.check(&network, joined, now + MINUTE),
+            Err(Wait::Busy)
+        );
+        courtesy.finish_download(&network);
+        assert_eq!(courtesy.check(&network, joined, now + MINUTE), Ok(()));
+    }
+}
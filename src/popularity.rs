@@ -0,0 +1,130 @@
+//! Time series of the download counters of the entries.
+//!
+//! The providers only expose the current number of downloads of a pack. By
+//! snapshotting that counter on every poll, [`Popularity`] keeps its history
+//! and can tell which releases are trending.
+
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use crate::entry::Entry;
+
+/// The download counter of an entry at a given time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Sample {
+    /// When the counter was observed.
+    pub at: SystemTime,
+    /// The number of downloads reported by the provider.
+    pub downloads: u64,
+}
+
+/// The download history of the tracked entries, indexed by [`Entry::id`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Popularity(HashMap<String, Vec<Sample>>);
+
+impl Popularity {
+    /// Snapshots the download counter of the given entries.
+    ///
+    /// A sample is only added when the counter changed since the previous one.
+    pub fn record<'a, I>(&mut self, entries: I, at: SystemTime)
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        for entry in entries {
+            let samples = self.0.entry(entry.id()).or_default();
+            if samples
+                .last()
+                .is_none_or(|last| last.downloads != entry.downloads)
+            {
+                samples.push(Sample {
+                    at,
+                    downloads: entry.downloads,
+                });
+            }
+        }
+    }
+
+    /// Returns the samples recorded for an entry, from the oldest to the newest.
+    pub fn popularity_history(&self, entry_id: &str) -> &[Sample] {
+        self.0.get(entry_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns the number of downloads an entry gained since the given time.
+    pub fn growth_since(&self, entry_id: &str, since: SystemTime) -> u64 {
+        let samples = self.popularity_history(entry_id);
+        let Some(last) = samples.last() else {
+            return 0;
+        };
+        let first = samples
+            .iter()
+            .rev()
+            .find(|sample| sample.at <= since)
+            .or(samples.first())
+            .unwrap_or(last);
+        last.downloads.saturating_sub(first.downloads)
+    }
+
+    /// Returns the entries that gained downloads since the given time, the most
+    /// downloaded first.
+    pub fn trending(&self, since: SystemTime) -> Vec<(&str, u64)> {
+        let mut result = self
+            .0
+            .keys()
+            .map(|id| (id.as_str(), self.growth_since(id, since)))
+            .filter(|(_, growth)| *growth > 0)
+            .collect::<Vec<_>>();
+        result.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        result
+    }
+
+    /// Drops the samples older than the given time, keeping at least the last
+    /// one of each entry.
+    pub fn prune(&mut self, before: SystemTime) {
+        for samples in self.0.values_mut() {
+            let keep_from = samples
+                .iter()
+                .position(|sample| sample.at >= before)
+                .unwrap_or(samples.len())
+                .min(samples.len().saturating_sub(1));
+            samples.drain(..keep_from);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    fn entry(packnum: u64, downloads: u64) -> Entry {
+        let mut entry = crate::process::tests::entry(packnum, 1024);
+        entry.downloads = downloads;
+        entry
+    }
+
+    #[test]
+    fn should_track_history_and_trends() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let hour = Duration::from_secs(3600);
+        let mut popularity = Popularity::default();
+        popularity.record(&[entry(1, 10), entry(2, 5)], start);
+        popularity.record(&[entry(1, 10), entry(2, 8)], start + hour);
+        popularity.record(&[entry(1, 12), entry(2, 30)], start + hour * 2);
+
+        let first = entry(1, 0).id();
+        let second = entry(2, 0).id();
+        assert_eq!(popularity.popularity_history(&first).len(), 2);
+        assert_eq!(popularity.popularity_history(&second).len(), 3);
+        assert_eq!(popularity.growth_since(&second, start + hour), 22);
+        assert_eq!(
+            popularity.trending(start),
+            vec![(second.as_str(), 25), (first.as_str(), 2)]
+        );
+
+        popularity.prune(start + hour * 2);
+        assert_eq!(popularity.popularity_history(&first).len(), 1);
+        assert_eq!(popularity.popularity_history(&second).len(), 1);
+        assert!(popularity.popularity_history("unknown").is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+//! Named sets of filters, reusable across searches.
+//!
+//! A [`Preset`] groups the filters of a [`SearchQuery`] under a name, e.g.
+//! `hd-movies` for "at least 2 GiB, 1080p, no CAM". Presets are usually defined in
+//! the configuration of the application and deserialized into [`Presets`]:
+//!
+//! ```
+//! use xdcc_search::preset::Presets;
+//! use xdcc_search::provider::SearchQuery;
+//!
+//! let presets: Presets = serde_json::from_str(r#"{
+//!     "hd-movies": { "min_size": 2147483648, "expression": "1080p NOT cam" }
+//! }"#).unwrap();
+//! let query = SearchQuery::new("dune").preset(&presets, "hd-movies").unwrap();
+//! assert_eq!(query.min_size, Some(2147483648));
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::expression::{Expression, ParseError};
+use crate::provider::SearchQuery;
+
+/// A named set of filters, see the [module documentation](self).
+///
+/// The constraints set in the preset replace the ones of the query, except the
+/// expression and the filename patterns which are added to the existing ones.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Preset {
+    /// The minimum file size, in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<u64>,
+    /// The maximum file size, in bytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    /// A boolean expression the filenames must match, see [`crate::expression`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+    /// Shell-like patterns the filenames must all match.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filename_globs: Vec<String>,
+    /// The audio language, as an ISO 639-1 code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// The subtitle language, as an ISO 639-1 code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitles: Option<String>,
+}
+
+/// The error returned when a preset can't be applied.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum PresetError {
+    /// No preset is registered with this name.
+    #[error("unknown preset {0:?}")]
+    Unknown(String),
+    /// The expression of the preset is invalid.
+    #[error("invalid expression in preset")]
+    Expression(#[from] ParseError),
+}
+
+impl Preset {
+    /// Applies the filters of the preset to the query.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PresetError::Expression`] if the expression of the preset is invalid.
+    pub fn apply(&self, mut query: SearchQuery) -> Result<SearchQuery, PresetError> {
+        if let Some(expression) = self.expression.as_deref() {
+            let expression = Expression::parse(expression)?;
+            query.expression = Some(match query.expression.take() {
+                Some(Expression::And(mut items)) => {
+                    items.push(expression);
+                    Expression::And(items)
+                }
+                Some(existing) => Expression::And(vec![existing, expression]),
+                None => expression,
+            });
+        }
+        query.min_size = self.min_size.or(query.min_size);
+        query.max_size = self.max_size.or(query.max_size);
+        for glob in self.filename_globs.iter() {
+            query = query.filename_glob(glob);
+        }
+        query.language = self.language.clone().or(query.language);
+        query.subtitles = self.subtitles.clone().or(query.subtitles);
+        Ok(query)
+    }
+}
+
+/// Presets indexed by name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(transparent)]
+pub struct Presets(BTreeMap<String, Preset>);
+
+impl Presets {
+    /// Registers a preset, replacing the one with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, preset: Preset) -> Option<Preset> {
+        self.0.insert(name.into(), preset)
+    }
+
+    /// Returns the preset registered with this name.
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.0.get(name)
+    }
+
+    /// Returns the names of the registered presets.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// Applies the preset registered with this name to the query.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`PresetError::Unknown`] if no preset has this name, or
+    /// [`PresetError::Expression`] if its expression is invalid.
+    pub fn apply(&self, name: &str, query: SearchQuery) -> Result<SearchQuery, PresetError> {
+        self.get(name)
+            .ok_or_else(|| PresetError::Unknown(name.to_owned()))?
+            .apply(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presets() -> Presets {
+        let mut presets = Presets::default();
+        presets.insert(
+            "hd-movies",
+            Preset {
+                min_size: Some(2 << 30),
+                expression: Some("1080p NOT cam".into()),
+                filename_globs: vec!["*.mkv".into()],
+                ..Default::default()
+            },
+        );
+        presets.insert(
+            "broken",
+            Preset {
+                expression: Some("(1080p".into()),
+                ..Default::default()
+            },
+        );
+        presets
+    }
+
+    #[test]
+    fn should_apply_preset() {
+        let query = SearchQuery::parse("dune NOT trailer")
+            .unwrap()
+            .max_size(10 << 30);
+        let query = presets().apply("hd-movies", query).unwrap();
+        assert_eq!(query.text, "dune");
+        assert_eq!(query.min_size, Some(2 << 30));
+        assert_eq!(query.max_size, Some(10 << 30));
+        let expression = query.expression.as_ref().unwrap();
+        assert!(expression.matches("Dune.2021.1080p.mkv"));
+        assert!(!expression.matches("Dune.2021.1080p.CAM.mkv"));
+        assert!(!expression.matches("Dune.2021.1080p.Trailer.mkv"));
+        assert_eq!(query.filename_patterns.len(), 1);
+    }
+
+    #[test]
+    fn shouldnt_apply_invalid_preset() {
+        let presets = presets();
+        assert_eq!(
+            presets.apply("missing", SearchQuery::new("dune")),
+            Err(PresetError::Unknown("missing".into()))
+        );
+        assert!(matches!(
+            presets.apply("broken", SearchQuery::new("dune")),
+            Err(PresetError::Expression(_))
+        ));
+    }
+
+    #[test]
+    fn should_serialize_presets() {
+        let presets = presets();
+        let json = serde_json::to_string(&presets).unwrap();
+        assert_eq!(serde_json::from_str::<Presets>(&json).unwrap(), presets);
+        assert_eq!(presets.names().collect::<Vec<_>>(), ["broken", "hd-movies"]);
+    }
+}
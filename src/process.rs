@@ -0,0 +1,477 @@
+//! Post-processing of the search results.
+//!
+//! A [`PostProcessor`] transforms the list of entries returned by a provider:
+//! removing duplicates, filtering, sorting... They are composed in a [`Pipeline`],
+//! configured once on the engine, and applied in order to every result.
+//!
+//! ```
+//! use xdcc_search::matching::Matcher;
+//! use xdcc_search::process::{Dedup, Filter, Pipeline, Rank};
+//! use xdcc_search::entry::Entry;
+//!
+//! let matcher = Matcher::new("ubuntu server");
+//! let pipeline = Pipeline::default()
+//!     .then(Dedup::default())
+//!     .then(Filter::new(move |entry: &Entry| matcher.matches(&entry.filename)))
+//!     .then(Rank::new(|entry: &Entry| {
+//!         // prefer the release groups we trust
+//!         if entry.filename.ends_with("-GOOD.mkv") { 1.0 } else { 0.0 }
+//!     }));
+//! ```
+//!
+//! The dedup and ranking steps rely on the [`Deduper`] and [`Ranker`] strategies,
+//! that can be replaced by custom implementations.
+
+use std::sync::Arc;
+
+use crate::entry::Entry;
+
+/// A step transforming a list of search results.
+pub trait PostProcessor: Send + Sync {
+    /// Transforms the entries.
+    fn process(&self, entries: Vec<Entry>) -> Vec<Entry>;
+}
+
+impl<F> PostProcessor for F
+where
+    F: Fn(Vec<Entry>) -> Vec<Entry> + Send + Sync,
+{
+    fn process(&self, entries: Vec<Entry>) -> Vec<Entry> {
+        self(entries)
+    }
+}
+
+/// An ordered list of [`PostProcessor`], itself a [`PostProcessor`].
+///
+/// The default pipeline is empty and returns the entries untouched.
+#[derive(Clone, Default)]
+pub struct Pipeline(Vec<Arc<dyn PostProcessor>>);
+
+impl std::fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("steps", &self.0.len())
+            .finish()
+    }
+}
+
+impl Pipeline {
+    /// Appends a step at the end of the pipeline.
+    pub fn then<P: PostProcessor + 'static>(mut self, step: P) -> Self {
+        self.0.push(Arc::new(step));
+        self
+    }
+
+    /// Returns the number of steps in the pipeline.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if the pipeline has no step.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl PostProcessor for Pipeline {
+    fn process(&self, entries: Vec<Entry>) -> Vec<Entry> {
+        self.0
+            .iter()
+            .fold(entries, |entries, step| step.process(entries))
+    }
+}
+
+/// A strategy deciding whether two entries are duplicates.
+pub trait Deduper: Send + Sync {
+    /// Returns `true` if `candidate` is a duplicate of the already `kept` entry.
+    fn is_duplicate(&self, kept: &Entry, candidate: &Entry) -> bool;
+}
+
+impl<F> Deduper for F
+where
+    F: Fn(&Entry, &Entry) -> bool + Send + Sync,
+{
+    fn is_duplicate(&self, kept: &Entry, candidate: &Entry) -> bool {
+        self(kept, candidate)
+    }
+}
+
+/// Entries are duplicates when they describe the same pack, offered by the same bot.
+///
+/// See [`Entry::is_same_pack`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SamePack;
+
+impl Deduper for SamePack {
+    fn is_duplicate(&self, kept: &Entry, candidate: &Entry) -> bool {
+        kept.is_same_pack(candidate)
+    }
+}
+
+/// Entries are duplicates when they have the same size and the same filename,
+/// once normalized with [`crate::matching::normalize`], whatever the bot offering them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SameFile;
+
+impl Deduper for SameFile {
+    fn is_duplicate(&self, kept: &Entry, candidate: &Entry) -> bool {
+        kept.filesize == candidate.filesize
+            && crate::matching::normalize(&kept.filename)
+                == crate::matching::normalize(&candidate.filename)
+    }
+}
+
+/// Removes the entries that are duplicates of an entry present earlier in the list.
+///
+/// Uses the [`SamePack`] strategy by default.
+pub struct Dedup(Box<dyn Deduper>);
+
+impl Default for Dedup {
+    fn default() -> Self {
+        Self::new(SamePack)
+    }
+}
+
+impl Dedup {
+    /// Creates a dedup step using the given strategy.
+    pub fn new<D: Deduper + 'static>(deduper: D) -> Self {
+        Self(Box::new(deduper))
+    }
+}
+
+impl PostProcessor for Dedup {
+    fn process(&self, entries: Vec<Entry>) -> Vec<Entry> {
+        let mut result: Vec<Entry> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            if !result.iter().any(|kept| self.0.is_duplicate(kept, &entry)) {
+                result.push(entry);
+            }
+        }
+        result
+    }
+}
+
+/// A strategy giving a score to the entries, the best ones having the highest score.
+pub trait Ranker: Send + Sync {
+    /// Returns the score of the entry.
+    fn score(&self, entry: &Entry) -> f64;
+}
+
+impl<F> Ranker for F
+where
+    F: Fn(&Entry) -> f64 + Send + Sync,
+{
+    fn score(&self, entry: &Entry) -> f64 {
+        self(entry)
+    }
+}
+
+/// Ranks the entries by number of downloads.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MostDownloaded;
+
+impl Ranker for MostDownloaded {
+    fn score(&self, entry: &Entry) -> f64 {
+        entry.downloads as f64
+    }
+}
+
+/// Ranks the entries by advertised bot speed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Fastest;
+
+impl Ranker for Fastest {
+    fn score(&self, entry: &Entry) -> f64 {
+        entry.bot_speed as f64
+    }
+}
+
+/// Ranks the entries by reliability of the bot offering them.
+impl Ranker for crate::score::BotScores {
+    fn score(&self, entry: &Entry) -> f64 {
+        crate::score::BotScores::score(self, entry.network.as_str(), entry.bot_name.as_str())
+            .value()
+    }
+}
+
+/// Ranks the entries by the speed to expect from the bot offering them, correcting
+/// the advertised speed with the speeds measured for the previous downloads,
+/// see [`BotScores::expected_speed`](crate::score::BotScores::expected_speed).
+#[derive(Clone, Debug, Default)]
+pub struct ExpectedSpeed(pub crate::score::BotScores);
+
+impl Ranker for ExpectedSpeed {
+    fn score(&self, entry: &Entry) -> f64 {
+        self.0.expected_speed(entry) as f64
+    }
+}
+
+/// Sorts the entries from the highest to the lowest score, keeping the order
+/// of the entries with the same score.
+///
+/// Uses the [`MostDownloaded`] strategy by default.
+pub struct Rank(Box<dyn Ranker>);
+
+impl Default for Rank {
+    fn default() -> Self {
+        Self::new(MostDownloaded)
+    }
+}
+
+impl Rank {
+    /// Creates a ranking step using the given strategy.
+    pub fn new<R: Ranker + 'static>(ranker: R) -> Self {
+        Self(Box::new(ranker))
+    }
+}
+
+impl PostProcessor for Rank {
+    fn process(&self, entries: Vec<Entry>) -> Vec<Entry> {
+        let mut scored = entries
+            .into_iter()
+            .map(|entry| (self.0.score(&entry), entry))
+            .collect::<Vec<_>>();
+        scored.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        scored.into_iter().map(|(_, entry)| entry).collect()
+    }
+}
+
+/// Selects the entries with the best total score whose files fit in a size budget,
+/// e.g. to fill a portable drive or a capped seedbox, keeping their order.
+///
+/// The sizes are rounded up to a thousandth of the budget to solve the selection,
+/// so the selected files never exceed the budget but may leave a little space
+/// unused. The entries with a negative score are never selected.
+pub struct Budget {
+    bytes: u64,
+    ranker: Box<dyn Ranker>,
+}
+
+impl Budget {
+    /// The number of units the budget is divided in.
+    const UNITS: u64 = 1000;
+
+    /// Creates a selection step, the entries being scored by the given strategy.
+    pub fn new<R: Ranker + 'static>(bytes: u64, ranker: R) -> Self {
+        Self {
+            bytes,
+            ranker: Box::new(ranker),
+        }
+    }
+}
+
+impl PostProcessor for Budget {
+    fn process(&self, entries: Vec<Entry>) -> Vec<Entry> {
+        let unit = self.bytes.div_ceil(Self::UNITS).max(1);
+        let capacity = (self.bytes / unit) as usize;
+        let candidates = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let score = self.ranker.score(entry);
+                let units = entry.filesize.div_ceil(unit) as usize;
+                // the entries without score still fill the remaining space
+                (score >= 0.0 && units <= capacity).then_some((index, units, score + 1e-9))
+            })
+            .collect::<Vec<_>>();
+        // best[c] is the best total score using at most c units
+        let mut best = vec![0.0; capacity + 1];
+        let mut taken = vec![vec![false; capacity + 1]; candidates.len()];
+        for (row, (_, units, score)) in candidates.iter().enumerate() {
+            for used in (*units..=capacity).rev() {
+                let with = best[used - units] + score;
+                if with > best[used] {
+                    best[used] = with;
+                    taken[row][used] = true;
+                }
+            }
+        }
+        let mut selected = vec![false; entries.len()];
+        let mut used = capacity;
+        for (row, (index, units, _)) in candidates.iter().enumerate().rev() {
+            if taken[row][used] {
+                selected[*index] = true;
+                used -= units;
+            }
+        }
+        entries
+            .into_iter()
+            .zip(selected)
+            .filter_map(|(entry, selected)| selected.then_some(entry))
+            .collect()
+    }
+}
+
+/// Sorts the entries by network, bot and pack number, so the same results always
+/// come in the same order, whatever the order returned by the provider.
+///
+/// The entries of the same pack are ordered by their other fields.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StableOrder;
+
+impl PostProcessor for StableOrder {
+    fn process(&self, mut entries: Vec<Entry>) -> Vec<Entry> {
+        entries.sort_by(|a, b| {
+            (&a.network, &a.bot_name, a.packnum)
+                .cmp(&(&b.network, &b.bot_name, b.packnum))
+                .then_with(|| a.cmp(b))
+        });
+        entries
+    }
+}
+
+/// Keeps the entries matching a predicate.
+pub struct Filter<F>(F);
+
+impl<F> Filter<F>
+where
+    F: Fn(&Entry) -> bool + Send + Sync,
+{
+    /// Creates a filter from a predicate.
+    pub fn new(predicate: F) -> Self {
+        Self(predicate)
+    }
+}
+
+impl<F> PostProcessor for Filter<F>
+where
+    F: Fn(&Entry) -> bool + Send + Sync,
+{
+    fn process(&self, mut entries: Vec<Entry>) -> Vec<Entry> {
+        entries.retain(|entry| (self.0)(entry));
+        entries
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn entry(packnum: u64, filesize: u64) -> Entry {
+        Entry {
+            filename: format!("file-{packnum}.mkv"),
+            filesize,
+            downloads: packnum,
+            packnum,
+            channel: "#chan".parse().unwrap(),
+            network: "net".parse().unwrap(),
+            bot_name: "bot".parse().unwrap(),
+            bot_speed: 0,
+            first_seen: None,
+            extras: Default::default(),
+        }
+    }
+
+    #[test]
+    fn should_apply_steps_in_order() {
+        let pipeline = Pipeline::default()
+            .then(Dedup::default())
+            .then(Filter::new(|entry: &Entry| entry.filesize > 10))
+            .then(|mut entries: Vec<Entry>| {
+                entries.reverse();
+                entries
+            });
+        let result = pipeline.process(vec![
+            entry(1, 100),
+            entry(2, 5),
+            entry(1, 100),
+            entry(3, 50),
+        ]);
+        assert_eq!(result, vec![entry(3, 50), entry(1, 100)]);
+    }
+
+    #[test]
+    fn should_keep_entries_with_empty_pipeline() {
+        let entries = vec![entry(1, 100), entry(1, 100)];
+        assert_eq!(Pipeline::default().process(entries.clone()), entries);
+    }
+
+    #[test]
+    fn should_dedup_with_strategy() {
+        let mut other_bot = entry(1, 100);
+        other_bot.bot_name = "other".parse().unwrap();
+        let mut renamed = entry(1, 100);
+        renamed.filename = "FILE_1.mkv".into();
+        renamed.packnum = 2;
+        let entries = vec![entry(1, 100), other_bot, renamed];
+
+        assert_eq!(Dedup::default().process(entries.clone()).len(), 3);
+        assert_eq!(Dedup::new(SameFile).process(entries).len(), 1);
+    }
+
+    #[test]
+    fn should_rank_with_strategy() {
+        let entries = vec![entry(1, 100), entry(3, 10), entry(2, 50)];
+        let result = Rank::default().process(entries.clone());
+        assert_eq!(result, vec![entry(3, 10), entry(2, 50), entry(1, 100)]);
+        let result = Rank::new(|entry: &Entry| entry.filesize as f64).process(entries);
+        assert_eq!(result, vec![entry(1, 100), entry(2, 50), entry(3, 10)]);
+    }
+
+    #[test_case::test_case(100, &[1, 3]; "best pair")]
+    #[test_case::test_case(60, &[3]; "single best")]
+    #[test_case::test_case(1000, &[1, 2, 3, 4]; "everything fits")]
+    #[test_case::test_case(5, &[]; "nothing fits")]
+    fn should_select_within_budget(budget: u64, expected: &[u64]) {
+        // the packnum is the number of downloads, used as score
+        let entries = vec![entry(1, 40), entry(2, 70), entry(3, 60), entry(4, 200)];
+        let result = Budget::new(budget, MostDownloaded).process(entries);
+        let packs = result.iter().map(|entry| entry.packnum).collect::<Vec<_>>();
+        assert_eq!(packs, expected);
+    }
+
+    #[test]
+    fn should_fill_budget_without_scores() {
+        let entries = vec![entry(1, 40), entry(2, 70), entry(3, 60)];
+        let result = Budget::new(110, |_: &Entry| 0.0).process(entries);
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().map(|entry| entry.filesize).sum::<u64>() <= 110);
+        let result = Budget::new(110, |_: &Entry| -1.0).process(vec![entry(1, 40)]);
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn should_sort_in_stable_order() {
+        let mut other_bot = entry(1, 100);
+        other_bot.bot_name = "Alpha".parse().unwrap();
+        let mut other_network = entry(1, 100);
+        other_network.network = "abjects".parse().unwrap();
+        let mut other_file = entry(2, 100);
+        other_file.filename = "a.mkv".into();
+        let entries = vec![
+            entry(3, 10),
+            entry(2, 50),
+            other_bot.clone(),
+            other_file.clone(),
+            other_network.clone(),
+        ];
+        let expected = vec![
+            other_network,
+            other_bot,
+            other_file,
+            entry(2, 50),
+            entry(3, 10),
+        ];
+        assert_eq!(StableOrder.process(entries.clone()), expected);
+        let mut shuffled = entries;
+        shuffled.reverse();
+        assert_eq!(StableOrder.process(shuffled), expected);
+    }
+
+    #[test]
+    fn should_rank_by_expected_speed() {
+        let mut slow = entry(1, 100);
+        slow.bot_name = "slow".parse().unwrap();
+        slow.bot_speed = 1000;
+        let mut steady = entry(2, 100);
+        steady.bot_speed = 500;
+        let mut scores = crate::score::BotScores::default();
+        // advertises 1000B/s but only delivers 100B/s
+        scores.record_transfer(&slow, 1000, std::time::Duration::from_secs(10));
+        let entries = vec![slow.clone(), steady.clone()];
+        let result = Rank::new(Fastest).process(entries.clone());
+        assert_eq!(result, vec![slow.clone(), steady.clone()]);
+        let result = Rank::new(ExpectedSpeed(scores)).process(entries);
+        assert_eq!(result, vec![steady, slow]);
+    }
+}
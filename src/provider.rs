@@ -0,0 +1,556 @@
+//! Abstraction over the search providers.
+//!
+//! Each listing site is exposed as a [`SearchProvider`], describing what it supports
+//! with its [`Capabilities`]. A [`SearchQuery`] can then be negotiated against those
+//! capabilities: the constraints the provider supports are sent to the server, the
+//! others are applied client-side on the results, see [`search_page`].
+
+use std::cmp::Reverse;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::entry::{Entry, Page};
+use crate::expression::{Expression, ParseError};
+use crate::http::RequestId;
+use crate::matching::FilenamePattern;
+use crate::process::PostProcessor;
+
+/// A boxed future, used to keep [`SearchProvider`] object safe.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// What a provider is able to do server-side.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The results can be fetched page by page.
+    pub pagination: bool,
+    /// The results can be sorted by the server, see [`SearchQuery::sort`].
+    pub server_sort: bool,
+    /// The results can be filtered on their size by the server, see [`SearchQuery::min_size`].
+    pub size_filter: bool,
+    /// The results can be filtered on their listing date by the server, see [`SearchQuery::max_age`].
+    pub date_filter: bool,
+    /// The results can be restricted to a bot by the server, see [`SearchQuery::bot`].
+    pub bot_filter: bool,
+    /// The results can be restricted to a channel by the server, see [`SearchQuery::channel`].
+    pub channel_filter: bool,
+}
+
+/// The order in which the results should be returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    /// The most downloaded first.
+    Downloads,
+    /// The biggest first.
+    Size,
+    /// Alphabetically, by filename.
+    Filename,
+}
+
+/// A search request, with its optional constraints.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    /// The search term.
+    pub text: String,
+    /// The minimum file size, in bytes.
+    pub min_size: Option<u64>,
+    /// The maximum file size, in bytes.
+    pub max_size: Option<u64>,
+    /// The order of the results.
+    pub sort: Option<SortOrder>,
+    /// The name of the bot offering the files, compared without case.
+    pub bot: Option<String>,
+    /// The channel of the bots offering the files, compared without case.
+    pub channel: Option<String>,
+    /// The maximum time since the files were first listed.
+    pub max_age: Option<Duration>,
+    /// The patterns the filenames must all match.
+    ///
+    /// No provider supports it, it's always applied client-side.
+    pub filename_patterns: Vec<FilenamePattern>,
+    /// The boolean expression the filenames must match, see [`SearchQuery::parse`].
+    ///
+    /// No provider supports it, it's always applied client-side.
+    pub expression: Option<Expression>,
+    /// The audio language detected in the filenames, as an ISO 639-1 code.
+    ///
+    /// No provider supports it, it's always applied client-side.
+    pub language: Option<String>,
+    /// The subtitle language detected in the filenames, as an ISO 639-1 code.
+    ///
+    /// No provider supports it, it's always applied client-side.
+    pub subtitles: Option<String>,
+}
+
+impl SearchQuery {
+    /// Creates a query without any constraint.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Only keeps the files of at least `value` bytes.
+    pub fn min_size(mut self, value: u64) -> Self {
+        self.min_size = Some(value);
+        self
+    }
+
+    /// Only keeps the files of at most `value` bytes.
+    pub fn max_size(mut self, value: u64) -> Self {
+        self.max_size = Some(value);
+        self
+    }
+
+    /// Sorts the results.
+    pub fn sort(mut self, value: SortOrder) -> Self {
+        self.sort = Some(value);
+        self
+    }
+
+    /// Only keeps the files offered by the given bot.
+    pub fn bot(mut self, value: impl Into<String>) -> Self {
+        self.bot = Some(value.into());
+        self
+    }
+
+    /// Only keeps the files offered in the given channel.
+    pub fn channel(mut self, value: impl Into<String>) -> Self {
+        self.channel = Some(value.into());
+        self
+    }
+
+    /// Only keeps the files first listed within the given duration.
+    ///
+    /// The files for which the provider doesn't report an age are kept.
+    pub fn max_age(mut self, value: Duration) -> Self {
+        self.max_age = Some(value);
+        self
+    }
+
+    /// Creates a query from a boolean expression, see [`crate::expression`].
+    ///
+    /// The search term sent to the providers is the broadest one, given by
+    /// [`Expression::search_term`], the expression being applied client-side.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let expression = Expression::parse(input)?;
+        let mut query = Self::new(expression.search_term());
+        query.expression = Some(expression);
+        Ok(query)
+    }
+
+    /// Only keeps the files whose name matches the shell-like pattern (e.g. `*.iso`),
+    /// see [`Glob`](crate::matching::Glob).
+    pub fn filename_glob(mut self, pattern: &str) -> Self {
+        self.filename_patterns
+            .push(FilenamePattern::Glob(crate::matching::Glob::new(pattern)));
+        self
+    }
+
+    /// Only keeps the files whose name matches the regular expression.
+    #[cfg(feature = "regex")]
+    pub fn filename_matches(mut self, regex: regex::Regex) -> Self {
+        self.filename_patterns.push(FilenamePattern::Regex(regex));
+        self
+    }
+
+    /// Only keeps the files tagged with the given audio language (e.g. `fr`),
+    /// see [`FileMetadata`](crate::metadata::FileMetadata).
+    pub fn language(mut self, value: impl Into<String>) -> Self {
+        self.language = Some(value.into());
+        self
+    }
+
+    /// Only keeps the files tagged with subtitles in the given language (e.g. `fr`),
+    /// see [`FileMetadata`](crate::metadata::FileMetadata).
+    pub fn subtitles(mut self, value: impl Into<String>) -> Self {
+        self.subtitles = Some(value.into());
+        self
+    }
+
+    /// Applies the filters of the preset registered with this name, see
+    /// [`Presets::apply`](crate::preset::Presets::apply).
+    ///
+    /// ```
+    /// use xdcc_search::preset::{Preset, Presets};
+    /// use xdcc_search::provider::SearchQuery;
+    ///
+    /// let mut presets = Presets::default();
+    /// presets.insert("hd-movies", Preset {
+    ///     min_size: Some(2 << 30),
+    ///     expression: Some("1080p NOT cam".into()),
+    ///     ..Default::default()
+    /// });
+    /// let query = SearchQuery::new("dune").preset(&presets, "hd-movies").unwrap();
+    /// assert_eq!(query.min_size, Some(2 << 30));
+    /// assert!(SearchQuery::new("dune").preset(&presets, "nope").is_err());
+    /// ```
+    pub fn preset(
+        self,
+        presets: &crate::preset::Presets,
+        name: &str,
+    ) -> Result<Self, crate::preset::PresetError> {
+        presets.apply(name, self)
+    }
+
+    /// Splits the query between the part to send to a provider with the given
+    /// capabilities, and the part to apply client-side on its results.
+    pub fn negotiate(&self, capabilities: Capabilities) -> (SearchQuery, ClientSide) {
+        let mut server = SearchQuery::new(self.text.clone());
+        let mut client = ClientSide::default();
+        if capabilities.size_filter {
+            server.min_size = self.min_size;
+            server.max_size = self.max_size;
+        } else {
+            client.min_size = self.min_size;
+            client.max_size = self.max_size;
+        }
+        if capabilities.server_sort {
+            server.sort = self.sort;
+        } else {
+            client.sort = self.sort;
+        }
+        if capabilities.bot_filter {
+            server.bot = self.bot.clone();
+        } else {
+            client.bot = self.bot.clone();
+        }
+        if capabilities.channel_filter {
+            server.channel = self.channel.clone();
+        } else {
+            client.channel = self.channel.clone();
+        }
+        if capabilities.date_filter {
+            server.max_age = self.max_age;
+        } else {
+            client.max_age = self.max_age;
+        }
+        client.filename_patterns = self.filename_patterns.clone();
+        client.expression = self.expression.clone();
+        client.language = self.language.clone();
+        client.subtitles = self.subtitles.clone();
+        (server, client)
+    }
+}
+
+/// The constraints of a [`SearchQuery`] that a provider doesn't support,
+/// applied on its results.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientSide {
+    /// The minimum file size, in bytes.
+    pub min_size: Option<u64>,
+    /// The maximum file size, in bytes.
+    pub max_size: Option<u64>,
+    /// The order of the results.
+    pub sort: Option<SortOrder>,
+    /// The name of the bot offering the files.
+    pub bot: Option<String>,
+    /// The channel of the bots offering the files.
+    pub channel: Option<String>,
+    /// The maximum time since the files were first listed.
+    pub max_age: Option<Duration>,
+    /// The patterns the filenames must all match.
+    pub filename_patterns: Vec<FilenamePattern>,
+    /// The boolean expression the filenames must match.
+    pub expression: Option<Expression>,
+    /// The audio language detected in the filenames.
+    pub language: Option<String>,
+    /// The subtitle language detected in the filenames.
+    pub subtitles: Option<String>,
+}
+
+impl ClientSide {
+    /// Returns `true` if there is nothing to apply.
+    pub fn is_empty(&self) -> bool {
+        self.min_size.is_none()
+            && self.max_size.is_none()
+            && self.sort.is_none()
+            && self.bot.is_none()
+            && self.channel.is_none()
+            && self.max_age.is_none()
+            && self.filename_patterns.is_empty()
+            && self.expression.is_none()
+            && self.language.is_none()
+            && self.subtitles.is_none()
+    }
+
+    fn matches_metadata(&self, entry: &Entry) -> bool {
+        if self.language.is_none() && self.subtitles.is_none() {
+            return true;
+        }
+        let metadata = entry.metadata();
+        self.language
+            .as_ref()
+            .is_none_or(|code| metadata.has_language(code))
+            && self
+                .subtitles
+                .as_ref()
+                .is_none_or(|code| metadata.has_subtitles(code))
+    }
+}
+
+impl PostProcessor for ClientSide {
+    fn process(&self, mut entries: Vec<Entry>) -> Vec<Entry> {
+        let now = std::time::SystemTime::now();
+        entries.retain(|entry| {
+            self.min_size.is_none_or(|min| entry.filesize >= min)
+                && self.max_size.is_none_or(|max| entry.filesize <= max)
+                && self
+                    .bot
+                    .as_ref()
+                    .is_none_or(|bot| entry.bot_name == bot.as_str())
+                && self
+                    .channel
+                    .as_ref()
+                    .is_none_or(|channel| entry.channel == channel.as_str())
+                && self
+                    .max_age
+                    .is_none_or(|max| entry.age_at(now).is_none_or(|age| age <= max))
+                && self
+                    .filename_patterns
+                    .iter()
+                    .all(|pattern| pattern.matches(&entry.filename))
+                && self
+                    .expression
+                    .as_ref()
+                    .is_none_or(|expression| expression.matches(&entry.filename))
+                && self.matches_metadata(entry)
+        });
+        match self.sort {
+            Some(SortOrder::Downloads) => entries.sort_by_key(|entry| Reverse(entry.downloads)),
+            Some(SortOrder::Size) => entries.sort_by_key(|entry| Reverse(entry.filesize)),
+            Some(SortOrder::Filename) => entries.sort_by(|a, b| a.filename.cmp(&b.filename)),
+            None => {}
+        }
+        entries
+    }
+}
+
+/// A site listing XDCC packs.
+pub trait SearchProvider: Send + Sync {
+    /// The name of the provider, used in logs and results annotations.
+    fn name(&self) -> &str;
+
+    /// What the provider supports server-side.
+    fn capabilities(&self) -> Capabilities;
+
+    /// Fetches a page of results.
+    ///
+    /// The query only contains the constraints allowed by [`SearchProvider::capabilities`],
+    /// use [`search_page`] to negotiate the others.
+    fn search_page<'a>(
+        &'a self,
+        query: &'a SearchQuery,
+        page: u8,
+    ) -> BoxFuture<'a, crate::Result<Page>>;
+
+    /// Same as [`SearchProvider::search_page`] with the [`RequestId`] of the search,
+    /// e.g. to correlate the requests sent to several providers for the same search.
+    ///
+    /// Ignores the identifier by default.
+    fn search_page_with_id<'a>(
+        &'a self,
+        query: &'a SearchQuery,
+        page: u8,
+        request_id: RequestId,
+    ) -> BoxFuture<'a, crate::Result<Page>> {
+        let _ = request_id;
+        self.search_page(query, page)
+    }
+
+    /// Estimates the number of results of a search term, exactly when the provider
+    /// reports it, from the pages already fetched otherwise.
+    ///
+    /// Returns `None` by default.
+    fn estimate_total(&self, query: &str) -> Option<crate::entry::Estimate> {
+        let _ = query;
+        None
+    }
+
+    /// Stops accepting new searches, the next ones fail with [`Error::Shutdown`](crate::Error::Shutdown).
+    ///
+    /// Does nothing by default.
+    fn close(&self) {}
+
+    /// Completes once the searches in flight are over.
+    ///
+    /// Completes immediately by default.
+    fn drained(&self) -> BoxFuture<'_, ()> {
+        Box::pin(std::future::ready(()))
+    }
+}
+
+/// Fetches a page of results from a provider, pushing the supported constraints
+/// of the query to the server and applying the others client-side.
+pub async fn search_page<P>(provider: &P, query: &SearchQuery, page: u8) -> crate::Result<Page>
+where
+    P: SearchProvider + ?Sized,
+{
+    search_page_with_id(provider, query, page, RequestId::generate()).await
+}
+
+/// Same as [`search_page`] with the [`RequestId`] of the search, see
+/// [`SearchProvider::search_page_with_id`].
+pub async fn search_page_with_id<P>(
+    provider: &P,
+    query: &SearchQuery,
+    page: u8,
+    request_id: RequestId,
+) -> crate::Result<Page>
+where
+    P: SearchProvider + ?Sized,
+{
+    let (server, client) = query.negotiate(provider.capabilities());
+    let mut found = provider
+        .search_page_with_id(&server, page, request_id)
+        .await?;
+    if !client.is_empty() {
+        found.entries = client.process(found.entries);
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE_ONLY: Capabilities = Capabilities {
+        pagination: true,
+        server_sort: false,
+        size_filter: true,
+        date_filter: false,
+        bot_filter: false,
+        channel_filter: false,
+    };
+
+    #[test]
+    fn should_negotiate_query() {
+        let query = SearchQuery::new("ubuntu")
+            .min_size(10)
+            .sort(SortOrder::Size)
+            .bot("Bot");
+        let (server, client) = query.negotiate(SIZE_ONLY);
+        assert_eq!(server, SearchQuery::new("ubuntu").min_size(10));
+        assert_eq!(
+            client,
+            ClientSide {
+                sort: Some(SortOrder::Size),
+                bot: Some("Bot".into()),
+                ..Default::default()
+            }
+        );
+
+        let (server, client) = query.negotiate(Capabilities::default());
+        assert_eq!(server, SearchQuery::new("ubuntu"));
+        assert_eq!(client.min_size, Some(10));
+        assert_eq!(client.sort, Some(SortOrder::Size));
+    }
+
+    #[test]
+    fn should_filter_on_age() {
+        let client = SearchQuery::new("show")
+            .max_age(Duration::from_secs(3600))
+            .negotiate(Capabilities::default())
+            .1;
+        let entry = |packnum: u64, age: Option<u64>| {
+            let mut entry = crate::process::tests::entry(packnum, 1024);
+            entry.first_seen =
+                age.map(|age| std::time::SystemTime::now() - Duration::from_secs(age));
+            entry
+        };
+        let entries = client.process(vec![
+            entry(1, Some(60)),
+            entry(2, Some(7200)),
+            entry(3, None),
+        ]);
+        let packnums = entries
+            .iter()
+            .map(|entry| entry.packnum)
+            .collect::<Vec<_>>();
+        assert_eq!(packnums, [1, 3]);
+        assert!(entries[0].age().unwrap() >= Duration::from_secs(60));
+        assert_eq!(entries[1].age(), None);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn should_filter_on_filename() {
+        let client = SearchQuery::new("ubuntu")
+            .filename_glob("*.iso")
+            .filename_matches(regex::Regex::new(r"\b24\.04\b").unwrap())
+            .negotiate(Capabilities::default())
+            .1;
+        let entry = |filename: &str| {
+            let mut entry = crate::process::tests::entry(1, 1024);
+            entry.filename = filename.into();
+            entry
+        };
+        let entries = client.process(vec![
+            entry("ubuntu-24.04-desktop.iso"),
+            entry("ubuntu-22.04-desktop.iso"),
+            entry("ubuntu-24.04-desktop.iso.torrent"),
+        ]);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].filename, "ubuntu-24.04-desktop.iso");
+    }
+
+    #[test]
+    fn should_filter_on_expression() {
+        let query = SearchQuery::parse("ubuntu (22.04 OR 24.04) NOT beta").unwrap();
+        assert_eq!(query.text, "ubuntu");
+        let client = query.negotiate(Capabilities::default()).1;
+        let entry = |filename: &str| {
+            let mut entry = crate::process::tests::entry(1, 1024);
+            entry.filename = filename.into();
+            entry
+        };
+        let entries = client.process(vec![
+            entry("ubuntu-24.04-desktop.iso"),
+            entry("ubuntu-24.04-beta-desktop.iso"),
+            entry("ubuntu-20.04-desktop.iso"),
+        ]);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].filename, "ubuntu-24.04-desktop.iso");
+    }
+
+    #[test]
+    fn should_filter_on_language() {
+        let client = SearchQuery::new("show")
+            .language("fr")
+            .negotiate(Capabilities::default())
+            .1;
+        let entry = |filename: &str| {
+            let mut entry = crate::process::tests::entry(1, 1024);
+            entry.filename = filename.into();
+            entry
+        };
+        let entries = client.process(vec![
+            entry("Show.S01E01.FRENCH.mkv"),
+            entry("Show.S01E01.VOSTFR.mkv"),
+            entry("Show.S01E01.ITA.mkv"),
+        ]);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].filename, "Show.S01E01.FRENCH.mkv");
+    }
+
+    #[cfg(feature = "sunxdcc")]
+    #[test]
+    fn should_apply_client_side_constraints() {
+        let engine = crate::sunxdcc::Engine::new(crate::http::StaticBackend(include_str!(
+            "../resources/ubuntu.json"
+        )));
+        let query = SearchQuery::new("ubuntu")
+            .max_size(1024 * 1024 * 1024)
+            .sort(SortOrder::Downloads);
+        let page = crate::runtime::now_or_never(search_page(&engine, &query, 0));
+        let entries = page.unwrap().entries;
+        assert!(!entries.is_empty());
+        assert!(
+            entries
+                .iter()
+                .all(|entry| entry.filesize <= 1024 * 1024 * 1024)
+        );
+        assert!(entries.is_sorted_by(|a, b| a.downloads >= b.downloads));
+    }
+}
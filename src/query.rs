@@ -0,0 +1,198 @@
+//! Helpers to work on search terms before sending them to a provider.
+
+/// Generates alternate spellings of a search term, to improve the recall on
+/// inconsistently named releases.
+///
+/// The original term (trimmed) always comes first, followed by the variants:
+///
+/// * episode formats: `S01E02` ↔ `1x02`,
+/// * common synonyms: `&` ↔ `and`,
+/// * separators: dots and underscores ↔ spaces.
+///
+/// # Example
+///
+/// ```
+/// let terms = xdcc_search::query::expand("tom & jerry s01e02");
+/// assert!(terms.contains(&"tom and jerry 1x02".to_string()));
+/// assert!(terms.contains(&"tom.&.jerry.s01e02".to_string()));
+/// ```
+pub fn expand(query: &str) -> Vec<String> {
+    let query = query.trim();
+    let mut variants = Vec::new();
+    push_unique(&mut variants, query.to_owned());
+    push_unique(&mut variants, join_words(query, " "));
+    for transform in [swap_episode, swap_ampersand] {
+        for index in 0..variants.len() {
+            if let Some(alternate) = transform(&variants[index]) {
+                push_unique(&mut variants, alternate);
+            }
+        }
+    }
+    for index in 0..variants.len() {
+        let dotted = join_words(&variants[index], ".");
+        push_unique(&mut variants, dotted);
+    }
+    variants
+}
+
+/// Normalizes a search term so that equivalent queries share the same cache entries:
+/// the words are split on whitespace, lowercased and sorted.
+///
+/// # Example
+///
+/// ```
+/// use xdcc_search::query::normalize;
+///
+/// assert_eq!(normalize(" Ubuntu  24.04"), "24.04 ubuntu");
+/// assert_eq!(normalize("ubuntu 24.04"), normalize("24.04 UBUNTU"));
+/// ```
+pub fn normalize(query: &str) -> String {
+    let mut words = query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    words.sort_unstable();
+    words.join(" ")
+}
+
+/// Generates lighter versions of a search term, each one dropping a single word,
+/// starting with the last one, to suggest alternatives to a search without results.
+///
+/// A term made of a single word can't be relaxed.
+///
+/// # Example
+///
+/// ```
+/// let terms = xdcc_search::query::relax("ubuntu 24.04 arm64");
+/// assert_eq!(terms, ["ubuntu 24.04", "ubuntu arm64", "24.04 arm64"]);
+/// ```
+pub fn relax(query: &str) -> Vec<String> {
+    let words = query.split_whitespace().collect::<Vec<_>>();
+    if words.len() < 2 {
+        return Vec::new();
+    }
+    let mut variants = Vec::with_capacity(words.len());
+    for dropped in (0..words.len()).rev() {
+        let variant = words
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != dropped)
+            .map(|(_, word)| *word)
+            .collect::<Vec<_>>()
+            .join(" ");
+        push_unique(&mut variants, variant);
+    }
+    variants
+}
+
+/// An alternative search term returning results, see [`relax`].
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Suggestion {
+    /// The suggested search term.
+    pub query: String,
+    /// The number of entries found on the first page of results.
+    pub results: usize,
+}
+
+fn push_unique(variants: &mut Vec<String>, value: String) {
+    if !value.is_empty() && !variants.contains(&value) {
+        variants.push(value);
+    }
+}
+
+fn words(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(|c: char| c.is_whitespace() || c == '.' || c == '_')
+        .filter(|word| !word.is_empty())
+}
+
+fn join_words(value: &str, separator: &str) -> String {
+    words(value).collect::<Vec<_>>().join(separator)
+}
+
+/// Rewrites each word with `replace`, returns `None` if no word was changed.
+fn map_words(value: &str, replace: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let mut changed = false;
+    let result = value
+        .split(' ')
+        .map(|word| match replace(word) {
+            Some(other) => {
+                changed = true;
+                other
+            }
+            None => word.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    changed.then_some(result)
+}
+
+fn parse_number(value: &str, max_len: usize) -> Option<u16> {
+    if value.is_empty() || value.len() > max_len || !value.bytes().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn swap_episode(value: &str) -> Option<String> {
+    map_words(value, |word| {
+        let lower = word.to_ascii_lowercase();
+        if let Some((season, episode)) = lower.strip_prefix('s').and_then(|v| v.split_once('e')) {
+            let season = parse_number(season, 2)?;
+            let episode = parse_number(episode, 3)?;
+            return Some(format!("{season}x{episode:02}"));
+        }
+        let (season, episode) = lower.split_once('x')?;
+        let season = parse_number(season, 2)?;
+        let episode = parse_number(episode, 3)?;
+        Some(format!("S{season:02}E{episode:02}"))
+    })
+}
+
+fn swap_ampersand(value: &str) -> Option<String> {
+    map_words(value, |word| {
+        if word == "&" {
+            Some("and".into())
+        } else if word.eq_ignore_ascii_case("and") {
+            Some("&".into())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[test_case::test_case("ubuntu", &["ubuntu"]; "single word")]
+    #[test_case::test_case("show s01e02", &["show s01e02", "show 1x02", "show.s01e02", "show.1x02"]; "season episode")]
+    #[test_case::test_case("show 2x10", &["show 2x10", "show S02E10", "show.2x10", "show.S02E10"]; "short episode")]
+    #[test_case::test_case("Show.Name", &["Show.Name", "Show Name"]; "dotted")]
+    #[test_case::test_case("a & b", &["a & b", "a and b", "a.&.b", "a.and.b"]; "ampersand")]
+    #[test_case::test_case("  ", &[]; "empty")]
+    fn should_expand(input: &str, expected: &[&str]) {
+        assert_eq!(super::expand(input), expected);
+    }
+
+    #[test_case::test_case("ubuntu", "ubuntu"; "single word")]
+    #[test_case::test_case("  Ubuntu \t 24.04 ", "24.04 ubuntu"; "whitespace and case")]
+    #[test_case::test_case("Tom.&.Jerry", "tom.&.jerry"; "dots kept")]
+    #[test_case::test_case("   ", ""; "empty")]
+    fn should_normalize(input: &str, expected: &str) {
+        assert_eq!(super::normalize(input), expected);
+    }
+
+    #[test_case::test_case("ubuntu", &[]; "single word")]
+    #[test_case::test_case(" ubuntu  24.04 ", &["ubuntu", "24.04"]; "two words")]
+    #[test_case::test_case("a b a", &["a b", "a a", "b a"]; "repeated word")]
+    #[test_case::test_case("a a", &["a"]; "duplicate variants")]
+    fn should_relax(input: &str, expected: &[&str]) {
+        assert_eq!(super::relax(input), expected);
+    }
+
+    #[test_case::test_case("box"; "not a number")]
+    #[test_case::test_case("1920x1080"; "resolution")]
+    #[test_case::test_case("season"; "word starting with s")]
+    fn shouldnt_swap_episode(input: &str) {
+        assert_eq!(super::swap_episode(input), None);
+    }
+}
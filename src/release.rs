@@ -0,0 +1,132 @@
+//! Grouping of the entries offering the same file.
+//!
+//! The same release is usually offered by several bots, sometimes on several
+//! networks. [`group_releases`] gathers them so that a single line can be shown
+//! per release, with the list of the sources to choose from.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::entry::Entry;
+
+/// The entries offering the same file, see [`group_releases`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReleaseGroup {
+    /// The preferred source, the most downloaded one, then the fastest one.
+    pub canonical: Entry,
+    /// The other sources offering the same file, in the order they were found.
+    pub mirrors: Vec<Entry>,
+}
+
+impl ReleaseGroup {
+    /// Returns all the sources, starting with the canonical one.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        std::iter::once(&self.canonical).chain(self.mirrors.iter())
+    }
+
+    /// Returns the number of sources offering the file.
+    pub fn source_count(&self) -> usize {
+        self.mirrors.len() + 1
+    }
+
+    /// Returns the number of distinct bots offering the file.
+    pub fn bot_count(&self) -> usize {
+        self.entries()
+            .map(|entry| (entry.network.as_str(), entry.bot_name.as_str()))
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    /// Returns the number of distinct networks offering the file.
+    pub fn network_count(&self) -> usize {
+        self.entries()
+            .map(|entry| entry.network.as_str())
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    /// Returns the sum of the downloads of all the sources.
+    pub fn total_downloads(&self) -> u64 {
+        self.entries().map(|entry| entry.downloads).sum()
+    }
+
+    fn push(&mut self, entry: Entry) {
+        if self.entries().any(|item| item.is_same_pack(&entry)) {
+            return;
+        }
+        let preferred = (entry.downloads, entry.bot_speed)
+            > (self.canonical.downloads, self.canonical.bot_speed);
+        if preferred {
+            let previous = std::mem::replace(&mut self.canonical, entry);
+            self.mirrors.insert(0, previous);
+        } else {
+            self.mirrors.push(entry);
+        }
+    }
+}
+
+/// Groups the entries offering the same file, with the same size and the same
+/// normalized filename.
+///
+/// The groups are returned in the order their first entry appears, and the
+/// same pack listed several times is only kept once.
+pub fn group_releases(entries: &[Entry]) -> Vec<ReleaseGroup> {
+    let mut groups: Vec<ReleaseGroup> = Vec::new();
+    let mut index: HashMap<(String, u64), usize> = HashMap::new();
+    for entry in entries {
+        let key = (crate::matching::normalize(&entry.filename), entry.filesize);
+        match index.get(&key) {
+            Some(position) => groups[*position].push(entry.clone()),
+            None => {
+                index.insert(key, groups.len());
+                groups.push(ReleaseGroup {
+                    canonical: entry.clone(),
+                    mirrors: Vec::new(),
+                });
+            }
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(network: &str, bot: &str, filename: &str, downloads: u64) -> Entry {
+        let mut entry = crate::process::tests::entry(1, 1024);
+        entry.filename = filename.into();
+        entry.downloads = downloads;
+        entry.network = network.parse().unwrap();
+        entry.bot_name = bot.parse().unwrap();
+        entry
+    }
+
+    #[test]
+    fn should_group_mirrors() {
+        let entries = vec![
+            entry("net", "first", "Show.S01E01.mkv", 10),
+            entry("net", "other", "Another.mkv", 1),
+            entry("net", "second", "show s01e01.mkv", 50),
+            entry("other", "first", "Show.S01E01.mkv", 5),
+            entry("net", "first", "Show.S01E01.mkv", 10),
+        ];
+        let groups = group_releases(&entries);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].canonical.bot_name, "second");
+        assert_eq!(groups[0].source_count(), 3);
+        assert_eq!(groups[0].bot_count(), 3);
+        assert_eq!(groups[0].network_count(), 2);
+        assert_eq!(groups[0].total_downloads(), 65);
+        assert_eq!(groups[0].mirrors[0].bot_name, "first");
+        assert_eq!(groups[1].canonical.filename, "Another.mkv");
+        assert!(groups[1].mirrors.is_empty());
+    }
+
+    #[test]
+    fn shouldnt_group_different_sizes() {
+        let mut other = entry("net", "second", "Show.S01E01.mkv", 0);
+        other.filesize = 2048;
+        let groups = group_releases(&[entry("net", "first", "Show.S01E01.mkv", 0), other]);
+        assert_eq!(groups.len(), 2);
+    }
+}
@@ -0,0 +1,365 @@
+//! Executor related abstractions.
+//!
+//! The engines never depend on a specific async runtime. When they need to wait,
+//! for example between the pages of a crawl, they rely on a [`Timer`], usually
+//! implemented by the [`HttpBackend`](crate::http::HttpBackend) itself.
+//!
+//! The few components spawning background tasks (e.g. the simulated provider of
+//! the `testing` feature) own them in a [`TaskSet`], so no task outlives its owner.
+
+use std::future::Future;
+use std::ops::RangeInclusive;
+#[cfg(feature = "sunxdcc")]
+use std::sync::Mutex;
+#[cfg(feature = "sunxdcc")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "sunxdcc")]
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+/// Something able to pause the current task for a given duration.
+pub trait Timer: Send + Sync {
+    /// Completes once `duration` has elapsed.
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+/// A [`Timer`] relying on the tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioTimer;
+
+#[cfg(feature = "tokio")]
+impl Timer for TokioTimer {
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Timer for crate::http::ReqwestBackend {
+    async fn sleep(&self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
+
+/// How a task of a [`TaskSet`] ended.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaskExit {
+    /// The name given when spawning the task.
+    pub name: String,
+    /// The failure of the task, `None` if it completed.
+    pub failure: Option<TaskFailure>,
+}
+
+/// Why a task of a [`TaskSet`] didn't complete.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TaskFailure {
+    /// The task panicked, with the panic message when it's a string.
+    Panicked(Option<String>),
+    /// The task was aborted.
+    Cancelled,
+}
+
+/// A set of named background tasks, spawned on the tokio runtime and owned by the
+/// component that spawned them: the tasks still running are aborted when the set
+/// is dropped, and the panics are logged and reported when joining them.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Default)]
+pub struct TaskSet {
+    tasks: tokio::task::JoinSet<()>,
+    names: std::collections::HashMap<tokio::task::Id, String>,
+}
+
+#[cfg(feature = "tokio")]
+impl TaskSet {
+    /// Creates an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawns a task in the set, the name being used in the logs and the [`TaskExit`].
+    ///
+    /// Must be called inside a tokio runtime.
+    pub fn spawn<F>(&mut self, name: impl Into<String>, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = self.tasks.spawn(task);
+        self.names.insert(handle.id(), name.into());
+    }
+
+    /// Returns the number of tasks not joined yet, finished or not.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if all the tasks were joined.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Waits for the next task to end, returns `None` once the set is empty.
+    pub async fn join_next(&mut self) -> Option<TaskExit> {
+        let result = self.tasks.join_next_with_id().await?;
+        Some(self.exit(result))
+    }
+
+    /// Joins the tasks that already ended, without waiting for the others, to free
+    /// their resources in long running loops.
+    pub fn reap(&mut self) -> Vec<TaskExit> {
+        let mut exits = Vec::new();
+        while let Some(result) = self.tasks.try_join_next_with_id() {
+            exits.push(self.exit(result));
+        }
+        exits
+    }
+
+    /// Aborts all the tasks and waits for them to end.
+    pub async fn shutdown(&mut self) -> Vec<TaskExit> {
+        self.tasks.abort_all();
+        let mut exits = Vec::new();
+        while let Some(exit) = self.join_next().await {
+            exits.push(exit);
+        }
+        exits
+    }
+
+    fn exit(&mut self, result: Result<(tokio::task::Id, ()), tokio::task::JoinError>) -> TaskExit {
+        let (id, failure) = match result {
+            Ok((id, ())) => (id, None),
+            Err(error) if error.is_panic() => {
+                let id = error.id();
+                let payload = error.into_panic();
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| (*message).to_owned())
+                    .or_else(|| payload.downcast_ref::<String>().cloned());
+                (id, Some(TaskFailure::Panicked(message)))
+            }
+            Err(error) => (error.id(), Some(TaskFailure::Cancelled)),
+        };
+        let name = self.names.remove(&id).unwrap_or_default();
+        if let Some(TaskFailure::Panicked(message)) = &failure {
+            crate::telemetry::log!(warn, "task {name:?} panicked: {message:?}");
+        }
+        TaskExit { name, failure }
+    }
+}
+
+/// Waits for `future`, giving up after `deadline`. Returns `None` on timeout.
+pub(crate) async fn with_deadline<T: Timer, F: Future>(
+    timer: &T,
+    deadline: Duration,
+    future: F,
+) -> Option<F::Output> {
+    let future = std::pin::pin!(future);
+    let sleep = std::pin::pin!(timer.sleep(deadline));
+    match futures_util::future::select(future, sleep).await {
+        futures_util::future::Either::Left((value, _)) => Some(value),
+        futures_util::future::Either::Right(_) => None,
+    }
+}
+
+/// Tracks the work in flight, to stop accepting new work and wait for the
+/// current one to complete on shutdown.
+#[cfg(feature = "sunxdcc")]
+#[derive(Debug, Default)]
+pub(crate) struct Drain {
+    closed: AtomicBool,
+    in_flight: AtomicUsize,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+#[cfg(feature = "sunxdcc")]
+impl Drain {
+    /// Registers some work, returns `None` once closed.
+    pub(crate) fn enter(&self) -> Option<DrainGuard<'_>> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = DrainGuard(self);
+        (!self.closed.load(Ordering::SeqCst)).then_some(guard)
+    }
+
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    pub(crate) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Completes once no work is in flight.
+    pub(crate) fn drained(&self) -> impl Future<Output = ()> + Send + '_ {
+        std::future::poll_fn(|cx| {
+            let mut wakers = self.wakers.lock().unwrap();
+            if self.in_flight.load(Ordering::SeqCst) == 0 {
+                return Poll::Ready(());
+            }
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+    }
+}
+
+/// The work registered with [`Drain::enter`], completed when dropped.
+#[cfg(feature = "sunxdcc")]
+pub(crate) struct DrainGuard<'a>(&'a Drain);
+
+#[cfg(feature = "sunxdcc")]
+impl Drop for DrainGuard<'_> {
+    fn drop(&mut self) {
+        // the lock prevents a waker being registered between the check and the wake up
+        let mut wakers = self.0.wakers.lock().unwrap();
+        if self.0.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            wakers.drain(..).for_each(Waker::wake);
+        }
+    }
+}
+
+/// A notification sent once, waited by any number of tasks, e.g. to dispatch
+/// concurrent requests one after the other.
+#[cfg(feature = "sunxdcc")]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Signal(std::sync::Arc<Mutex<SignalState>>);
+
+#[cfg(feature = "sunxdcc")]
+#[derive(Debug, Default)]
+struct SignalState {
+    notified: bool,
+    wakers: Vec<Waker>,
+}
+
+#[cfg(feature = "sunxdcc")]
+impl Signal {
+    pub(crate) fn notify(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.notified = true;
+        state.wakers.drain(..).for_each(Waker::wake);
+    }
+
+    /// Completes once [`Signal::notify`] was called.
+    pub(crate) fn notified(&self) -> impl Future<Output = ()> + Send + '_ {
+        std::future::poll_fn(|cx| {
+            let mut state = self.0.lock().unwrap();
+            if state.notified {
+                return Poll::Ready(());
+            }
+            if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                state.wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+    }
+}
+
+/// The default range of the random delay waited between two pages of a crawl.
+pub(crate) const DEFAULT_PAGE_DELAY: RangeInclusive<Duration> =
+    Duration::from_millis(500)..=Duration::from_millis(1500);
+
+/// Picks a random duration in the given range, with a millisecond precision.
+pub(crate) fn jitter(range: &RangeInclusive<Duration>) -> Duration {
+    let min = range.start().as_millis() as u64;
+    let max = range.end().as_millis() as u64;
+    if min >= max {
+        return Duration::from_millis(min);
+    }
+    Duration::from_millis(fastrand::u64(min..=max))
+}
+
+/// Polls a future once, without any executor, expecting it to be ready.
+#[cfg(test)]
+pub(crate) fn now_or_never<F: Future>(future: F) -> F::Output {
+    use std::task::{Context, Poll, Waker};
+
+    let future = std::pin::pin!(future);
+    match future.poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(value) => value,
+        Poll::Pending => panic!("future should complete without any executor"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[cfg(feature = "sunxdcc")]
+    #[test]
+    fn should_drain_in_flight_work() {
+        let drain = super::Drain::default();
+        let guard = drain.enter().unwrap();
+        drain.close();
+        assert!(drain.enter().is_none());
+        let mut drained = std::pin::pin!(drain.drained());
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        assert!(drained.as_mut().poll(&mut cx).is_pending());
+        drop(guard);
+        assert!(drained.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[cfg(feature = "sunxdcc")]
+    #[test]
+    fn should_wait_for_signal() {
+        let signal = super::Signal::default();
+        let mut notified = std::pin::pin!(signal.notified());
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        assert!(notified.as_mut().poll(&mut cx).is_pending());
+        signal.clone().notify();
+        assert!(notified.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test_case::test_case(0, 0; "empty")]
+    #[test_case::test_case(500, 1500; "regular")]
+    #[test_case::test_case(1500, 500; "reversed")]
+    fn should_jitter_within_range(min: u64, max: u64) {
+        let range = Duration::from_millis(min)..=Duration::from_millis(max);
+        for _ in 0..100 {
+            let value = super::jitter(&range).as_millis() as u64;
+            assert!(value >= min.min(max) && value <= max.max(min));
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    mod tasks {
+        use crate::runtime::{TaskExit, TaskFailure, TaskSet};
+
+        #[tokio::test]
+        async fn should_report_task_exits() {
+            let mut tasks = TaskSet::new();
+            tasks.spawn("complete", async {});
+            tasks.spawn("panic", async { panic!("boom") });
+            let mut exits = Vec::new();
+            while let Some(exit) = tasks.join_next().await {
+                exits.push(exit);
+            }
+            exits.sort_by(|a, b| a.name.cmp(&b.name));
+            assert_eq!(
+                exits,
+                vec![
+                    TaskExit {
+                        name: "complete".into(),
+                        failure: None,
+                    },
+                    TaskExit {
+                        name: "panic".into(),
+                        failure: Some(TaskFailure::Panicked(Some("boom".into()))),
+                    },
+                ]
+            );
+            assert!(tasks.is_empty());
+        }
+
+        #[tokio::test]
+        async fn should_abort_tasks_on_shutdown() {
+            let mut tasks = TaskSet::new();
+            tasks.spawn("pending", std::future::pending());
+            assert!(tasks.reap().is_empty());
+            assert_eq!(tasks.len(), 1);
+            let exits = tasks.shutdown().await;
+            assert_eq!(exits[0].failure, Some(TaskFailure::Cancelled));
+            assert!(tasks.is_empty());
+        }
+    }
+}
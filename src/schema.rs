@@ -0,0 +1,124 @@
+//! Versioning of the serialized [`Entry`](crate::entry::Entry) and
+//! [`SearchHit`](crate::entry::SearchHit).
+//!
+//! The data stored for a long time (exports, caches...) should be wrapped in a
+//! [`Versioned`] envelope, recording the [`SCHEMA_VERSION`] it was written with.
+//! When the structures evolve, the older data is migrated when read back.
+//!
+//! ```
+//! use xdcc_search::schema::Versioned;
+//!
+//! let stored = serde_json::to_value(Versioned::new(vec!["not an entry"])).unwrap();
+//! let read: Vec<String> = Versioned::from_value(stored).unwrap();
+//! assert_eq!(read, ["not an entry"]);
+//! ```
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// The current version of the serialized format.
+///
+/// * `0`: the data written before the versioning, without envelope.
+/// * `1`: same fields, `first_seen` and `extras` being optional.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The migrations, the one at index `n` converting the version `n` to `n + 1`.
+const MIGRATIONS: &[fn(Value) -> Value] = &[
+    // the version 1 only introduced the envelope
+    |value| value,
+];
+
+/// The error returned when some data can't be read back.
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    /// The data was written by a newer version of the crate.
+    #[error("unsupported schema version {0}, the latest known is {SCHEMA_VERSION}")]
+    UnsupportedVersion(u32),
+    /// The data doesn't match the expected structure.
+    #[error("invalid data")]
+    Invalid(#[from] serde_json::Error),
+}
+
+/// Data wrapped with the version of its format.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Versioned<T> {
+    /// The [`SCHEMA_VERSION`] the data was written with.
+    pub version: u32,
+    /// The data.
+    pub data: T,
+}
+
+impl<T> Versioned<T> {
+    /// Wraps the data with the current [`SCHEMA_VERSION`].
+    pub fn new(data: T) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            data,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Versioned<T> {
+    /// Reads back some data, migrating it from the version it was written with.
+    ///
+    /// A value without envelope is considered written before the versioning (version `0`).
+    pub fn from_value(value: Value) -> Result<T, SchemaError> {
+        let (version, data) = match value {
+            Value::Object(mut object) if object.len() == 2 && object.contains_key("data") => {
+                match object.get("version").and_then(Value::as_u64) {
+                    Some(version) => {
+                        let version = u32::try_from(version).unwrap_or(u32::MAX);
+                        (version, object.remove("data").unwrap_or_default())
+                    }
+                    None => (0, Value::Object(object)),
+                }
+            }
+            other => (0, other),
+        };
+        Ok(serde_json::from_value(migrate(data, version)?)?)
+    }
+}
+
+/// Migrates some serialized data (an entry, a hit, or any structure containing
+/// them) from the given version to the current [`SCHEMA_VERSION`].
+pub fn migrate(value: Value, from_version: u32) -> Result<Value, SchemaError> {
+    if from_version > SCHEMA_VERSION {
+        return Err(SchemaError::UnsupportedVersion(from_version));
+    }
+    Ok(MIGRATIONS[from_version as usize..]
+        .iter()
+        .fold(value, |value, migration| migration(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::Entry;
+
+    const UNVERSIONED_ENTRY: &str = r##"{"filename":"file.iso","filesize":1024,"downloads":1,"packnum":1,"channel":"#chan","network":"abjects","bot_name":"bot","bot_speed":12}"##;
+
+    #[test]
+    fn should_read_unversioned_entry() {
+        let value = serde_json::from_str(UNVERSIONED_ENTRY).unwrap();
+        let entry: Entry = Versioned::from_value(value).unwrap();
+        assert_eq!(entry.filename, "file.iso");
+    }
+
+    #[test]
+    fn should_roundtrip_versioned_entries() {
+        let entry: Entry = serde_json::from_str(UNVERSIONED_ENTRY).unwrap();
+        let value = serde_json::to_value(Versioned::new(vec![entry.clone()])).unwrap();
+        assert_eq!(value["version"], SCHEMA_VERSION);
+        let read: Vec<Entry> = Versioned::from_value(value).unwrap();
+        assert_eq!(read, [entry]);
+    }
+
+    #[test]
+    fn should_reject_newer_version() {
+        let value = serde_json::json!({"version": SCHEMA_VERSION + 1, "data": []});
+        let error = Versioned::<Vec<Entry>>::from_value(value).unwrap_err();
+        assert!(
+            matches!(error, SchemaError::UnsupportedVersion(version) if version == SCHEMA_VERSION + 1)
+        );
+    }
+}
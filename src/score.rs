@@ -0,0 +1,337 @@
+//! Reliability scoring of the bots, built from observations collected over time.
+//!
+//! [`BotScores`] accumulates what happened with each bot: finished and failed
+//! downloads, measured speed compared to the advertised one, and whether the bot
+//! was still listed when looked for. It implements `serde` traits so it can be
+//! persisted between runs, and is used to pick the most reliable source of a
+//! file offered by several bots.
+//!
+//! A bot failing [`DEAD_AFTER`] downloads in a row is marked dead, and isn't
+//! picked anymore until it's [revived](BotScores::revive) or a download succeeds.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::entry::Entry;
+use crate::name::fold_case;
+
+/// The number of consecutive failed downloads after which a bot is marked dead.
+pub const DEAD_AFTER: u64 = 5;
+
+/// The observations collected for a single bot.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct BotRecord {
+    /// Number of downloads that completed.
+    pub downloads_succeeded: u64,
+    /// Number of downloads that failed or were rejected.
+    pub downloads_failed: u64,
+    /// Sum of the speeds advertised in the listings for the measured downloads, in bytes per second.
+    pub advertised_speed: u64,
+    /// Sum of the speeds measured during the downloads, in bytes per second.
+    pub measured_speed: u64,
+    /// Number of times the bot was found in a listing.
+    pub listed: u64,
+    /// Number of times the bot was expected in a listing but missing.
+    pub unlisted: u64,
+    /// Number of downloads that failed since the last one that completed.
+    #[serde(default)]
+    pub consecutive_failures: u64,
+    /// `true` once the bot failed [`DEAD_AFTER`] downloads in a row.
+    #[serde(default)]
+    pub dead: bool,
+}
+
+/// The reliability of a bot, each component being between `0` and `1`.
+///
+/// Components without observation default to `0.5`, so unknown bots rank below
+/// the good ones but above the ones known to be bad.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BotScore {
+    /// Estimated probability for a download to succeed.
+    pub success_rate: f64,
+    /// Ratio between the measured and advertised speeds, capped to `1`.
+    pub speed_ratio: f64,
+    /// Estimated probability for the bot to still be listed.
+    pub availability: f64,
+}
+
+impl BotScore {
+    /// Combines the components in a single value, favoring the success rate.
+    pub fn value(&self) -> f64 {
+        self.success_rate * 0.5 + self.speed_ratio * 0.25 + self.availability * 0.25
+    }
+}
+
+impl From<&BotRecord> for BotScore {
+    fn from(record: &BotRecord) -> Self {
+        // Laplace smoothing, to avoid trusting a single observation too much
+        let rate = |good: u64, bad: u64| (good as f64 + 1.0) / ((good + bad) as f64 + 2.0);
+        let speed_ratio = if record.advertised_speed == 0 {
+            0.5
+        } else {
+            (record.measured_speed as f64 / record.advertised_speed as f64).min(1.0)
+        };
+        Self {
+            success_rate: rate(record.downloads_succeeded, record.downloads_failed),
+            speed_ratio,
+            availability: rate(record.listed, record.unlisted),
+        }
+    }
+}
+
+/// The observations for all the bots, grouped by network then bot name.
+///
+/// The names are case-folded, a bot being the same whatever the casing used
+/// by the listing.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct BotScores(HashMap<String, HashMap<String, BotRecord>>);
+
+impl BotScores {
+    fn record_mut(&mut self, network: &str, bot_name: &str) -> &mut BotRecord {
+        self.0
+            .entry(fold_case(network))
+            .or_default()
+            .entry(fold_case(bot_name))
+            .or_default()
+    }
+
+    /// Returns the observations collected for a bot, if any.
+    pub fn record(&self, network: &str, bot_name: &str) -> Option<&BotRecord> {
+        self.0
+            .get(&fold_case(network))
+            .and_then(|bots| bots.get(&fold_case(bot_name)))
+    }
+
+    /// Records the outcome of a download of one of the bot packs.
+    pub fn record_download(&mut self, network: &str, bot_name: &str, success: bool) {
+        let record = self.record_mut(network, bot_name);
+        if success {
+            record.downloads_succeeded += 1;
+            record.consecutive_failures = 0;
+            record.dead = false;
+        } else {
+            record.downloads_failed += 1;
+            record.consecutive_failures += 1;
+            if record.consecutive_failures >= DEAD_AFTER && !record.dead {
+                crate::telemetry::log!(info, "marking {bot_name:?} on {network:?} as dead");
+                record.dead = true;
+            }
+        }
+    }
+
+    /// Returns `true` if the bot was marked dead, see [`DEAD_AFTER`].
+    pub fn is_dead(&self, network: &str, bot_name: &str) -> bool {
+        self.record(network, bot_name)
+            .is_some_and(|record| record.dead)
+    }
+
+    /// Returns `true` if the bot offering the entry isn't marked dead, e.g. to
+    /// filter the results with [`Filter`](crate::process::Filter).
+    pub fn is_alive(&self, entry: &Entry) -> bool {
+        !self.is_dead(entry.network.as_str(), entry.bot_name.as_str())
+    }
+
+    /// Returns the network and name of the bots marked dead, case-folded.
+    pub fn dead_bots(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().flat_map(|(network, bots)| {
+            bots.iter()
+                .filter(|(_, record)| record.dead)
+                .map(move |(bot_name, _)| (network.as_str(), bot_name.as_str()))
+        })
+    }
+
+    /// Removes the dead mark of a bot, resetting its count of consecutive failures,
+    /// returns `false` if it wasn't marked dead.
+    pub fn revive(&mut self, network: &str, bot_name: &str) -> bool {
+        match self
+            .0
+            .get_mut(&fold_case(network))
+            .and_then(|bots| bots.get_mut(&fold_case(bot_name)))
+        {
+            Some(record) if record.dead => {
+                record.dead = false;
+                record.consecutive_failures = 0;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records the speed measured while downloading from the bot, compared to the advertised one.
+    pub fn record_speed(&mut self, network: &str, bot_name: &str, advertised: u64, measured: u64) {
+        let record = self.record_mut(network, bot_name);
+        record.advertised_speed = record.advertised_speed.saturating_add(advertised);
+        record.measured_speed = record.measured_speed.saturating_add(measured);
+    }
+
+    /// Records the throughput of a completed download of the entry, compared to the
+    /// speed advertised in the listing, see [`BotScores::record_speed`].
+    pub fn record_transfer(&mut self, entry: &Entry, bytes: u64, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        if seconds <= 0.0 {
+            return;
+        }
+        let measured = (bytes as f64 / seconds) as u64;
+        self.record_speed(
+            entry.network.as_str(),
+            entry.bot_name.as_str(),
+            entry.bot_speed,
+            measured,
+        );
+    }
+
+    /// Returns the speed to expect when downloading the entry: the advertised speed,
+    /// corrected by the ratio between the speeds measured and advertised by the bot
+    /// for the previous downloads.
+    pub fn expected_speed(&self, entry: &Entry) -> u64 {
+        match self.record(entry.network.as_str(), entry.bot_name.as_str()) {
+            Some(record) if record.advertised_speed > 0 => {
+                let ratio = record.measured_speed as f64 / record.advertised_speed as f64;
+                (entry.bot_speed as f64 * ratio) as u64
+            }
+            _ => entry.bot_speed,
+        }
+    }
+
+    /// Records whether the bot was present when it was looked for in a listing.
+    pub fn record_listing(&mut self, network: &str, bot_name: &str, present: bool) {
+        let record = self.record_mut(network, bot_name);
+        if present {
+            record.listed += 1;
+        } else {
+            record.unlisted += 1;
+        }
+    }
+
+    /// Returns the score of a bot, a bot without observation getting the default score.
+    pub fn score(&self, network: &str, bot_name: &str) -> BotScore {
+        let default = BotRecord::default();
+        BotScore::from(self.record(network, bot_name).unwrap_or(&default))
+    }
+
+    /// Returns the entry offered by the most reliable bot, the first one winning ties.
+    ///
+    /// The entries offered by dead bots are never returned.
+    pub fn best<'a, I>(&self, entries: I) -> Option<&'a Entry>
+    where
+        I: IntoIterator<Item = &'a Entry>,
+    {
+        entries
+            .into_iter()
+            .filter(|entry| self.is_alive(entry))
+            .map(|entry| {
+                let score = self.score(entry.network.as_str(), entry.bot_name.as_str());
+                (score.value(), entry)
+            })
+            .fold(
+                None,
+                |best: Option<(f64, &Entry)>, (value, entry)| match best {
+                    Some((best_value, _)) if best_value >= value => best,
+                    _ => Some((value, entry)),
+                },
+            )
+            .map(|(_, entry)| entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(bot_name: &str) -> Entry {
+        let mut entry = crate::process::tests::entry(1, 1024);
+        entry.bot_name = bot_name.parse().unwrap();
+        entry
+    }
+
+    #[test]
+    fn should_score_unknown_bot() {
+        let scores = BotScores::default();
+        let score = scores.score("net", "bot");
+        assert_eq!(score.success_rate, 0.5);
+        assert_eq!(score.speed_ratio, 0.5);
+        assert_eq!(score.availability, 0.5);
+    }
+
+    #[test]
+    fn should_prefer_reliable_bot() {
+        let mut scores = BotScores::default();
+        scores.record_download("net", "good", true);
+        scores.record_download("net", "good", true);
+        scores.record_speed("net", "good", 1000, 900);
+        scores.record_download("net", "bad", false);
+        scores.record_listing("net", "bad", false);
+
+        assert!(scores.score("net", "good").value() > scores.score("net", "unknown").value());
+        assert!(scores.score("net", "unknown").value() > scores.score("net", "bad").value());
+
+        let entries = [entry("bad"), entry("unknown"), entry("good")];
+        assert_eq!(scores.best(&entries).unwrap().bot_name, "good");
+    }
+
+    #[test]
+    fn should_mark_dead_bots() {
+        let mut scores = BotScores::default();
+        for _ in 0..DEAD_AFTER - 1 {
+            scores.record_download("net", "flaky", false);
+            scores.record_download("net", "dead", false);
+        }
+        scores.record_download("net", "flaky", true);
+        scores.record_download("net", "flaky", false);
+        scores.record_download("net", "dead", false);
+        assert!(!scores.is_dead("net", "flaky"));
+        assert!(scores.is_dead("net", "dead"));
+        assert_eq!(scores.dead_bots().collect::<Vec<_>>(), [("net", "dead")]);
+
+        let entries = [entry("dead")];
+        assert_eq!(scores.best(&entries), None);
+        assert!(!scores.is_alive(&entries[0]));
+
+        assert!(scores.revive("net", "dead"));
+        assert!(!scores.revive("net", "dead"));
+        assert_eq!(scores.best(&entries), Some(&entries[0]));
+        assert_eq!(
+            scores.record("net", "dead").unwrap().consecutive_failures,
+            0
+        );
+    }
+
+    #[test]
+    fn should_ignore_names_casing() {
+        let mut scores = BotScores::default();
+        for _ in 0..DEAD_AFTER {
+            scores.record_download("Abjects", "Bot[1]", false);
+        }
+        assert!(scores.is_dead("abjects", "bot{1}"));
+        let mut listed = entry("BOT[1]");
+        listed.network = "ABJECTS".parse().unwrap();
+        assert!(!scores.is_alive(&listed));
+        assert_eq!(
+            scores.dead_bots().collect::<Vec<_>>(),
+            [("abjects", "bot{1}")]
+        );
+        assert!(scores.revive("ABJECTS", "bot[1]"));
+    }
+
+    #[test]
+    fn should_read_records_without_dead_mark() {
+        let scores: BotScores = serde_json::from_str(
+            r#"{"net":{"bot":{"downloads_succeeded":1,"downloads_failed":0,"advertised_speed":0,"measured_speed":0,"listed":0,"unlisted":0}}}"#,
+        )
+        .unwrap();
+        assert!(!scores.is_dead("net", "bot"));
+    }
+
+    #[test]
+    fn should_expect_measured_speed() {
+        let mut scores = BotScores::default();
+        let mut advertised = entry("bot");
+        advertised.bot_speed = 1000;
+        assert_eq!(scores.expected_speed(&advertised), 1000);
+        scores.record_transfer(&advertised, 4000, Duration::from_secs(10));
+        assert_eq!(scores.expected_speed(&advertised), 400);
+        // another pack of the same bot
+        advertised.bot_speed = 2000;
+        assert_eq!(scores.expected_speed(&advertised), 800);
+    }
+}
@@ -0,0 +1,235 @@
+//! Stores remembering which entries have already been observed.
+//!
+//! When watching broad queries, the same entries come back on every poll. A
+//! [`SeenStore`] keeps track of the ones already reported, either exactly with a
+//! [`ExactSet`], or in a constant amount of memory with a [`BloomFilter`] which
+//! accepts a small, configurable, rate of false positives.
+//!
+//! Both stores can be persisted with `write_to` and restored with `read_from`.
+
+use std::collections::HashSet;
+use std::io::{BufRead, Read, Write};
+
+use crate::entry::Entry;
+
+/// A set of already observed entries.
+pub trait SeenStore {
+    /// Marks the entry as seen, returns `true` if it wasn't seen before.
+    fn insert(&mut self, entry: &Entry) -> bool;
+
+    /// Returns `true` if the entry was already seen.
+    fn contains(&self, entry: &Entry) -> bool;
+}
+
+/// A store keeping the identity of every observed entry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExactSet(HashSet<String>);
+
+impl ExactSet {
+    /// Returns the number of entries in the store.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no entry was stored.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Writes the store, one entry identity per line.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for key in self.0.iter() {
+            writeln!(writer, "{key}")?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a store written with [`ExactSet::write_to`].
+    pub fn read_from<R: BufRead>(reader: R) -> std::io::Result<Self> {
+        reader
+            .lines()
+            .filter(|line| !line.as_ref().is_ok_and(|value| value.is_empty()))
+            .collect::<std::io::Result<HashSet<_>>>()
+            .map(Self)
+    }
+}
+
+impl SeenStore for ExactSet {
+    fn insert(&mut self, entry: &Entry) -> bool {
+        self.0.insert(entry.id())
+    }
+
+    fn contains(&self, entry: &Entry) -> bool {
+        self.0.contains(&entry.id())
+    }
+}
+
+const BLOOM_MAGIC: &[u8; 4] = b"XSBF";
+const BLOOM_VERSION: u8 = 1;
+
+/// A bloom filter of observed entries.
+///
+/// The memory used only depends on the capacity and the false positive rate
+/// it has been created with: about 1.2MB for a million entries at 1%.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// Creates a filter sized to hold `capacity` entries with, at most, the given
+    /// rate of false positives (e.g. `0.01` for 1%).
+    pub fn with_capacity(capacity: usize, false_positive_rate: f64) -> Self {
+        let capacity = capacity.max(1) as f64;
+        let rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-capacity * rate.ln() / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let hashes = ((bits as f64 / capacity) * ln2).round().max(1.0) as u32;
+        Self {
+            bits: vec![0; bits.div_ceil(64) as usize],
+            hashes,
+        }
+    }
+
+    fn bit_count(&self) -> u64 {
+        self.bits.len() as u64 * 64
+    }
+
+    /// Returns the positions of the bits used by a key, with double hashing.
+    fn positions(&self, key: &str) -> impl Iterator<Item = u64> + use<> {
+        let first = fnv1a(FNV_OFFSET, key.as_bytes());
+        let second = fnv1a(FNV_OFFSET_ALT, key.as_bytes()) | 1;
+        let bit_count = self.bit_count();
+        (0..self.hashes as u64)
+            .map(move |index| first.wrapping_add(index.wrapping_mul(second)) % bit_count)
+    }
+
+    fn is_set(&self, position: u64) -> bool {
+        self.bits[(position / 64) as usize] & (1 << (position % 64)) != 0
+    }
+
+    /// Writes the filter in a compact binary format.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writer.write_all(BLOOM_MAGIC)?;
+        writer.write_all(&[BLOOM_VERSION])?;
+        writer.write_all(&self.hashes.to_le_bytes())?;
+        writer.write_all(&(self.bits.len() as u64).to_le_bytes())?;
+        for word in self.bits.iter() {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Reads a filter written with [`BloomFilter::write_to`].
+    pub fn read_from<R: Read>(mut reader: R) -> std::io::Result<Self> {
+        let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let mut header = [0u8; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != BLOOM_MAGIC || header[4] != BLOOM_VERSION {
+            return Err(invalid("not a bloom filter file"));
+        }
+        let mut buffer = [0u8; 4];
+        reader.read_exact(&mut buffer)?;
+        let hashes = u32::from_le_bytes(buffer);
+        let mut buffer = [0u8; 8];
+        reader.read_exact(&mut buffer)?;
+        let words = u64::from_le_bytes(buffer);
+        if hashes == 0 || words == 0 {
+            return Err(invalid("empty bloom filter"));
+        }
+        let bits = (0..words)
+            .map(|_| {
+                reader.read_exact(&mut buffer)?;
+                Ok(u64::from_le_bytes(buffer))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        Ok(Self { bits, hashes })
+    }
+}
+
+impl SeenStore for BloomFilter {
+    fn insert(&mut self, entry: &Entry) -> bool {
+        let mut inserted = false;
+        for position in self.positions(&entry.id()) {
+            let mask = 1 << (position % 64);
+            let word = &mut self.bits[(position / 64) as usize];
+            inserted |= *word & mask == 0;
+            *word |= mask;
+        }
+        inserted
+    }
+
+    fn contains(&self, entry: &Entry) -> bool {
+        self.positions(&entry.id())
+            .all(|position| self.is_set(position))
+    }
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_OFFSET_ALT: u64 = 0x84222325cbf29ce4;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A hash that is stable across platforms and releases, required for persistence.
+fn fnv1a(offset: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(offset, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(packnum: u64) -> Entry {
+        crate::process::tests::entry(packnum, 1024)
+    }
+
+    #[test]
+    fn should_remember_exactly() {
+        let mut store = ExactSet::default();
+        assert!(store.insert(&entry(1)));
+        assert!(!store.insert(&entry(1)));
+        assert!(store.contains(&entry(1)));
+        assert!(!store.contains(&entry(2)));
+
+        let mut buffer = Vec::new();
+        store.write_to(&mut buffer).unwrap();
+        let restored = ExactSet::read_from(buffer.as_slice()).unwrap();
+        assert_eq!(restored, store);
+    }
+
+    #[test]
+    fn should_remember_whatever_the_names_casing() {
+        let mut store = ExactSet::default();
+        store.insert(&entry(1));
+        let mut renamed = entry(1);
+        renamed.network = "NET".parse().unwrap();
+        renamed.bot_name = "Bot".parse().unwrap();
+        assert!(store.contains(&renamed));
+        assert!(!store.insert(&renamed));
+    }
+
+    #[test]
+    fn should_remember_with_bloom_filter() {
+        let mut store = BloomFilter::with_capacity(1000, 0.01);
+        for packnum in 0..1000 {
+            store.insert(&entry(packnum));
+        }
+        assert!((0..1000).all(|packnum| store.contains(&entry(packnum))));
+        let false_positives = (1000..11000)
+            .filter(|packnum| store.contains(&entry(*packnum)))
+            .count();
+        assert!(false_positives < 200, "{false_positives} false positives");
+
+        let mut buffer = Vec::new();
+        store.write_to(&mut buffer).unwrap();
+        let restored = BloomFilter::read_from(buffer.as_slice()).unwrap();
+        assert_eq!(restored, store);
+    }
+
+    #[test]
+    fn shouldnt_read_invalid_bloom_filter() {
+        assert!(BloomFilter::read_from(b"nope, not a filter".as_slice()).is_err());
+    }
+}
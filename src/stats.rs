@@ -0,0 +1,127 @@
+//! Cumulative counters collected by the engines.
+
+#[cfg(feature = "sunxdcc")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+#[cfg(feature = "sunxdcc")]
+use crate::telemetry;
+
+/// A snapshot of the counters of an engine, returned by `Engine::stats`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of requests sent to the provider.
+    pub requests: u64,
+    /// Number of body bytes received from the provider.
+    pub bytes_received: u64,
+    /// Number of pages served from the engine cache (e.g. `304 Not Modified`).
+    pub cache_hits: u64,
+    /// Number of requests that couldn't be executed by the backend.
+    pub transport_failures: u64,
+    /// Number of responses with a non successful status code.
+    pub status_failures: u64,
+    /// Number of responses asking to slow down, also counted in `status_failures`.
+    pub rate_limited: u64,
+    /// Number of responses with a body that couldn't be deserialized.
+    pub body_failures: u64,
+    /// Total time spent waiting for the backend.
+    pub total_latency: Duration,
+}
+
+impl Stats {
+    /// Returns the total number of failed searches.
+    pub fn failures(&self) -> u64 {
+        self.transport_failures + self.status_failures + self.body_failures
+    }
+
+    /// Returns the average time spent per request, if any request was sent.
+    pub fn average_latency(&self) -> Option<Duration> {
+        let requests = u32::try_from(self.requests).unwrap_or(u32::MAX);
+        (requests > 0).then(|| self.total_latency / requests)
+    }
+}
+
+/// The live counters, shared by all the clones of an engine, also reported as
+/// metrics, see [`crate::telemetry`].
+#[cfg(feature = "sunxdcc")]
+#[derive(Debug)]
+pub(crate) struct Counters {
+    provider: &'static str,
+    requests: AtomicU64,
+    bytes_received: AtomicU64,
+    cache_hits: AtomicU64,
+    transport_failures: AtomicU64,
+    status_failures: AtomicU64,
+    rate_limited: AtomicU64,
+    body_failures: AtomicU64,
+    latency_micros: AtomicU64,
+}
+
+#[cfg(feature = "sunxdcc")]
+impl Counters {
+    pub(crate) fn new(provider: &'static str) -> Self {
+        Self {
+            provider,
+            requests: Default::default(),
+            bytes_received: Default::default(),
+            cache_hits: Default::default(),
+            transport_failures: Default::default(),
+            status_failures: Default::default(),
+            rate_limited: Default::default(),
+            body_failures: Default::default(),
+            latency_micros: Default::default(),
+        }
+    }
+
+    pub(crate) fn record_request(&self, latency: Duration) {
+        telemetry::increment(telemetry::REQUESTS, self.provider, 1);
+        telemetry::record_duration(telemetry::REQUEST_DURATION, self.provider, latency);
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.latency_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_bytes(&self, size: usize) {
+        telemetry::increment(telemetry::BYTES_RECEIVED, self.provider, size as u64);
+        self.bytes_received
+            .fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_cache_hit(&self) {
+        telemetry::increment(telemetry::CACHE_HITS, self.provider, 1);
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_failure(&self, error: &crate::Error) {
+        let (counter, kind) = match error {
+            crate::Error::Request { source, .. } => return self.record_failure(source),
+            crate::Error::Transport(_) => (&self.transport_failures, "transport"),
+            crate::Error::Status(_) => (&self.status_failures, "status"),
+            crate::Error::Body(_) | crate::Error::BodyTooLarge { .. } => {
+                (&self.body_failures, "body")
+            }
+            crate::Error::Shutdown
+            | crate::Error::ForeignCursor(_)
+            | crate::Error::OfflineMiss { .. } => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        telemetry::increment_failures(self.provider, kind);
+        if error.is_rate_limited() {
+            self.rate_limited.fetch_add(1, Ordering::Relaxed);
+            telemetry::increment(telemetry::RATE_LIMITED, self.provider, 1);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> Stats {
+        Stats {
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            transport_failures: self.transport_failures.load(Ordering::Relaxed),
+            status_failures: self.status_failures.load(Ordering::Relaxed),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            body_failures: self.body_failures.load(Ordering::Relaxed),
+            total_latency: Duration::from_micros(self.latency_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Combinators on the streams of pages.
+//!
+//! The streams returned by the engines fetch the pages lazily, when polled. The
+//! combinators of [`PageStreamExt`] stop polling the underlying stream as soon as
+//! their condition can't be satisfied anymore, saving the requests of the
+//! following pages.
+
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::Stream;
+
+use crate::entry::Page;
+
+#[derive(Clone, Copy, Debug)]
+enum Rule {
+    Limit(usize),
+    SizeAbove(u64),
+}
+
+/// A stream stopping early, created by the methods of [`PageStreamExt`].
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Truncated<S> {
+    inner: Pin<Box<S>>,
+    rule: Rule,
+    done: bool,
+}
+
+impl<S> Truncated<S> {
+    fn new(inner: S, rule: Rule) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            rule,
+            done: matches!(rule, Rule::Limit(0)),
+        }
+    }
+}
+
+impl<S: Stream<Item = crate::Result<Page>>> Stream for Truncated<S> {
+    type Item = crate::Result<Page>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        let mut page = match self.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(page))) => page,
+            other => return other,
+        };
+        match &mut self.rule {
+            Rule::Limit(remaining) => {
+                page.entries.truncate(*remaining);
+                *remaining -= page.entries.len();
+                self.done = *remaining == 0;
+            }
+            Rule::SizeAbove(size) => {
+                if let Some(index) = page
+                    .entries
+                    .iter()
+                    .position(|entry| entry.filesize <= *size)
+                {
+                    page.entries.truncate(index);
+                    self.done = true;
+                }
+            }
+        }
+        if self.done && page.entries.is_empty() {
+            return Poll::Ready(None);
+        }
+        Poll::Ready(Some(Ok(page)))
+    }
+}
+
+/// Combinators on the streams of pages, e.g. [`Engine::search_stream`](crate::sunxdcc::Engine::search_stream).
+pub trait PageStreamExt: Stream<Item = crate::Result<Page>> + Sized {
+    /// Yields at most `count` entries, the last page being truncated. No page is
+    /// fetched once the limit is reached.
+    fn limit(self, count: usize) -> Truncated<Self> {
+        Truncated::new(self, Rule::Limit(count))
+    }
+
+    /// Yields the entries while they are bigger than `size` bytes, and stops at the
+    /// first smaller one, like [`Iterator::take_while`].
+    ///
+    /// Meant for streams sorted by decreasing size, where no bigger file can follow.
+    fn take_while_size_above(self, size: u64) -> Truncated<Self> {
+        Truncated::new(self, Rule::SizeAbove(size))
+    }
+}
+
+impl<S: Stream<Item = crate::Result<Page>>> PageStreamExt for S {}
+
+/// The progress of a crawl, given to its [`LookAhead`] policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CrawlProgress {
+    /// The number of pages received so far.
+    pub pages: usize,
+    /// The number of entries received, before the pipeline is applied.
+    pub received: usize,
+    /// The number of entries kept by the pipeline.
+    pub kept: usize,
+}
+
+/// Decides how many pages a stream fetches ahead of its consumer, see
+/// [`EngineBuilder::look_ahead`](crate::sunxdcc::EngineBuilder::look_ahead).
+///
+/// The pages fetched ahead are requested concurrently and yielded in order. Once
+/// the consumer stops polling, nothing is fetched anymore, and the pages fetched
+/// after the last one are discarded.
+pub trait LookAhead: std::fmt::Debug + Send + Sync {
+    /// Returns the number of pages to fetch ahead of the one being awaited.
+    fn pages_ahead(&self, progress: &CrawlProgress) -> usize;
+}
+
+/// Never fetches ahead, the pages are requested one after the other.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sequential;
+
+impl LookAhead for Sequential {
+    fn pages_ahead(&self, _progress: &CrawlProgress) -> usize {
+        0
+    }
+}
+
+/// Fetches pages ahead when the pipeline filters most of the entries out, the
+/// consumer otherwise waiting for several pages to get a single result.
+///
+/// The default policy of the engines: up to 2 pages ahead, when less than a
+/// quarter of the entries are kept.
+#[derive(Clone, Copy, Debug)]
+pub struct Adaptive {
+    max: usize,
+    kept_ratio: f64,
+}
+
+impl Default for Adaptive {
+    fn default() -> Self {
+        Self::new(2, 0.25)
+    }
+}
+
+impl Adaptive {
+    /// Fetches up to `max` pages ahead when the ratio of entries kept by the
+    /// pipeline is below `kept_ratio`, the fewer entries kept the more pages ahead.
+    pub fn new(max: usize, kept_ratio: f64) -> Self {
+        Self { max, kept_ratio }
+    }
+}
+
+impl LookAhead for Adaptive {
+    fn pages_ahead(&self, progress: &CrawlProgress) -> usize {
+        if progress.received == 0 {
+            return 0;
+        }
+        let ratio = progress.kept as f64 / progress.received as f64;
+        if ratio >= self.kept_ratio {
+            return 0;
+        }
+        let ahead = (1.0 - ratio / self.kept_ratio) * self.max as f64;
+        (ahead.ceil() as usize).min(self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::entry::{Entry, PageInfo};
+    use crate::runtime::now_or_never;
+
+    fn page(sizes: &[u64]) -> crate::Result<Page> {
+        let entries = sizes
+            .iter()
+            .map(|size| crate::process::tests::entry(1, *size))
+            .collect();
+        Ok(Page {
+            entries,
+            info: PageInfo {
+                page: 0,
+                per_page: 3,
+                is_last_guess: false,
+            },
+            timing: None,
+        })
+    }
+
+    /// A stream of the given pages, panicking if polled once they are exhausted.
+    fn pages(pages: Vec<crate::Result<Page>>) -> impl Stream<Item = crate::Result<Page>> {
+        futures_util::stream::iter(pages).chain(futures_util::stream::poll_fn(|_| {
+            panic!("no more pages should be fetched")
+        }))
+    }
+
+    fn sizes(pages: Vec<crate::Result<Page>>) -> Vec<Vec<u64>> {
+        pages
+            .into_iter()
+            .map(|page| {
+                page.unwrap()
+                    .entries
+                    .iter()
+                    .map(|entry: &Entry| entry.filesize)
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn should_limit_entries() {
+        let stream = pages(vec![page(&[1, 2, 3]), page(&[4, 5, 6])]).limit(5);
+        let result = now_or_never(stream.collect::<Vec<_>>());
+        assert_eq!(sizes(result), vec![vec![1, 2, 3], vec![4, 5]]);
+    }
+
+    #[test]
+    fn should_limit_on_page_boundary() {
+        let stream = pages(vec![page(&[1, 2, 3])]).limit(3);
+        let result = now_or_never(stream.collect::<Vec<_>>());
+        assert_eq!(sizes(result), vec![vec![1, 2, 3]]);
+        let stream = pages(Vec::new()).limit(0);
+        assert!(now_or_never(stream.collect::<Vec<_>>()).is_empty());
+    }
+
+    #[test]
+    fn should_take_while_size_above() {
+        let stream = pages(vec![page(&[30, 20, 15]), page(&[12, 10, 5])]).take_while_size_above(10);
+        let result = now_or_never(stream.collect::<Vec<_>>());
+        assert_eq!(sizes(result), vec![vec![30, 20, 15], vec![12]]);
+        let stream = pages(vec![page(&[30, 20, 15]), page(&[8, 5, 1])]).take_while_size_above(10);
+        let result = now_or_never(stream.collect::<Vec<_>>());
+        assert_eq!(sizes(result), vec![vec![30, 20, 15]]);
+    }
+
+    #[test_case::test_case(0, 0, 0; "nothing received")]
+    #[test_case::test_case(40, 20, 0; "most kept")]
+    #[test_case::test_case(40, 5, 1; "few kept")]
+    #[test_case::test_case(40, 0, 2; "none kept")]
+    fn should_adapt_look_ahead(received: usize, kept: usize, expected: usize) {
+        let progress = CrawlProgress {
+            pages: 1,
+            received,
+            kept,
+        };
+        assert_eq!(Adaptive::default().pages_ahead(&progress), expected);
+        assert_eq!(Sequential.pages_ahead(&progress), 0);
+    }
+}
@@ -0,0 +1,83 @@
+//! The names of the tracing spans and of the metrics emitted by the engines.
+//!
+//! Both integrations are optional: the logs and spans are emitted with
+//! [`tracing`](https://docs.rs/tracing) when the `tracing` feature is enabled
+//! (default), the counters and histograms with [`metrics`](https://docs.rs/metrics)
+//! when the `metrics` feature is enabled. When disabled, nothing is recorded and
+//! the dependency isn't compiled at all.
+//!
+//! The names below are part of the public API: they only change in a major
+//! release, so the dashboards and alerts built on them keep working.
+//!
+//! All the metrics carry the [`PROVIDER_LABEL`] label, the name of the provider.
+
+/// The span wrapping the request of a page to sunxdcc, with the `request_id`,
+/// `query` and `page` fields.
+pub const PAGE_SPAN: &str = "sunxdcc.page";
+
+/// The label holding the name of the provider, on all the metrics.
+pub const PROVIDER_LABEL: &str = "provider";
+/// The label holding the kind of failure (`transport`, `status` or `body`),
+/// on [`FAILURES`].
+pub const KIND_LABEL: &str = "kind";
+
+/// Counter of the requests sent to the providers.
+pub const REQUESTS: &str = "xdcc_search_requests_total";
+/// Histogram of the time spent waiting for the providers, in seconds.
+pub const REQUEST_DURATION: &str = "xdcc_search_request_duration_seconds";
+/// Counter of the body bytes received from the providers.
+pub const BYTES_RECEIVED: &str = "xdcc_search_bytes_received_total";
+/// Counter of the pages served from the engine cache.
+pub const CACHE_HITS: &str = "xdcc_search_cache_hits_total";
+/// Counter of the failed requests, by [`KIND_LABEL`].
+pub const FAILURES: &str = "xdcc_search_failures_total";
+/// Counter of the responses asking to slow down, also counted in [`FAILURES`].
+pub const RATE_LIMITED: &str = "xdcc_search_rate_limited_total";
+
+/// Logs with `tracing` at the given level, e.g. `log!(debug, "...")`, or only
+/// type checks the arguments when the `tracing` feature is disabled.
+macro_rules! log {
+    ($level:ident, $($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = format_args!($($arg)*);
+        }
+    }};
+}
+pub(crate) use log;
+
+/// Increments the counter `name` of the provider, when the `metrics` feature is enabled.
+#[cfg(feature = "sunxdcc")]
+#[inline]
+pub(crate) fn increment(name: &'static str, provider: &'static str, value: u64) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(name, PROVIDER_LABEL => provider).increment(value);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (name, provider, value);
+}
+
+/// Increments the [`FAILURES`] counter of the provider for the given kind.
+#[cfg(feature = "sunxdcc")]
+#[inline]
+pub(crate) fn increment_failures(provider: &'static str, kind: &'static str) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(FAILURES, PROVIDER_LABEL => provider, KIND_LABEL => kind).increment(1);
+    #[cfg(not(feature = "metrics"))]
+    let _ = (provider, kind);
+}
+
+/// Records a duration in the histogram `name` of the provider.
+#[cfg(feature = "sunxdcc")]
+#[inline]
+pub(crate) fn record_duration(
+    name: &'static str,
+    provider: &'static str,
+    duration: std::time::Duration,
+) {
+    #[cfg(feature = "metrics")]
+    metrics::histogram!(name, PROVIDER_LABEL => provider).record(duration.as_secs_f64());
+    #[cfg(not(feature = "metrics"))]
+    let _ = (name, provider, duration);
+}
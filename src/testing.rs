@@ -0,0 +1,440 @@
+//! A simulated provider, for the integration tests of the applications embedding
+//! this crate (behind the `testing` feature).
+//!
+//! [`SimulatedProvider`] serves `deliver.php` on a local port, answering each
+//! `(query, page)` with the configured [`Reply`], and an empty page otherwise.
+//!
+//! The replies can simulate a misbehaving provider (truncated JSON, columns of
+//! different lengths, a body sent byte per byte), and a rate of random server
+//! errors can be configured with [`SimulatedProviderBuilder::random_errors`], to
+//! check how the retry or circuit breaker settings behave.
+//!
+//! ```no_run
+//! # #[cfg(feature = "reqwest")]
+//! # async fn run() -> std::io::Result<()> {
+//! use xdcc_search::testing::{Reply, SimulatedProvider};
+//!
+//! let provider = SimulatedProvider::builder()
+//!     .page("ubuntu", 0, Reply::Status(503))
+//!     .start()
+//!     .await?;
+//! let backend = xdcc_search::http::ReqwestBackend::default();
+//! let engine = xdcc_search::sunxdcc::Engine::with_url(backend, provider.url().to_owned());
+//! assert!(engine.search("ubuntu", 0).await.is_err());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::entry::Entry;
+use crate::runtime::TaskSet;
+
+/// The maximum size of the head of a request.
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// What the simulated provider answers to a request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reply {
+    /// A page listing the entries.
+    Entries(Vec<Entry>),
+    /// A raw body, e.g. a fixture from `resources/`.
+    Body(String),
+    /// An empty response with the given status code.
+    Status(u16),
+    /// A page listing the entries, cut in the middle of the JSON document.
+    Truncated(Vec<Entry>),
+    /// A page listing the entries, with the last filename missing, so the
+    /// columns don't have the same length.
+    MismatchedColumns(Vec<Entry>),
+    /// The reply, with its body sent one byte at a time, waiting the given
+    /// delay before each byte.
+    SlowLoris(Box<Reply>, Duration),
+}
+
+impl Reply {
+    fn render(&self) -> (u16, String) {
+        match self {
+            Self::Entries(entries) => (200, crate::sunxdcc::encode_page(entries)),
+            Self::Body(body) => (200, body.clone()),
+            Self::Status(status) => (*status, String::new()),
+            Self::Truncated(entries) => {
+                let mut body = crate::sunxdcc::encode_page(entries);
+                body.truncate(body.len() / 2);
+                (200, body)
+            }
+            Self::MismatchedColumns(entries) => {
+                let mut page = serde_json::from_str::<serde_json::Value>(
+                    &crate::sunxdcc::encode_page(entries),
+                )
+                .unwrap_or_default();
+                if let Some(filenames) = page.get_mut("fname").and_then(|v| v.as_array_mut()) {
+                    filenames.pop();
+                }
+                (200, page.to_string())
+            }
+            Self::SlowLoris(reply, _) => reply.render(),
+        }
+    }
+
+    fn byte_delay(&self) -> Option<Duration> {
+        match self {
+            Self::SlowLoris(_, delay) => Some(*delay),
+            _ => None,
+        }
+    }
+}
+
+/// Random server errors, see [`SimulatedProviderBuilder::random_errors`].
+#[derive(Debug)]
+struct RandomErrors {
+    rate: f64,
+    statuses: Vec<u16>,
+    rng: fastrand::Rng,
+}
+
+impl RandomErrors {
+    fn pick(&mut self) -> Option<u16> {
+        if self.statuses.is_empty() || self.rng.f64() >= self.rate {
+            return None;
+        }
+        Some(self.statuses[self.rng.usize(..self.statuses.len())])
+    }
+}
+
+/// Builder of a [`SimulatedProvider`], created with [`SimulatedProvider::builder`].
+#[derive(Debug, Default)]
+pub struct SimulatedProviderBuilder {
+    pages: HashMap<(String, u8), Reply>,
+    latency: Duration,
+    errors: Option<RandomErrors>,
+}
+
+impl SimulatedProviderBuilder {
+    /// Sets the reply to a page of a search term, the terms being compared once
+    /// [normalized](crate::query::normalize).
+    pub fn page(mut self, query: &str, page: u8, reply: Reply) -> Self {
+        self.pages
+            .insert((crate::query::normalize(query), page), reply);
+        self
+    }
+
+    /// Sets the delay waited before answering each request.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Answers a proportion of the requests (between `0` and `1`) with one of the
+    /// statuses, picked at random, whatever the configured reply.
+    ///
+    /// The random generator is seeded, so a test fails the same way on every run.
+    pub fn random_errors(mut self, rate: f64, statuses: &[u16], seed: u64) -> Self {
+        self.errors = Some(RandomErrors {
+            rate,
+            statuses: statuses.to_vec(),
+            rng: fastrand::Rng::with_seed(seed),
+        });
+        self
+    }
+
+    /// Starts serving on a random local port, until the provider is dropped.
+    pub async fn start(self) -> std::io::Result<SimulatedProvider> {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(State {
+            pages: self.pages,
+            latency: self.latency,
+            errors: Mutex::new(self.errors),
+            requests: Mutex::default(),
+        });
+        let mut tasks = TaskSet::new();
+        tasks.spawn("simulated-provider", serve(listener, state.clone()));
+        Ok(SimulatedProvider {
+            url: format!("http://{address}/deliver.php"),
+            address,
+            state,
+            _tasks: tasks,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    pages: HashMap<(String, u8), Reply>,
+    latency: Duration,
+    errors: Mutex<Option<RandomErrors>>,
+    requests: Mutex<Vec<(String, u8)>>,
+}
+
+/// A provider served in-process, mimicking `deliver.php`.
+#[derive(Debug)]
+pub struct SimulatedProvider {
+    url: String,
+    address: SocketAddr,
+    state: Arc<State>,
+    /// The accept loop, aborted with its connections when the provider is dropped.
+    _tasks: TaskSet,
+}
+
+impl SimulatedProvider {
+    /// Creates a builder, replying with empty pages by default.
+    pub fn builder() -> SimulatedProviderBuilder {
+        SimulatedProviderBuilder::default()
+    }
+
+    /// Returns the address the provider listens on.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns the URL to configure the engine with, see [`EngineBuilder::url`](crate::sunxdcc::EngineBuilder::url).
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the `(query, page)` of the requests received so far, in order.
+    pub fn requests(&self) -> Vec<(String, u8)> {
+        self.state.requests.lock().unwrap().clone()
+    }
+}
+
+async fn serve(listener: TcpListener, state: Arc<State>) {
+    // owned by the accept loop, aborted with it
+    let mut connections = TaskSet::new();
+    while let Ok((stream, address)) = listener.accept().await {
+        connections.reap();
+        connections.spawn(
+            format!("simulated-provider-{address}"),
+            handle(stream, state.clone()),
+        );
+    }
+}
+
+async fn handle(mut stream: TcpStream, state: Arc<State>) {
+    let Some(target) = read_target(&mut stream).await else {
+        let _ = respond(&mut stream, 400, "", None).await;
+        return;
+    };
+    let (path, params) = target.split_once('?').unwrap_or((&target, ""));
+    if !path.ends_with("/deliver.php") {
+        let _ = respond(&mut stream, 404, "", None).await;
+        return;
+    }
+    let mut query = String::new();
+    let mut page = 0;
+    for (name, value) in params.split('&').filter_map(|pair| pair.split_once('=')) {
+        match name {
+            "sterm" => query = percent_decode(value),
+            "page" => page = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    state.requests.lock().unwrap().push((query.clone(), page));
+    let reply = state.pages.get(&(crate::query::normalize(&query), page));
+    let random_error = state
+        .errors
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(RandomErrors::pick);
+    let (status, body) = match (random_error, reply) {
+        (Some(status), _) => (status, String::new()),
+        (None, Some(reply)) => reply.render(),
+        (None, None) => (200, crate::sunxdcc::encode_page(&[])),
+    };
+    if !state.latency.is_zero() {
+        tokio::time::sleep(state.latency).await;
+    }
+    let byte_delay = reply
+        .and_then(Reply::byte_delay)
+        .filter(|_| random_error.is_none());
+    let _ = respond(&mut stream, status, &body, byte_delay).await;
+}
+
+/// Reads the head of the request, returns the target of its request line.
+async fn read_target(stream: &mut TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await.ok()?;
+        if read == 0 || head.len() + read > MAX_HEAD_SIZE {
+            return None;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    let head = String::from_utf8(head).ok()?;
+    let mut request_line = head.lines().next()?.split(' ');
+    match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some(target)) => Some(target.to_owned()),
+        _ => None,
+    }
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    body: &str,
+    byte_delay: Option<Duration>,
+) -> std::io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {status} Simulated\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    match byte_delay {
+        Some(delay) => {
+            for byte in body.as_bytes() {
+                tokio::time::sleep(delay).await;
+                stream.write_all(std::slice::from_ref(byte)).await?;
+                stream.flush().await?;
+            }
+        }
+        None => stream.write_all(body.as_bytes()).await?,
+    }
+    stream.shutdown().await
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 2;
+            }
+            (byte, _) => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case::test_case("ubuntu", "ubuntu"; "plain")]
+    #[test_case::test_case("ubuntu+24.04", "ubuntu 24.04"; "plus")]
+    #[test_case::test_case("tom%20%26%20jerry", "tom & jerry"; "encoded")]
+    #[test_case::test_case("100%", "100%"; "truncated")]
+    #[test_case::test_case("%zz", "%zz"; "invalid")]
+    fn should_percent_decode(input: &str, expected: &str) {
+        assert_eq!(percent_decode(input), expected);
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn should_serve_configured_pages() {
+        let entries = vec![
+            crate::process::tests::entry(1, 1503238553),
+            crate::process::tests::entry(2, 12),
+        ];
+        let provider = SimulatedProvider::builder()
+            .page("ubuntu 24.04", 0, Reply::Entries(entries.clone()))
+            .page("ubuntu 24.04", 1, Reply::Status(503))
+            .start()
+            .await
+            .unwrap();
+        let engine = crate::sunxdcc::Engine::with_url(
+            crate::http::ReqwestBackend::default(),
+            provider.url().to_owned(),
+        );
+        let found = engine.search("ubuntu 24.04", 0).await.unwrap();
+        assert_eq!(found, entries);
+        let error = engine.search("ubuntu 24.04", 1).await.unwrap_err();
+        assert_eq!(error.status(), Some(503));
+        assert!(engine.search("debian", 0).await.unwrap().is_empty());
+        assert_eq!(
+            provider.requests(),
+            [
+                ("ubuntu 24.04".to_owned(), 0),
+                ("ubuntu 24.04".to_owned(), 1),
+                ("debian".to_owned(), 0)
+            ]
+        );
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn should_simulate_malformed_pages() {
+        let entries = vec![
+            crate::process::tests::entry(1, 100),
+            crate::process::tests::entry(2, 200),
+        ];
+        let provider = SimulatedProvider::builder()
+            .page("ubuntu", 0, Reply::Truncated(entries.clone()))
+            .page("ubuntu", 1, Reply::MismatchedColumns(entries.clone()))
+            .start()
+            .await
+            .unwrap();
+        let engine = crate::sunxdcc::Engine::with_url(
+            crate::http::ReqwestBackend::default(),
+            provider.url().to_owned(),
+        );
+        let error = engine.search("ubuntu", 0).await.unwrap_err();
+        assert!(matches!(error.inner(), crate::Error::Body(_)));
+        assert!(!error.is_retryable());
+        let found = engine.search("ubuntu", 1).await.unwrap();
+        assert_eq!(found, entries[..1]);
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn should_simulate_slow_loris() {
+        let entries = vec![crate::process::tests::entry(1, 100)];
+        let reply = Reply::SlowLoris(Box::new(Reply::Entries(entries)), Duration::from_millis(50));
+        let provider = SimulatedProvider::builder()
+            .page("ubuntu", 0, reply)
+            .start()
+            .await
+            .unwrap();
+        let engine = crate::sunxdcc::Engine::builder(crate::http::ReqwestBackend::default())
+            .url(provider.url().to_owned())
+            .timeout(Duration::from_millis(200))
+            .build();
+        let error = engine.search("ubuntu", 0).await.unwrap_err();
+        assert!(matches!(error.inner(), crate::Error::Transport(_)));
+        assert!(error.is_retryable());
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[tokio::test]
+    async fn should_inject_random_errors() {
+        let provider = SimulatedProvider::builder()
+            .random_errors(0.5, &[500, 503], 42)
+            .start()
+            .await
+            .unwrap();
+        let engine = crate::sunxdcc::Engine::with_url(
+            crate::http::ReqwestBackend::default(),
+            provider.url().to_owned(),
+        );
+        let mut statuses = Vec::new();
+        for page in 0..20 {
+            statuses.push(
+                engine
+                    .search("ubuntu", page)
+                    .await
+                    .err()
+                    .and_then(|e| e.status()),
+            );
+        }
+        let failures = statuses.iter().flatten().collect::<Vec<_>>();
+        assert!(!failures.is_empty() && failures.len() < statuses.len());
+        assert!(failures.iter().all(|status| matches!(status, 500 | 503)));
+    }
+}
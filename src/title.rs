@@ -0,0 +1,156 @@
+//! Extraction of a human readable title from scene-style filenames.
+
+/// A clean title extracted from a filename, see [`Title::parse`].
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Title {
+    /// The title, with the words separated by single spaces.
+    pub name: String,
+    /// The release year, when present in the filename.
+    pub year: Option<u16>,
+}
+
+/// Words marking the end of the title in scene-style names.
+const STOP_WORDS: &[&str] = &[
+    "480p", "576p", "720p", "1080p", "1080i", "2160p", "4k", "uhd", "hdr", "bluray", "bdrip",
+    "brrip", "webrip", "web", "webdl", "hdtv", "dvdrip", "dvd", "remux", "x264", "x265", "h264",
+    "h265", "hevc", "avc", "xvid", "aac", "ac3", "dts", "multi", "vostfr", "vf", "vff", "proper",
+    "repack", "internal", "complete", "iso",
+];
+
+impl Title {
+    /// Converts a scene-style filename into a title and a year.
+    ///
+    /// The extension, bracketed tags, release group suffix, dots and underscores
+    /// are removed, and the title stops at the year, the episode number or the
+    /// first technical tag (resolution, source, codec...).
+    ///
+    /// ```
+    /// use xdcc_search::title::Title;
+    ///
+    /// let title = Title::parse("[Group] The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv");
+    /// assert_eq!(title.name, "The Matrix");
+    /// assert_eq!(title.year, Some(1999));
+    /// ```
+    pub fn parse(filename: &str) -> Self {
+        let mut year = None;
+        let stem = strip_extension(filename.trim());
+        let stem = strip_brackets(stem, &mut year);
+        let stem = stem.replace(['.', '_'], " ");
+        let stem = strip_group(&stem);
+
+        let mut words = Vec::new();
+        for (index, word) in stem.split_whitespace().enumerate() {
+            if let Some(value) = parse_year(word).filter(|_| index > 0) {
+                year.get_or_insert(value);
+                break;
+            }
+            let lower = word.to_ascii_lowercase().replace('-', "");
+            if STOP_WORDS.contains(&lower.as_str()) || is_episode(&lower) {
+                break;
+            }
+            // anime style episode numbers: "Title - 01"
+            if words.last() == Some(&"-") && word.bytes().all(|c| c.is_ascii_digit()) {
+                break;
+            }
+            words.push(word);
+        }
+        let name = words
+            .join(" ")
+            .trim_matches(|c: char| c == '-' || c.is_whitespace())
+            .to_owned();
+        Self { name, year }
+    }
+}
+
+fn strip_extension(value: &str) -> &str {
+    match value.rsplit_once('.') {
+        Some((stem, ext))
+            if (2..=4).contains(&ext.len())
+                && ext.chars().all(|c| c.is_ascii_alphanumeric())
+                && ext.chars().any(|c| c.is_ascii_alphabetic()) =>
+        {
+            stem
+        }
+        _ => value,
+    }
+}
+
+/// Removes the `[...]`, `(...)` and `{...}` groups, keeping the year if one is found.
+fn strip_brackets(value: &str, year: &mut Option<u16>) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut current: Option<(char, String)> = None;
+    for c in value.chars() {
+        match (&mut current, c) {
+            (None, '[') => current = Some((']', String::new())),
+            (None, '(') => current = Some((')', String::new())),
+            (None, '{') => current = Some(('}', String::new())),
+            (None, c) => result.push(c),
+            (Some((end, content)), c) if *end == c => {
+                if let Some(value) = parse_year(content.trim()) {
+                    year.get_or_insert(value);
+                }
+                result.push(' ');
+                current = None;
+            }
+            (Some((_, content)), c) => content.push(c),
+        }
+    }
+    result
+}
+
+/// Removes a trailing `-GROUP` suffix.
+fn strip_group(value: &str) -> &str {
+    let value = value.trim_end();
+    match value.rsplit_once('-') {
+        Some((stem, group))
+            if !group.is_empty()
+                && !stem.ends_with(' ')
+                && group.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            stem
+        }
+        _ => value,
+    }
+}
+
+fn parse_year(word: &str) -> Option<u16> {
+    if word.len() != 4 {
+        return None;
+    }
+    word.parse::<u16>()
+        .ok()
+        .filter(|value| (1900..2100).contains(value))
+}
+
+/// Matches `s01e02`, `s01`, `1x02` and `e02`, expecting a lowercase word.
+fn is_episode(word: &str) -> bool {
+    let digits = |value: &str| !value.is_empty() && value.bytes().all(|c| c.is_ascii_digit());
+    if let Some(rest) = word.strip_prefix('s') {
+        return match rest.split_once('e') {
+            Some((season, episode)) => digits(season) && digits(episode),
+            None => digits(rest),
+        };
+    }
+    if let Some(rest) = word.strip_prefix('e') {
+        return digits(rest);
+    }
+    word.split_once('x')
+        .is_some_and(|(season, episode)| season.len() <= 2 && digits(season) && digits(episode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Title;
+
+    #[test_case::test_case("The.Matrix.1999.1080p.BluRay.x264-GROUP.mkv", "The Matrix", Some(1999); "movie")]
+    #[test_case::test_case("[SubGroup] Cowboy Bebop - 01 [1080p].mkv", "Cowboy Bebop", None; "anime")]
+    #[test_case::test_case("Some_Show_S02E05_720p_HDTV.avi", "Some Show", None; "show")]
+    #[test_case::test_case("Blade Runner (1982) [Final Cut].mp4", "Blade Runner", Some(1982); "year in brackets")]
+    #[test_case::test_case("2001.A.Space.Odyssey.1968.iso", "2001 A Space Odyssey", Some(1968); "year as title")]
+    #[test_case::test_case("Show.Name.S01E01.WEB-DL-GRP.mkv", "Show Name", None; "dashed tag")]
+    fn should_parse(input: &str, name: &str, year: Option<u16>) {
+        let title = Title::parse(input);
+        assert_eq!(title.name, name);
+        assert_eq!(title.year, year);
+    }
+}
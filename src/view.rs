@@ -0,0 +1,255 @@
+//! View models of the entries, with the display strings computed once (behind the
+//! `view` feature).
+//!
+//! [`EntryView`] gives the terminal, graphical or web interfaces, whatever their
+//! language, the formatted size and speed, a shortened filename, a hint to pick an
+//! icon and the relative age, instead of each one reimplementing the formatting.
+//! It implements `serde` traits to be sent as is to another process.
+
+use std::time::{Duration, SystemTime};
+
+use crate::entry::Entry;
+use crate::format::{ByteSize, Format, Speed};
+
+/// The kind of file, guessed from the extension, to pick an icon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Category {
+    /// A movie or an episode.
+    Video,
+    /// A music track or an audiobook.
+    Audio,
+    /// A compressed archive.
+    Archive,
+    /// A disk image, e.g. an operating system installer.
+    DiskImage,
+    /// A book, a comic or a text file.
+    Document,
+    /// A subtitle track.
+    Subtitle,
+    /// Anything else.
+    Other,
+}
+
+/// The extensions of each category.
+const CATEGORIES: &[(Category, &[&str])] = &[
+    (
+        Category::Video,
+        &[
+            "avi", "m2ts", "m4v", "mkv", "mov", "mp4", "mpg", "ts", "webm", "wmv",
+        ],
+    ),
+    (
+        Category::Audio,
+        &["aac", "flac", "m4a", "m4b", "mp3", "ogg", "opus", "wav"],
+    ),
+    (
+        Category::Archive,
+        &["7z", "bz2", "gz", "rar", "tar", "xz", "zip"],
+    ),
+    (Category::DiskImage, &["bin", "dmg", "img", "iso"]),
+    (
+        Category::Document,
+        &["azw3", "cbr", "cbz", "epub", "mobi", "nfo", "pdf", "txt"],
+    ),
+    (Category::Subtitle, &["ass", "srt", "ssa", "sub", "vtt"]),
+];
+
+impl Category {
+    /// Guesses the category from the extension of the filename.
+    pub fn from_filename(filename: &str) -> Self {
+        let Some((_, extension)) = filename.rsplit_once('.') else {
+            return Self::Other;
+        };
+        CATEGORIES
+            .iter()
+            .find(|(_, extensions)| {
+                extensions
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(extension))
+            })
+            .map_or(Self::Other, |(category, _)| *category)
+    }
+
+    /// Returns the name of the category, usable as an icon identifier (e.g. `disk-image`).
+    pub fn icon(self) -> &'static str {
+        match self {
+            Self::Video => "video",
+            Self::Audio => "audio",
+            Self::Archive => "archive",
+            Self::DiskImage => "disk-image",
+            Self::Document => "document",
+            Self::Subtitle => "subtitle",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Options used to build an [`EntryView`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ViewOptions {
+    format: Format,
+    max_filename_len: usize,
+}
+
+impl Default for ViewOptions {
+    fn default() -> Self {
+        Self {
+            format: Format::default(),
+            max_filename_len: 60,
+        }
+    }
+}
+
+impl ViewOptions {
+    /// Sets how the sizes and speeds are formatted, see [`Format`].
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the maximum number of characters of the filename, 60 by default.
+    ///
+    /// Longer filenames are shortened in the middle, keeping the extension.
+    pub fn max_filename_len(mut self, len: usize) -> Self {
+        self.max_filename_len = len;
+        self
+    }
+}
+
+/// An entry with its display strings, see [`EntryView::new`].
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct EntryView {
+    /// The identifier of the entry, see [`Entry::id`].
+    pub id: String,
+    /// The filename, shortened to the configured length.
+    pub filename: String,
+    /// The size of the file, e.g. `1.4 GiB`.
+    pub size: String,
+    /// The upload speed of the bot, e.g. `114.0 KiB/s`.
+    pub speed: String,
+    /// The kind of file, to pick an icon.
+    pub category: Category,
+    /// For how long the pack has been listed, e.g. `3 days ago`, when known.
+    pub age: Option<String>,
+    /// Where to find the pack, e.g. `bot@network #channel`.
+    pub source: String,
+}
+
+impl EntryView {
+    /// Builds the view of the entry, the age being relative to now.
+    pub fn new(entry: &Entry, options: &ViewOptions) -> Self {
+        Self::at(entry, options, SystemTime::now())
+    }
+
+    /// Builds the view of the entry, the age being relative to the given time.
+    pub fn at(entry: &Entry, options: &ViewOptions, now: SystemTime) -> Self {
+        Self {
+            id: entry.id(),
+            filename: shorten(&entry.filename, options.max_filename_len),
+            size: ByteSize(entry.filesize).display(options.format).to_string(),
+            speed: Speed(entry.bot_speed).display(options.format).to_string(),
+            category: Category::from_filename(&entry.filename),
+            age: entry.age_at(now).map(relative_age),
+            source: format!("{}@{} {}", entry.bot_name, entry.network, entry.channel),
+        }
+    }
+}
+
+impl From<&Entry> for EntryView {
+    fn from(entry: &Entry) -> Self {
+        Self::new(entry, &ViewOptions::default())
+    }
+}
+
+/// Shortens the filename in the middle, keeping the extension when it's short.
+fn shorten(filename: &str, max_len: usize) -> String {
+    const ELLIPSIS: char = '…';
+    if filename.chars().count() <= max_len {
+        return filename.to_owned();
+    }
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .filter(|extension| extension.chars().count() < 8)
+        .map(|extension| format!(".{extension}"))
+        .unwrap_or_default();
+    let kept = max_len.saturating_sub(extension.chars().count() + 1);
+    let mut result = filename.chars().take(kept).collect::<String>();
+    result.push(ELLIPSIS);
+    if kept > 0 {
+        result.push_str(&extension);
+    }
+    result
+}
+
+/// Formats a duration as a relative age, e.g. `3 days ago`.
+fn relative_age(age: Duration) -> String {
+    const UNITS: &[(&str, u64)] = &[
+        ("year", 365 * 24 * 60 * 60),
+        ("month", 30 * 24 * 60 * 60),
+        ("day", 24 * 60 * 60),
+        ("hour", 60 * 60),
+        ("minute", 60),
+    ];
+    let seconds = age.as_secs();
+    UNITS.iter().find(|(_, unit)| seconds >= *unit).map_or_else(
+        || "just now".to_owned(),
+        |(name, unit)| match seconds / unit {
+            1 => format!("1 {name} ago"),
+            count => format!("{count} {name}s ago"),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case::test_case("Show.S01E01.MKV", Category::Video; "video")]
+    #[test_case::test_case("ubuntu-24.04-desktop-amd64.iso", Category::DiskImage; "disk image")]
+    #[test_case::test_case("album.flac", Category::Audio; "audio")]
+    #[test_case::test_case("README", Category::Other; "without extension")]
+    #[test_case::test_case("file.xyz", Category::Other; "unknown extension")]
+    fn should_guess_category(filename: &str, expected: Category) {
+        assert_eq!(Category::from_filename(filename), expected);
+    }
+
+    #[test_case::test_case("short.mkv", 20, "short.mkv"; "short enough")]
+    #[test_case::test_case("A.Very.Long.Release.Name.mkv", 15, "A.Very.Lon….mkv"; "keeping extension")]
+    #[test_case::test_case("A.Very.Long.Release.Name", 10, "A.Ve….Name"; "dotted name")]
+    #[test_case::test_case("archive.verylongext", 10, "archive.v…"; "long extension")]
+    #[test_case::test_case("Épisode.Spécial.mkv", 12, "Épisode….mkv"; "multibyte")]
+    fn should_shorten_filename(filename: &str, max_len: usize, expected: &str) {
+        assert_eq!(shorten(filename, max_len), expected);
+    }
+
+    #[test_case::test_case(10, "just now"; "seconds")]
+    #[test_case::test_case(60, "1 minute ago"; "one minute")]
+    #[test_case::test_case(3 * 24 * 60 * 60 + 10, "3 days ago"; "days")]
+    #[test_case::test_case(400 * 24 * 60 * 60, "1 year ago"; "year")]
+    fn should_format_relative_age(seconds: u64, expected: &str) {
+        assert_eq!(relative_age(Duration::from_secs(seconds)), expected);
+    }
+
+    #[test]
+    fn should_build_view() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut entry = crate::process::tests::entry(5, 1503238553);
+        entry.bot_speed = 116736;
+        entry.first_seen = Some(now - Duration::from_secs(2 * 60 * 60));
+        let options = ViewOptions::default().max_filename_len(8);
+        assert_eq!(
+            EntryView::at(&entry, &options, now),
+            EntryView {
+                id: "net/bot/#5/file-5.mkv".into(),
+                filename: "fil….mkv".into(),
+                size: "1.4 GiB".into(),
+                speed: "114.0 KiB/s".into(),
+                category: Category::Video,
+                age: Some("2 hours ago".into()),
+                source: "bot@net #chan".into(),
+            }
+        );
+    }
+}
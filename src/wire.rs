@@ -0,0 +1,114 @@
+//! Deserialization helpers shared by the models of the provider responses.
+//!
+//! The listing sites spell the same fields differently (`fname` or `filename`,
+//! `botrec` or `record`...) and encode the same values as strings or numbers.
+//! Instead of a model per provider, the models declare the known spellings with
+//! `#[serde(alias = "...")]` and accept both encodings with the functions of this
+//! module, used with `#[serde(deserialize_with = "...")]`.
+//!
+//! ```
+//! use xdcc_search::wire;
+//!
+//! #[derive(serde::Deserialize)]
+//! struct Row {
+//!     #[serde(alias = "number", deserialize_with = "wire::lenient_string")]
+//!     packnum: String,
+//! }
+//!
+//! let row: Row = serde_json::from_str(r#"{"number": 12}"#).unwrap();
+//! assert_eq!(row.packnum, "12");
+//! ```
+
+use serde::{Deserialize, Deserializer};
+
+/// A scalar value, whatever its JSON type.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    String(String),
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    Bool(bool),
+    Null,
+}
+
+impl From<Scalar> for String {
+    fn from(value: Scalar) -> Self {
+        match value {
+            Scalar::String(value) => value,
+            Scalar::Unsigned(value) => value.to_string(),
+            Scalar::Signed(value) => value.to_string(),
+            Scalar::Float(value) => value.to_string(),
+            Scalar::Bool(value) => value.to_string(),
+            Scalar::Null => String::new(),
+        }
+    }
+}
+
+/// Deserializes a string, a number or a boolean as a string, `null` being
+/// an empty string.
+pub fn lenient_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Scalar::deserialize(deserializer).map(String::from)
+}
+
+/// Deserializes a list of values as strings, see [`lenient_string`].
+pub fn lenient_strings<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    Vec::<Scalar>::deserialize(deserializer)
+        .map(|values| values.into_iter().map(String::from).collect())
+}
+
+/// Deserializes a list of values as strings like [`lenient_strings`], the
+/// unsigned integers being formatted with `format`.
+///
+/// It lets a model decode a textual format strictly (e.g. `#42`) while accepting
+/// the values sent as JSON numbers: a bare number sent as a string isn't
+/// formatted, and is still rejected by the decoding.
+pub fn lenient_numbers<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    format: fn(u64) -> String,
+) -> Result<Vec<String>, D::Error> {
+    Vec::<Scalar>::deserialize(deserializer).map(|values| {
+        values
+            .into_iter()
+            .map(|value| match value {
+                Scalar::Unsigned(number) => format(number),
+                other => String::from(other),
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #[derive(Debug, serde::Deserialize)]
+    struct Column(#[serde(deserialize_with = "super::lenient_strings")] Vec<String>);
+
+    #[test]
+    fn should_accept_any_scalar() {
+        let column: Column = serde_json::from_str(r##"["#1", 2, -3, 1.5, true, null]"##).unwrap();
+        assert_eq!(column.0, ["#1", "2", "-3", "1.5", "true", ""]);
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Packnums(#[serde(deserialize_with = "packnums")] Vec<String>);
+
+    fn packnums<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        super::lenient_numbers(deserializer, |number| format!("#{number}"))
+    }
+
+    #[test]
+    fn should_format_numbers_only() {
+        let column: Packnums = serde_json::from_str(r##"["#1", 2, "3", -4]"##).unwrap();
+        assert_eq!(column.0, ["#1", "#2", "3", "-4"]);
+    }
+
+    #[test]
+    fn should_reject_nested_values() {
+        assert!(serde_json::from_str::<Column>(r#"[["nested"]]"#).is_err());
+    }
+}